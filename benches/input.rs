@@ -0,0 +1,50 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Compare the different input paths of a loaded [`magic::Cookie`]
+//!
+//! ```shell
+//! $ cargo bench --bench input
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SMALL_FILE: &str = "data/tests/rust-logo-128x128-blk.png";
+const LARGE_SIZE: usize = 16 * 1024 * 1024;
+
+fn input(c: &mut Criterion) {
+    let cookie = magic::Cookie::open(magic::cookie::Flags::ERROR).unwrap();
+    let database = ["data/tests/db-images-png"];
+    let cookie = cookie.load(database).unwrap();
+
+    let small = std::fs::read(SMALL_FILE).unwrap();
+
+    // the small PNG padded with zeroes, libmagic only looks at a prefix of it
+    let mut large = small.clone();
+    large.resize(LARGE_SIZE, 0);
+    let large_file = std::env::temp_dir().join("rust-magic-bench-large.png");
+    std::fs::write(&large_file, &large).unwrap();
+
+    let inputs = [
+        ("small", std::path::PathBuf::from(SMALL_FILE), small),
+        ("large", large_file.clone(), large),
+    ];
+
+    let mut group = c.benchmark_group("input");
+    for (size, path, contents) in inputs.iter() {
+        group.throughput(Throughput::Bytes(contents.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("file", size), path, |b, path| {
+            b.iter(|| cookie.file(path).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("buffer", size), contents, |b, contents| {
+            b.iter(|| cookie.buffer(contents).unwrap())
+        });
+    }
+    group.finish();
+
+    std::fs::remove_file(large_file).unwrap();
+}
+
+criterion_group!(benches, input);
+criterion_main!(benches);
@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Compare results of the `magic` crate with the installed `file(1)` CLI
+//!
+//! Analyzes the files given as command line arguments with both and prints divergences.
+//! The first argument is a comma-separated list of `cookie::Flags` names,
+//! which are translated into the equivalent `file` CLI options:
+//! ```shell
+//! $ cargo run --example compare-file-cli -- MIME_TYPE,COMPRESS data/tests/rust-logo-128x128-blk.png
+//! file --brief --no-dereference --uncompress --mime-type
+//! same: data/tests/rust-logo-128x128-blk.png: image/png
+//! ```
+//!
+//! Both use the system's default database, i.e. both honour the "MAGIC" environment variable.
+//! Exits with a non-zero status if any results diverge.
+
+use magic::cookie::Flags;
+
+/// `file` CLI options equivalent to each individual flag
+const CLI_OPTIONS: &[(Flags, &str)] = &[
+    (Flags::DEBUG, "--debug"),
+    (Flags::COMPRESS, "--uncompress"),
+    (Flags::DEVICES, "--special-files"),
+    (Flags::MIME_TYPE, "--mime-type"),
+    (Flags::CONTINUE, "--keep-going"),
+    (Flags::PRESERVE_ATIME, "--preserve-date"),
+    (Flags::RAW, "--raw"),
+    (Flags::MIME_ENCODING, "--mime-encoding"),
+    (Flags::APPLE, "--apple"),
+    (Flags::EXTENSION, "--extension"),
+    (Flags::COMPRESS_TRANSP, "--uncompress-noreport"),
+    (Flags::NO_CHECK_COMPRESS, "--exclude=compress"),
+    (Flags::NO_CHECK_TAR, "--exclude=tar"),
+    (Flags::NO_CHECK_SOFT, "--exclude=soft"),
+    (Flags::NO_CHECK_APPTYPE, "--exclude=apptype"),
+    (Flags::NO_CHECK_ELF, "--exclude=elf"),
+    (Flags::NO_CHECK_TEXT, "--exclude=text"),
+    (Flags::NO_CHECK_CDF, "--exclude=cdf"),
+    (Flags::NO_CHECK_CSV, "--exclude=csv"),
+    (Flags::NO_CHECK_TOKENS, "--exclude=tokens"),
+    (Flags::NO_CHECK_ENCODING, "--exclude=encoding"),
+    (Flags::NO_CHECK_JSON, "--exclude=json"),
+];
+
+/// Flags without an equivalent `file` CLI option, since they only affect error reporting
+const NO_CLI_OPTION: Flags = Flags::ERROR.union(Flags::CHECK);
+
+fn cli_options(flags: Flags) -> Vec<&'static str> {
+    let mut options = vec!["--brief"];
+
+    // `file` might follow symlinks by default, depending on how it was built and `POSIXLY_CORRECT`
+    options.push(match flags.contains(Flags::SYMLINK) {
+        true => "--dereference",
+        false => "--no-dereference",
+    });
+
+    options.extend(
+        CLI_OPTIONS
+            .iter()
+            .filter(|(flag, _)| flags.contains(*flag))
+            .map(|(_, option)| *option),
+    );
+
+    options
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args_os().skip(1);
+
+    let flag_names = args
+        .next()
+        .expect("Comma-separated flag names as first command line argument")
+        .into_string()
+        .expect("Flag names are valid UTF-8");
+    let flags = flag_names
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| Flags::from_name(name.trim()).ok_or(format!("unknown flag {}", name)))
+        .collect::<Result<Flags, _>>()?;
+
+    if flags.intersects(NO_CLI_OPTION) {
+        eprintln!(
+            "no equivalent `file` CLI option for {}",
+            flags.intersection(NO_CLI_OPTION)
+        );
+    }
+
+    let options = cli_options(flags);
+    println!("file {}", options.join(" "));
+
+    let cookie = magic::Cookie::open(flags)?;
+    let cookie = cookie.load(magic::cookie::DatabasePaths::default())?;
+
+    let mut diverged = false;
+    for file in args {
+        let path = std::path::Path::new(&file);
+
+        let crate_result = cookie.file(path).map_err(|err| err.to_string());
+
+        let output = std::process::Command::new("file")
+            .args(&options)
+            .arg("--")
+            .arg(path)
+            .output()?;
+        let cli_result = match output.status.success() {
+            true => Ok(String::from_utf8_lossy(&output.stdout)
+                .trim_end_matches('\n')
+                .to_string()),
+            false => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+        };
+
+        match (crate_result, cli_result) {
+            (Ok(crate_result), Ok(cli_result)) if crate_result == cli_result => {
+                println!("same: {}: {}", path.display(), crate_result);
+            }
+            (crate_result, cli_result) => {
+                diverged = true;
+                println!("diverged: {}", path.display());
+                println!("  crate: {:?}", crate_result);
+                println!("  file:  {:?}", cli_result);
+            }
+        }
+    }
+
+    if diverged {
+        std::process::exit(1);
+    }
+    Ok(())
+}
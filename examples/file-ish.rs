@@ -11,21 +11,31 @@
 //! $ cargo run --example file-ish -- data/tests/rust-logo-128x128-blk.png
 //! PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced
 //! ```
+//!
+//! Like `file -`, an argument of `-` analyzes the standard input:
+//! ```shell
+//! $ cat data/tests/rust-logo-128x128-blk.png | cargo run --example file-ish -- -
+//! PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced
+//! ```
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // open a new configuration with flags
     let cookie = magic::Cookie::open(magic::cookie::Flags::ERROR)?;
 
     // load the system's default database
-    let database = &Default::default();
+    let database = magic::cookie::DatabasePaths::default();
     let cookie = cookie.load(database)?;
 
     let file = std::env::args_os()
         .nth(1)
         .expect("One command line argument");
 
-    // analyze the file
-    println!("{}", cookie.file(file)?);
+    // analyze the file, or the standard input
+    if file == "-" {
+        println!("{}", cookie.stdin()?);
+    } else {
+        println!("{}", cookie.file(file)?);
+    }
 
     Ok(())
 }
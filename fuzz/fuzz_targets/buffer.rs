@@ -0,0 +1,28 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Fuzz [`magic::Cookie::buffer()`] with arbitrary bytes
+//!
+//! Uses the test databases instead of the system's default database,
+//! so findings are reproducible regardless of the installed `libmagic` database.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+thread_local! {
+    static COOKIE: magic::Cookie<magic::cookie::Load> = {
+        let cookie = magic::Cookie::open(magic::cookie::Flags::ERROR).unwrap();
+        let database = [
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../data/tests/db-images-png"),
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../data/tests/db-python"),
+        ];
+        cookie.load(database).unwrap()
+    };
+}
+
+fuzz_target!(|data: &[u8]| {
+    COOKIE.with(|cookie| {
+        let _ = cookie.buffer(data);
+    });
+});
@@ -0,0 +1,19 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Fuzz the [`magic::cookie::DatabasePaths`] constructors with arbitrary paths
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use magic::cookie::DatabasePaths;
+use std::convert::TryFrom;
+
+fuzz_target!(|paths: Vec<String>| {
+    let _ = DatabasePaths::new(&paths);
+    let _ = DatabasePaths::try_from(paths.as_slice());
+
+    if let Some(path) = paths.first() {
+        let _ = DatabasePaths::try_from(path.as_str());
+    }
+});
@@ -0,0 +1,202 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Classification of request payloads in the [`actix-web` web framework](https://crates.io/crates/actix-web)
+//!
+//! [`Classify`] is a middleware that analyzes the payload of each request with a [`SharedCookie`]
+//! and adds the detected [`Classification`] to the request extensions, where handlers get it with
+//! [`web::ReqData`](::actix_web::web::ReqData). The payload is passed on unchanged.
+//! Empty payloads are not analyzed, their classification is unknown.
+//!
+//! The payload is read completely, up to the limit of the [`PayloadConfig`](::actix_web::web::PayloadConfig)
+//! of the app, which is 256 KiB by default. The analysis runs on the blocking thread pool of `actix-web`.
+//!
+//! Requires the `actix-web` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use actix_web::{web, App};
+//! use magic::actix_web::{Classification, Classify};
+//! use magic::sync::SharedCookie;
+//!
+//! let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+//! let cookie = SharedCookie::new(cookie);
+//!
+//! let app = App::new()
+//!     .route(
+//!         "/upload",
+//!         web::post().to(|classification: web::ReqData<Classification>| async move {
+//!             match classification.mime() {
+//!                 Some(mime) => format!("received {}", mime.essence()),
+//!                 None => "received unknown content".to_string(),
+//!             }
+//!         }),
+//!     )
+//!     .wrap(Classify::new(cookie));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+
+use ::actix_web::dev::{
+    forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform,
+};
+use ::actix_web::web::{self, Bytes};
+use ::actix_web::{Error, HttpMessage};
+
+use crate::media_type::{Mime, MimeError};
+use crate::sync::SharedCookie;
+
+/// Result of [`Classify`] in the request extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classification {
+    mime: Option<Mime>,
+}
+
+impl Classification {
+    /// Returns the MIME type and encoding of the payload, see [`Cookie::mime()`](crate::Cookie::mime)
+    ///
+    /// Returns `None` if the payload is empty or `libmagic` returned no valid media type for it.
+    pub fn mime(&self) -> Option<&Mime> {
+        self.mime.as_ref()
+    }
+
+    /// Returns the MIME type and encoding of the payload, consuming the classification
+    ///
+    /// See [`mime()`](Classification::mime).
+    pub fn into_mime(self) -> Option<Mime> {
+        self.mime
+    }
+}
+
+/// Middleware that classifies request payloads, see mod [`actix_web`](crate::actix_web)
+///
+/// Clones share the same cookie.
+#[derive(Debug, Clone)]
+pub struct Classify {
+    cookie: SharedCookie,
+}
+
+impl Classify {
+    /// Classifies payloads with the `cookie`
+    ///
+    /// The flags of the cookie do not matter, see [`Cookie::mime()`](crate::Cookie::mime).
+    pub fn new<C: Into<SharedCookie>>(cookie: C) -> Self {
+        Self {
+            cookie: cookie.into(),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for Classify
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ClassifyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ClassifyMiddleware {
+            service: Rc::new(service),
+            cookie: self.cookie.clone(),
+        }))
+    }
+}
+
+/// Service of the [`Classify`] middleware
+#[derive(Debug)]
+pub struct ClassifyMiddleware<S> {
+    service: Rc<S>,
+    cookie: SharedCookie,
+}
+
+impl<S, B> Service<ServiceRequest> for ClassifyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    #[allow(clippy::type_complexity)]
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut request: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let cookie = self.cookie.clone();
+        Box::pin(async move {
+            let bytes = request.extract::<Bytes>().await?;
+
+            let mime = if bytes.is_empty() {
+                None
+            } else {
+                let buffer = bytes.clone();
+                match web::block(move || cookie.lock().mime_buffer(&buffer)).await? {
+                    Ok(mime) => Some(mime),
+                    Err(MimeError::Invalid(_)) => None,
+                    Err(err) => return Err(::actix_web::error::ErrorInternalServerError(err)),
+                }
+            };
+            request.extensions_mut().insert(Classification { mime });
+
+            request.set_payload(Payload::from(bytes));
+            service.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::actix_web::http::StatusCode;
+    use ::actix_web::{test, web, App};
+
+    use super::{Classification, Classify};
+    use crate::cookie::Flags;
+    use crate::sync::SharedCookie;
+    use crate::Cookie;
+
+    #[test]
+    fn classify() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+
+        ::actix_web::rt::System::new().block_on(async {
+            let app = test::init_service(
+                App::new()
+                    .route(
+                        "/",
+                        web::post().to(|classification: web::ReqData<Classification>| async move {
+                            match classification.mime() {
+                                Some(mime) => mime.essence(),
+                                None => "unknown".to_string(),
+                            }
+                        }),
+                    )
+                    .wrap(Classify::new(SharedCookie::new(cookie))),
+            )
+            .await;
+
+            let request = test::TestRequest::post().set_payload(png).to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(&test::read_body(response).await[..], b"image/png");
+
+            // empty payloads are not analyzed
+            let request = test::TestRequest::post().to_request();
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(&test::read_body(response).await[..], b"unknown");
+        });
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! All kinds of results in one call
+//!
+//! `libmagic` returns one kind of result per analysis, selected by the flags.
+//! [`Cookie::analyze()`](Cookie::analyze) runs one [query](crate::query) per kind of result
+//! and collects them in an [`Analysis`], restoring the flags of the cookie afterwards.
+
+use std::path::Path;
+
+use crate::cookie::Load;
+use crate::query::{AppleCodes, QueryError};
+use crate::Cookie;
+
+/// Results of [`Cookie::analyze()`](Cookie::analyze) and [`Cookie::analyze_buffer()`](Cookie::analyze_buffer)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
+pub struct Analysis {
+    description: String,
+    mime_type: String,
+    mime_encoding: String,
+    extensions: Vec<String>,
+    apple: AppleCodes,
+}
+
+impl Analysis {
+    /// Returns the textual description, see [`Query::description()`](crate::query::Query::description)
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the MIME type, see [`Query::mime()`](crate::query::Query::mime)
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// Returns the MIME encoding, see [`Query::mime_encoding()`](crate::query::Query::mime_encoding)
+    pub fn mime_encoding(&self) -> &str {
+        &self.mime_encoding
+    }
+
+    /// Returns the file extensions, see [`Query::extensions()`](crate::query::Query::extensions)
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// Returns the Apple creator and type codes, see [`Query::apple()`](crate::query::Query::apple)
+    pub fn apple(&self) -> &AppleCodes {
+        &self.apple
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns all kinds of results for the contents of the file `filename`
+    ///
+    /// The file is analyzed once per kind of result, so it should not change in the meantime.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+    ///
+    /// let analysis = cookie.analyze("data/tests/rust-logo-128x128-blk.png")?;
+    /// assert_eq!(analysis.mime_type(), "image/png");
+    /// assert_eq!(analysis.mime_encoding(), "binary");
+    /// assert_eq!(analysis.extensions(), ["png"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_file()`](crate::query::Query::of_file).
+    pub fn analyze<P: AsRef<Path>>(&self, filename: P) -> Result<Analysis, QueryError> {
+        let filename = filename.as_ref();
+        Ok(Analysis {
+            description: self.query().of_file(filename)?,
+            mime_type: self.query().mime().of_file(filename)?,
+            mime_encoding: self.query().mime_encoding().of_file(filename)?,
+            extensions: self.query().extensions().of_file(filename)?,
+            apple: self.query().apple().of_file(filename)?,
+        })
+    }
+
+    /// Returns all kinds of results for the contents of the `buffer`
+    ///
+    /// See [`analyze()`](Cookie::analyze).
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_buffer()`](crate::query::Query::of_buffer).
+    pub fn analyze_buffer(&self, buffer: &[u8]) -> Result<Analysis, QueryError> {
+        Ok(Analysis {
+            description: self.query().of_buffer(buffer)?,
+            mime_type: self.query().mime().of_buffer(buffer)?,
+            mime_encoding: self.query().mime_encoding().of_buffer(buffer)?,
+            extensions: self.query().extensions().of_buffer(buffer)?,
+            apple: self.query().apple().of_buffer(buffer)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn analyze() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let analysis = cookie.analyze(path).unwrap();
+        assert_eq!(
+            analysis.description(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(analysis.mime_type(), "image/png");
+        assert_eq!(analysis.mime_encoding(), "binary");
+        assert_eq!(analysis.extensions(), ["png"]);
+
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(cookie.analyze_buffer(&data).unwrap(), analysis);
+        assert_eq!(cookie.flags(), Flags::ERROR);
+
+        assert!(cookie.analyze("data/tests/doesntexist.png").is_err());
+    }
+}
@@ -0,0 +1,353 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of archive members
+//!
+//! `libmagic` analyzes an archive as a whole, e.g. "Zip archive data".
+//! The functions in this module instead iterate the archive's members
+//! and analyze each member's content with [`Cookie::buffer()`](Cookie::buffer), without unpacking them to disk.
+//!
+//! Each archive format requires its feature:
+//! - `zip` for [`scan_zip()`] via the [`zip` crate](https://crates.io/crates/zip)
+//! - `tar` for [`scan_tar()`] via the [`tar` crate](https://crates.io/crates/tar)
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use std::io::Read;
+
+/// Maximum number of bytes per member that are analyzed
+///
+/// This bounds the memory usage for large or maliciously compressed members.
+pub const ENTRY_LIMIT: usize = 1024 * 1024;
+
+/// Analysis result of a single archive member
+#[derive(Debug)]
+pub struct Entry {
+    name: String,
+    size: u64,
+    result: Result<String, EntryError>,
+}
+
+impl Entry {
+    /// Returns the member's path within the archive
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the member's uncompressed size as stored in the archive
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the textual result of the analysis of the member's content
+    ///
+    /// Members that can not be extracted or read have an error result, the other members are still analyzed.
+    pub fn result(&self) -> &Result<String, EntryError> {
+        &self.result
+    }
+}
+
+/// Error of a single archive member, see [`Entry::result()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum EntryError {
+    /// Reading the member's content failed
+    #[error("could not read archive member")]
+    Read(#[source] std::io::Error),
+    /// The ZIP member can not be extracted, e.g. due to an unsupported compression method
+    #[cfg(feature = "zip")]
+    #[error("could not extract ZIP member")]
+    Zip(#[source] zip::result::ZipError),
+    /// Analyzing the member's content failed
+    #[error("could not analyze archive member")]
+    Analyze(#[source] Error),
+}
+
+/// Error within [`scan_zip()`] or [`scan_tar()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// Reading the archive failed
+    ///
+    /// For TAR archives, this also means the archive is invalid.
+    #[error("could not read archive")]
+    Read(#[source] std::io::Error),
+    /// The ZIP archive is invalid
+    #[cfg(feature = "zip")]
+    #[error("invalid ZIP archive")]
+    Zip(#[source] zip::result::ZipError),
+}
+
+fn analyze_entry<R: Read>(cookie: &Cookie<Load>, name: String, size: u64, reader: R) -> Entry {
+    let mut content = Vec::new();
+    let result = match reader.take(ENTRY_LIMIT as u64).read_to_end(&mut content) {
+        Ok(_) => cookie.buffer(&content).map_err(EntryError::Analyze),
+        Err(err) => Err(EntryError::Read(err)),
+    };
+
+    Entry { name, size, result }
+}
+
+/// Analyzes the content of each file member of the ZIP archive `reader`
+///
+/// Directory members are skipped. Up to [`ENTRY_LIMIT`] bytes of each member are analyzed.
+///
+/// This is also useful for ZIP-based formats such as Office Open XML (docx, …), APK and JAR.
+///
+/// Requires the `zip` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+///
+/// let archive = std::fs::File::open("upload.zip")?;
+/// for entry in magic::archive::scan_zip(archive, &cookie)? {
+///     println!("{}: {:?}", entry.name(), entry.result());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the archive is invalid, an [`ArchiveError::Zip`] will be returned.
+///
+/// Members that can not be extracted, e.g. due to an unsupported compression method, or read
+/// do not abort the scan but have an [`EntryError`] result, see [`Entry::result()`].
+/// A member whose header can not be read has an empty name.
+#[cfg(feature = "zip")]
+pub fn scan_zip<R: Read + std::io::Seek>(
+    reader: R,
+    cookie: &Cookie<Load>,
+) -> Result<Vec<Entry>, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(reader).map_err(ArchiveError::Zip)?;
+
+    // the number of members is read from the archive, so it is not trusted for preallocation
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        // raw members are not decompressed, so their name is known even if they can not be extracted
+        let (name, size) = match archive.by_index_raw(index) {
+            Ok(member) if member.is_dir() => continue,
+            Ok(member) => (member.name().to_string(), member.size()),
+            Err(err) => {
+                entries.push(Entry {
+                    name: String::new(),
+                    size: 0,
+                    result: Err(EntryError::Zip(err)),
+                });
+                continue;
+            }
+        };
+
+        let entry = match archive.by_index(index) {
+            Ok(member) => analyze_entry(cookie, name, size, member),
+            Err(err) => Entry {
+                name,
+                size,
+                result: Err(EntryError::Zip(err)),
+            },
+        };
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// Analyzes the content of each regular file member of the TAR archive `reader`
+///
+/// Other members such as directories and links are skipped. Up to [`ENTRY_LIMIT`] bytes of each member are analyzed.
+///
+/// The archive is read as a stream, i.e. it does not need to be seekable.
+/// If the archive itself is compressed in a format supported by [`decompress`](crate::decompress),
+/// e.g. a "tar.gz" with the `gzip` feature, it is transparently decompressed.
+///
+/// Requires the `tar` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+///
+/// let archive = std::fs::File::open("backup.tar")?;
+/// for entry in magic::archive::scan_tar(archive, &cookie)? {
+///     println!("{}: {:?}", entry.name(), entry.result());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the archive is invalid or reading it failed, an [`ArchiveError::Read`] will be returned.
+///
+/// A member whose content can not be read has an [`EntryError::Read`] result, see [`Entry::result()`].
+/// Since the archive is read as a stream, this usually also fails reading the next member.
+#[cfg(feature = "tar")]
+pub fn scan_tar<R: Read>(reader: R, cookie: &Cookie<Load>) -> Result<Vec<Entry>, ArchiveError> {
+    #[cfg(any(feature = "gzip", feature = "xz", feature = "zstd"))]
+    {
+        let mut reader = reader;
+        let mut header = Vec::with_capacity(8);
+        reader
+            .by_ref()
+            .take(8)
+            .read_to_end(&mut header)
+            .map_err(ArchiveError::Read)?;
+
+        let compression = crate::decompress::Compression::detect(&header);
+        let reader = std::io::Cursor::new(header).chain(reader);
+        match compression {
+            Some(compression) => {
+                let decoder = compression.decoder(reader).map_err(ArchiveError::Read)?;
+                scan_tar_members(decoder, cookie)
+            }
+            None => scan_tar_members(reader, cookie),
+        }
+    }
+    #[cfg(not(any(feature = "gzip", feature = "xz", feature = "zstd")))]
+    scan_tar_members(reader, cookie)
+}
+
+#[cfg(feature = "tar")]
+fn scan_tar_members<R: Read>(reader: R, cookie: &Cookie<Load>) -> Result<Vec<Entry>, ArchiveError> {
+    let mut archive = tar::Archive::new(reader);
+
+    let mut entries = Vec::new();
+    for member in archive.entries().map_err(ArchiveError::Read)? {
+        let member = member.map_err(ArchiveError::Read)?;
+        if !member.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = member
+            .path()
+            .map_err(ArchiveError::Read)?
+            .to_string_lossy()
+            .into_owned();
+        let size = member.size();
+        entries.push(analyze_entry(cookie, name, size, member));
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "zip")]
+    #[test]
+    fn scan_zip() {
+        use crate::cookie::Flags;
+        use std::io::Write;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png", "data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        archive.add_directory("images/", options).unwrap();
+        archive.start_file("images/logo.png", options).unwrap();
+        archive
+            .write_all(&std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap())
+            .unwrap();
+        archive.start_file("hello.py", options).unwrap();
+        archive
+            .write_all(b"#!/usr/bin/env python\nprint('Hello, world!')")
+            .unwrap();
+        let archive = archive.finish().unwrap();
+
+        let entries = super::scan_zip(archive, &cookie).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name(), "images/logo.png");
+        assert_eq!(
+            entries[0].result().as_ref().unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(entries[1].name(), "hello.py");
+        assert_eq!(
+            entries[1].result().as_ref().unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn scan_zip_unsupported() {
+        use crate::cookie::Flags;
+        use std::io::Write;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-python"]).unwrap();
+
+        let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        archive.start_file("broken.bin", options).unwrap();
+        archive.write_all(b"not really compressed").unwrap();
+        archive.start_file("hello.py", options).unwrap();
+        archive
+            .write_all(b"#!/usr/bin/env python\nprint('Hello, world!')")
+            .unwrap();
+        let mut archive = archive.finish().unwrap().into_inner();
+
+        // claim the unsupported compression method 12 (bzip2) for the first member
+        // in its local file header and its central directory header
+        let local = archive.windows(4).position(|w| w == b"PK\x03\x04").unwrap();
+        archive[local + 8] = 12;
+        let central = archive.windows(4).position(|w| w == b"PK\x01\x02").unwrap();
+        archive[central + 10] = 12;
+
+        let entries = super::scan_zip(std::io::Cursor::new(archive), &cookie).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name(), "broken.bin");
+        assert!(matches!(
+            entries[0].result(),
+            Err(super::EntryError::Zip(_))
+        ));
+        assert_eq!(entries[1].name(), "hello.py");
+        assert_eq!(
+            entries[1].result().as_ref().unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn scan_tar() {
+        use crate::cookie::Flags;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png", "data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let mut archive = tar::Builder::new(Vec::new());
+        archive
+            .append_path_with_name("data/tests/rust-logo-128x128-blk.png", "images/logo.png")
+            .unwrap();
+        let script = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(script.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive
+            .append_data(&mut header, "hello.py", &script[..])
+            .unwrap();
+        let archive = archive.into_inner().unwrap();
+
+        let entries = super::scan_tar(archive.as_slice(), &cookie).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name(), "images/logo.png");
+        assert_eq!(
+            entries[0].result().as_ref().unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(entries[1].name(), "hello.py");
+        assert_eq!(
+            entries[1].result().as_ref().unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+}
@@ -0,0 +1,312 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Validation of uploads in the [`axum` web framework](https://crates.io/crates/axum)
+//!
+//! The content type that clients declare for uploads can not be trusted.
+//! [`check_upload()`] is a middleware that analyzes the request body with a cookie of a shared [`CookiePool`]
+//! and rejects requests whose detected MIME type is not in the [`Allowlist`] with an [`UploadRejection`].
+//! The allowlist is a [`ContentPolicy`], which can also limit the size or allow unidentified content.
+//! Accepted requests are passed on with the [`Detected`] MIME type in the request extensions.
+//!
+//! For multipart requests, check the bytes of each field with [`Allowlist::check()`] in the handler instead.
+//!
+//! The analysis runs on the blocking thread pool of `tokio`, see mod [`tokio`](crate::tokio).
+//! Requires the `axum` and `tokio` features.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::sync::Arc;
+//!
+//! use axum::{routing::post, Extension, Router};
+//! use magic::axum::{check_upload, Allowlist, Detected};
+//! use magic::sync::CookiePool;
+//!
+//! let pool = CookiePool::new(4, Default::default(), &Default::default())?;
+//! let allowlist = Allowlist::new(Arc::new(pool), ["image/png", "image/jpeg"]);
+//!
+//! let app: Router = Router::new()
+//!     .route(
+//!         "/avatar",
+//!         post(|Extension(detected): Extension<Detected>| async move {
+//!             format!("stored {}", detected.mime_type())
+//!         }),
+//!     )
+//!     .layer(axum::middleware::from_fn_with_state(allowlist, check_upload));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use ::axum::body::{Body, Bytes};
+use ::axum::extract::{Request, State};
+use ::axum::http::StatusCode;
+use ::axum::middleware::Next;
+use ::axum::response::{IntoResponse, Response};
+
+use crate::policy::{CheckError, ContentPolicy, Violation};
+use crate::sync::CookiePool;
+
+/// Default of [`Allowlist::body_limit()`], 2 MiB like the default body limit of `axum`
+pub const DEFAULT_BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Rejection of [`check_upload()`] and error of [`Allowlist::check()`]
+///
+/// The response has status `400 Bad Request` if the body could not be read,
+/// `413 Payload Too Large` or `415 Unsupported Media Type` if the content violates the allowlist
+/// and `500 Internal Server Error` otherwise.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum UploadRejection {
+    /// Reading the request body failed, e.g. because it exceeds the body limit
+    #[error("could not read request body")]
+    Body(#[source] ::axum::Error),
+    /// The content violates the allowlist, or analyzing it failed
+    #[error("could not check content")]
+    Check(#[source] CheckError),
+    /// The blocking analysis was cancelled, e.g. because the runtime shuts down
+    #[error("content analysis was cancelled")]
+    Cancelled(#[source] ::tokio::task::JoinError),
+}
+
+impl UploadRejection {
+    /// Returns the violation if the content was rejected by the allowlist, `None` if the check itself failed
+    pub fn violation(&self) -> Option<&Violation> {
+        match self {
+            Self::Check(err) => err.violation(),
+            _ => None,
+        }
+    }
+
+    /// Returns the status code of the response
+    pub fn status(&self) -> StatusCode {
+        match (self, self.violation()) {
+            (Self::Body(_), _) => StatusCode::BAD_REQUEST,
+            (_, Some(Violation::TooLarge { .. })) => StatusCode::PAYLOAD_TOO_LARGE,
+            (_, Some(_)) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for UploadRejection {
+    /// Responds with the [`status()`](UploadRejection::status) and the violation, or this error otherwise
+    fn into_response(self) -> Response {
+        let message = match self.violation() {
+            Some(violation) => violation.to_string(),
+            None => self.to_string(),
+        };
+        (self.status(), message).into_response()
+    }
+}
+
+/// MIME type of an accepted upload, in the request extensions after [`check_upload()`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Detected {
+    mime_type: String,
+}
+
+impl Detected {
+    /// Returns the normalized MIME type, e.g. `image/png`
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+}
+
+/// [`ContentPolicy`] of uploads and the cookies to detect their MIME types, the state of [`check_upload()`]
+///
+/// Clones share the same cookies and policy.
+#[derive(Debug, Clone)]
+pub struct Allowlist {
+    pool: Arc<CookiePool>,
+    policy: Arc<ContentPolicy>,
+    body_limit: usize,
+}
+
+impl Allowlist {
+    /// Allows the `mime_types` as detected by the cookies of the `pool`
+    ///
+    /// The MIME types are compared case-insensitively, a `type/*` entry allows all subtypes of `type`,
+    /// see [`ContentPolicy::allow()`]. Unidentified content is rejected, see [`with_policy()`](Allowlist::with_policy) otherwise.
+    /// The flags of the cookies do not matter, see [`Cookie::query()`](crate::Cookie::query).
+    pub fn new<I, T>(pool: Arc<CookiePool>, mime_types: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let policy = mime_types
+            .into_iter()
+            .fold(ContentPolicy::new(), |policy, mime_type| {
+                policy.allow(mime_type.as_ref())
+            });
+        Self::with_policy(pool, policy)
+    }
+
+    /// Accepts content that the `policy` accepts, as detected by the cookies of the `pool`
+    ///
+    /// The flags of the cookies do not matter, see [`Cookie::query()`](crate::Cookie::query).
+    pub fn with_policy(pool: Arc<CookiePool>, policy: ContentPolicy) -> Self {
+        Self {
+            pool,
+            policy: Arc::new(policy),
+            body_limit: DEFAULT_BODY_LIMIT,
+        }
+    }
+
+    /// Sets the maximum size of request bodies in bytes that [`check_upload()`] reads
+    ///
+    /// Defaults to [`DEFAULT_BODY_LIMIT`].
+    pub fn body_limit(mut self, limit: usize) -> Self {
+        self.body_limit = limit;
+        self
+    }
+
+    /// Returns the policy that uploads are checked against
+    pub fn policy(&self) -> &ContentPolicy {
+        &self.policy
+    }
+
+    /// Returns whether the MIME type `mime_type` is allowed, see [`ContentPolicy::allows()`]
+    pub fn allows(&self, mime_type: &str) -> bool {
+        self.policy.allows(mime_type)
+    }
+
+    /// Detects the MIME type of the `bytes` and checks them against the policy, see [`ContentPolicy::check_buffer()`]
+    ///
+    /// The analysis runs on the blocking thread pool and waits for a free cookie of the pool.
+    ///
+    /// # Errors
+    ///
+    /// If the content violates the policy or the analysis failed, an [`UploadRejection::Check`] will be returned.
+    ///
+    /// If the analysis was cancelled, an [`UploadRejection::Cancelled`] will be returned.
+    pub async fn check(&self, bytes: Bytes) -> Result<Detected, UploadRejection> {
+        let pool = Arc::clone(&self.pool);
+        let policy = Arc::clone(&self.policy);
+        let analysis = ::tokio::task::spawn_blocking(move || {
+            let cookie = pool.get();
+            policy.check_buffer(&cookie, &bytes[..])
+        });
+        match analysis.await {
+            Ok(result) => result
+                .map(|mime_type| Detected { mime_type })
+                .map_err(UploadRejection::Check),
+            Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            Err(err) => Err(UploadRejection::Cancelled(err)),
+        }
+    }
+}
+
+/// Middleware that rejects requests whose body is not of an allowed MIME type
+///
+/// Use it with [`axum::middleware::from_fn_with_state()`](::axum::middleware::from_fn_with_state), see mod [`axum`](crate::axum).
+/// The body is read completely up to the [body limit](Allowlist::body_limit) and passed on unchanged,
+/// along with the [`Detected`] MIME type in the request extensions.
+///
+/// # Errors
+///
+/// If the body can not be read, an [`UploadRejection::Body`] will be returned.
+///
+/// Otherwise see [`Allowlist::check()`].
+pub async fn check_upload(
+    State(allowlist): State<Allowlist>,
+    request: Request,
+    next: Next,
+) -> Result<Response, UploadRejection> {
+    let (mut parts, body) = request.into_parts();
+    let bytes = ::axum::body::to_bytes(body, allowlist.body_limit)
+        .await
+        .map_err(UploadRejection::Body)?;
+
+    let detected = allowlist.check(bytes.clone()).await?;
+    parts.extensions.insert(detected);
+
+    let request = Request::from_parts(parts, Body::from(bytes));
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::sync::Arc;
+
+    use ::axum::body::Body;
+    use ::axum::extract::Request;
+    use ::axum::http::StatusCode;
+    use ::axum::{routing::post, Extension, Router};
+    use tower::ServiceExt;
+
+    use super::{check_upload, Allowlist, Detected, UploadRejection};
+    use crate::cookie::Flags;
+    use crate::policy::{CheckError, Violation};
+    use crate::sync::CookiePool;
+
+    fn allowlist() -> Allowlist {
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let pool = CookiePool::new(1, Flags::ERROR, &databases).unwrap();
+        Allowlist::new(Arc::new(pool), ["Image/PNG", "text/*"])
+    }
+
+    #[test]
+    fn allows() {
+        let allowlist = allowlist();
+        assert!(allowlist.allows("image/png; charset=binary"));
+        assert!(allowlist.allows("text/x-python"));
+        assert!(!allowlist.allows("image/gif"));
+        assert!(!allowlist.allows("texts/plain"));
+
+        let rejection = |violation| UploadRejection::Check(CheckError::Violation(violation));
+        assert_eq!(
+            rejection(Violation::Disallowed("image/gif".to_string())).status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+        assert_eq!(
+            rejection(Violation::TooLarge {
+                size: 2,
+                max_size: 1
+            })
+            .status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[test]
+    fn upload() {
+        let app = Router::new()
+            .route(
+                "/",
+                post(|Extension(detected): Extension<Detected>| async move {
+                    detected.mime_type().to_string()
+                }),
+            )
+            .layer(::axum::middleware::from_fn_with_state(
+                allowlist().body_limit(64 * 1024),
+                check_upload,
+            ));
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+
+        let runtime = ::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/")
+                .body(Body::from(png))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let request = Request::builder()
+                .method("POST")
+                .uri("/")
+                .body(Body::from(vec![0x00, 0xff, 0x10, 0x80]))
+                .unwrap();
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        });
+    }
+}
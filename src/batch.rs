@@ -0,0 +1,220 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Batched analysis of many files
+//!
+//! [`Cookie::files()`](Cookie::files) analyzes paths lazily one after another and yields each path with its result,
+//! so scanning thousands of files needs neither a loop with error handling per call nor a C string allocation per path.
+//!
+//! For in-memory payloads, see [`Cookie::buffers()`](Cookie::buffers).
+//!
+//! Scans that keep the results of huge corpora can intern them with [`Files::interned()`],
+//! so that identical results share their memory, see mod [`intern`](crate::intern).
+
+use std::ffi::CString;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::cookie::{Error, Load};
+use crate::intern::Interner;
+use crate::Cookie;
+
+/// Iterator of [`Cookie::files()`](Cookie::files)
+///
+/// Yields each path along with its result of [`Cookie::file()`](Cookie::file).
+#[derive(Debug)]
+pub struct Files<'c, I> {
+    cookie: &'c Cookie<Load>,
+    paths: I,
+    // reused for the C string conversion of every path
+    buffer: Vec<u8>,
+}
+
+impl<'c, I, P> Iterator for Files<'c, I>
+where
+    I: Iterator<Item = P>,
+    P: AsRef<Path>,
+{
+    type Item = (P, Result<String, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = self.paths.next()?;
+
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        buffer.extend_from_slice(&crate::ffi::path_bytes(path.as_ref()));
+        let result = match CString::new(buffer) {
+            Ok(c_string) => {
+                let result = self.cookie.file_cstr(&c_string);
+                self.buffer = c_string.into_bytes();
+                result
+            }
+            Err(err) => {
+                self.buffer = err.into_vec();
+                // let `file()` report the invalid path
+                self.cookie.file(path.as_ref())
+            }
+        };
+        Some((path, result))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.paths.size_hint()
+    }
+}
+
+impl<'c, I> Files<'c, I> {
+    /// Returns an iterator that yields the results interned with `interner`
+    ///
+    /// Identical results of different files are then shared instead of allocated per file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use magic::intern::Interner;
+    ///
+    /// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+    ///
+    /// let mut interner = Interner::new();
+    /// let paths = ["data/tests/rust-logo-128x128-blk.png"; 3];
+    /// let results: Vec<_> = cookie.files(paths).interned(&mut interner).collect();
+    /// assert_eq!(results.len(), 3);
+    /// assert_eq!(interner.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interned(self, interner: &mut Interner) -> InternedFiles<'c, '_, I> {
+        InternedFiles {
+            files: self,
+            interner,
+        }
+    }
+}
+
+/// Iterator of [`Files::interned()`]
+///
+/// Yields each path along with its result of [`Cookie::file()`](Cookie::file), interned with an [`Interner`].
+#[derive(Debug)]
+pub struct InternedFiles<'c, 'i, I> {
+    files: Files<'c, I>,
+    interner: &'i mut Interner,
+}
+
+impl<'c, 'i, I, P> Iterator for InternedFiles<'c, 'i, I>
+where
+    I: Iterator<Item = P>,
+    P: AsRef<Path>,
+{
+    type Item = (P, Result<Arc<str>, Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, result) = self.files.next()?;
+        let interner = &mut *self.interner;
+        Some((path, result.map(|result| interner.intern(&result))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.files.size_hint()
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns an iterator of the textual descriptions of the contents of all files `paths`
+    ///
+    /// Each path is yielded along with its result, which is the same as that of [`file()`](Cookie::file).
+    /// The files are analyzed lazily while iterating, and an error for one file does not stop the iteration.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // open a new cookie with default flags and database
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// let paths = ["data/tests/rust-logo-128x128-blk.png", "Cargo.toml"];
+    /// for (path, result) in cookie.files(paths) {
+    ///     match result {
+    ///         Ok(description) => println!("{}: {}", path, description),
+    ///         Err(err) => eprintln!("{}: {}", path, err),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[doc(alias = "magic_file")]
+    pub fn files<I, P>(&self, paths: I) -> Files<'_, I::IntoIter>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        Files {
+            cookie: self,
+            paths: paths.into_iter(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::cookie::{ErrorKind, Flags};
+    use crate::intern::Interner;
+    use crate::Cookie;
+
+    #[test]
+    fn files() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        let paths = [
+            "data/tests/rust-logo-128x128-blk.png",
+            "data/tests/doesntexist.png",
+            "data/tests/\0.png",
+            "data/tests/rust-logo-128x128-blk.png",
+        ];
+        let results: Vec<_> = cookie.files(paths).collect();
+        assert_eq!(results.len(), 4);
+
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+        assert_eq!(results[0].0, paths[0]);
+        assert_eq!(results[0].1.as_ref().unwrap(), expected);
+        assert_eq!(
+            results[1].1.as_ref().unwrap_err().kind(),
+            ErrorKind::Libmagic
+        );
+        assert_eq!(
+            results[2].1.as_ref().unwrap_err().kind(),
+            ErrorKind::InvalidPath
+        );
+        assert_eq!(results[3].1.as_ref().unwrap(), expected);
+    }
+
+    #[test]
+    fn interned() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        let mut interner = Interner::new();
+        let paths = [
+            "data/tests/rust-logo-128x128-blk.png",
+            "data/tests/doesntexist.png",
+            "data/tests/rust-logo-128x128-blk.png",
+        ];
+        let results: Vec<_> = cookie.files(paths).interned(&mut interner).collect();
+        assert_eq!(results.len(), 3);
+
+        let first = results[0].1.as_ref().unwrap();
+        assert_eq!(
+            &**first,
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert!(results[1].1.is_err());
+        assert!(Arc::ptr_eq(first, results[2].1.as_ref().unwrap()));
+        assert_eq!(interner.len(), 1);
+    }
+}
@@ -0,0 +1,136 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Construction of loaded cookies in one step
+//!
+//! [`Cookie::builder()`](Cookie::builder) collects flags, parameters and databases,
+//! and [`Builder::build()`] opens the cookie, sets the parameters and loads the databases.
+//! This saves the separate [`Cookie::open()`](Cookie::open) and [`Cookie::load()`](Cookie::load) steps
+//! for cookies that do not need the [`Open`] state.
+
+use std::path::{Path, PathBuf};
+
+use crate::cookie::{Flags, Load, LoadError, Open, OpenError, Parameter, ParameterError};
+use crate::Cookie;
+
+/// Error within [`Builder::build()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// Opening the cookie failed
+    #[error("could not open cookie")]
+    Open(#[source] OpenError),
+    /// Setting a parameter failed
+    #[error("could not set parameter")]
+    Parameter(#[source] ParameterError),
+    /// Loading the databases failed
+    #[error("could not load databases")]
+    Load(#[source] LoadError<Open>),
+}
+
+/// Builder of a loaded cookie, see [`Cookie::builder()`](Cookie::builder)
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    flags: Flags,
+    parameters: Vec<(Parameter, usize)>,
+    databases: Vec<PathBuf>,
+}
+
+impl Builder {
+    /// Opens the cookie with `flags`
+    ///
+    /// Defaults to [`Flags::default()`].
+    pub fn flags(mut self, flags: Flags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Sets the `parameter` to `value` before loading the databases
+    ///
+    /// Parameters are set in the order given. See [`Cookie::set_parameter()`](Cookie::set_parameter).
+    pub fn parameter(mut self, parameter: Parameter, value: usize) -> Self {
+        self.parameters.push((parameter, value));
+        self
+    }
+
+    /// Loads the database `path`, in addition to the ones given before
+    ///
+    /// Without any database, the default databases are loaded, see [`DatabasePaths::default()`](crate::cookie::DatabasePaths::default).
+    pub fn database<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.databases.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Opens the cookie, sets the parameters and loads the databases
+    ///
+    /// # Errors
+    ///
+    /// If the cookie can not be opened, a [`BuildError::Open`] will be returned.
+    ///
+    /// If a parameter can not be set, a [`BuildError::Parameter`] will be returned.
+    ///
+    /// If the databases can not be loaded, a [`BuildError::Load`] will be returned.
+    pub fn build(self) -> Result<Cookie<Load>, BuildError> {
+        let cookie = Cookie::open(self.flags).map_err(BuildError::Open)?;
+        for (parameter, value) in self.parameters {
+            cookie
+                .set_parameter(parameter, value)
+                .map_err(BuildError::Parameter)?;
+        }
+        cookie.load(self.databases).map_err(BuildError::Load)
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns a builder of a loaded cookie
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use magic::cookie::{Flags, Parameter};
+    ///
+    /// let cookie = magic::Cookie::builder()
+    ///     .flags(Flags::MIME_TYPE)
+    ///     .parameter(Parameter::BytesMax, 64 * 1024)
+    ///     .database("data/tests/db-images-png")
+    ///     .build()?;
+    ///
+    /// let mime = cookie.file("data/tests/rust-logo-128x128-blk.png")?;
+    /// assert_eq!(mime, "image/png");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BuildError;
+    use crate::cookie::{Flags, Parameter};
+    use crate::Cookie;
+
+    #[test]
+    fn builder() {
+        let cookie = Cookie::builder()
+            .flags(Flags::ERROR)
+            .parameter(Parameter::BytesMax, 1024)
+            .database("data/tests/db-images-png")
+            .build()
+            .unwrap();
+        assert!(cookie.flags().contains(Flags::ERROR));
+        assert_eq!(cookie.parameter(Parameter::BytesMax).unwrap(), 1024);
+        assert_eq!(
+            cookie.file("data/tests/rust-logo-128x128-blk.png").unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+
+        let err = Cookie::builder()
+            .database("data/tests/doesntexist.mgc")
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, BuildError::Load(_)));
+    }
+}
@@ -0,0 +1,219 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Character encodings of text content
+//!
+//! With [`Flags::MIME_ENCODING`](crate::cookie::Flags::MIME_ENCODING), `libmagic` returns the character encoding
+//! of text content, e.g. "utf-8", or "binary" for anything else.
+//! [`Cookie::charset()`](Cookie::charset) returns it as a [`Charset`],
+//! and [`Cookie::is_text()`](Cookie::is_text) answers whether content can be displayed as text at all.
+//! With the `encoding` feature, `Charset::encoding()` returns the encoding to decode the content, see mod `encoding`.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use magic::charset::Charset;
+//!
+//! let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+//!
+//! assert_eq!(cookie.charset_buffer(b"plain text\n")?, Charset::UsAscii);
+//! assert!(cookie.is_text_buffer("gr\u{fc}n\n".as_bytes())?);
+//! assert!(cookie.is_binary("data/tests/rust-logo-128x128-blk.png")?);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use crate::cookie::Load;
+use crate::query::QueryError;
+use crate::Cookie;
+
+/// Character encoding of content, as detected by `libmagic`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Charset {
+    /// Not text, e.g. images or executables
+    Binary,
+    /// 7-bit ASCII text
+    UsAscii,
+    /// UTF-8 text
+    Utf8,
+    /// UTF-16 text in little-endian byte order
+    Utf16Le,
+    /// UTF-16 text in big-endian byte order
+    Utf16Be,
+    /// ISO-8859-1 (Latin-1) text
+    Iso8859_1,
+    /// Text in an 8-bit encoding that `libmagic` can not tell
+    Unknown8Bit,
+    /// EBCDIC text
+    Ebcdic,
+    /// Any other encoding, e.g. "utf-7", lowercase
+    Other(String),
+}
+
+impl Charset {
+    /// Returns the charset of a [`Flags::MIME_ENCODING`](crate::cookie::Flags::MIME_ENCODING) `result`
+    ///
+    /// The `result` is compared case-insensitively.
+    pub fn from_encoding(result: &str) -> Self {
+        match result.trim().to_ascii_lowercase().as_str() {
+            "binary" => Self::Binary,
+            "us-ascii" => Self::UsAscii,
+            "utf-8" => Self::Utf8,
+            "utf-16le" => Self::Utf16Le,
+            "utf-16be" => Self::Utf16Be,
+            "iso-8859-1" => Self::Iso8859_1,
+            "unknown-8bit" => Self::Unknown8Bit,
+            "ebcdic" => Self::Ebcdic,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// Returns the name as used by `libmagic`, e.g. "utf-8"
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Binary => "binary",
+            Self::UsAscii => "us-ascii",
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Iso8859_1 => "iso-8859-1",
+            Self::Unknown8Bit => "unknown-8bit",
+            Self::Ebcdic => "ebcdic",
+            Self::Other(other) => other,
+        }
+    }
+
+    /// Returns whether the content is text in any encoding
+    pub fn is_text(&self) -> bool {
+        !self.is_binary()
+    }
+
+    /// Returns whether the content is not text
+    pub fn is_binary(&self) -> bool {
+        *self == Self::Binary
+    }
+}
+
+impl std::fmt::Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns the character encoding of the contents of the file `filename`
+    ///
+    /// See [`Query::mime_encoding()`](crate::query::Query::mime_encoding).
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_file()`](crate::query::Query::of_file).
+    pub fn charset<P: AsRef<Path>>(&self, filename: P) -> Result<Charset, QueryError> {
+        let result = self.query().mime_encoding().of_file(filename)?;
+        Ok(Charset::from_encoding(&result))
+    }
+
+    /// Returns the character encoding of the contents of the `buffer`
+    ///
+    /// See [`charset()`](Cookie::charset).
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_buffer()`](crate::query::Query::of_buffer).
+    pub fn charset_buffer(&self, buffer: &[u8]) -> Result<Charset, QueryError> {
+        let result = self.query().mime_encoding().of_buffer(buffer)?;
+        Ok(Charset::from_encoding(&result))
+    }
+
+    /// Returns whether the contents of the file `filename` are text, see [`Charset::is_text()`]
+    ///
+    /// # Errors
+    ///
+    /// See [`charset()`](Cookie::charset).
+    pub fn is_text<P: AsRef<Path>>(&self, filename: P) -> Result<bool, QueryError> {
+        Ok(self.charset(filename)?.is_text())
+    }
+
+    /// Returns whether the contents of the file `filename` are not text, see [`Charset::is_binary()`]
+    ///
+    /// # Errors
+    ///
+    /// See [`charset()`](Cookie::charset).
+    pub fn is_binary<P: AsRef<Path>>(&self, filename: P) -> Result<bool, QueryError> {
+        Ok(self.charset(filename)?.is_binary())
+    }
+
+    /// Returns whether the contents of the `buffer` are text, see [`Charset::is_text()`]
+    ///
+    /// # Errors
+    ///
+    /// See [`charset_buffer()`](Cookie::charset_buffer).
+    pub fn is_text_buffer(&self, buffer: &[u8]) -> Result<bool, QueryError> {
+        Ok(self.charset_buffer(buffer)?.is_text())
+    }
+
+    /// Returns whether the contents of the `buffer` are not text, see [`Charset::is_binary()`]
+    ///
+    /// # Errors
+    ///
+    /// See [`charset_buffer()`](Cookie::charset_buffer).
+    pub fn is_binary_buffer(&self, buffer: &[u8]) -> Result<bool, QueryError> {
+        Ok(self.charset_buffer(buffer)?.is_binary())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Charset;
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn from_encoding() {
+        assert_eq!(Charset::from_encoding("binary"), Charset::Binary);
+        assert_eq!(Charset::from_encoding("UTF-8"), Charset::Utf8);
+        assert_eq!(
+            Charset::from_encoding("utf-7"),
+            Charset::Other("utf-7".to_string())
+        );
+        assert_eq!(Charset::Utf16Le.to_string(), "utf-16le");
+        assert!(Charset::Unknown8Bit.is_text());
+        assert!(Charset::Binary.is_binary());
+    }
+
+    #[test]
+    fn charset() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        assert_eq!(
+            cookie
+                .charset("data/tests/rust-logo-128x128-blk.png")
+                .unwrap(),
+            Charset::Binary
+        );
+        assert!(cookie
+            .is_binary("data/tests/rust-logo-128x128-blk.png")
+            .unwrap());
+        assert_eq!(
+            cookie.charset_buffer(b"plain text\n").unwrap(),
+            Charset::UsAscii
+        );
+        assert_eq!(
+            cookie.charset_buffer("gr\u{fc}n\n".as_bytes()).unwrap(),
+            Charset::Utf8
+        );
+        assert!(cookie.is_text_buffer(b"plain text\n").unwrap());
+        assert!(!cookie.is_binary_buffer(b"plain text\n").unwrap());
+        assert!(cookie.is_binary_buffer(&[0x00, 0xff, 0x00, 0xfe]).unwrap());
+        assert!(!cookie
+            .is_text("data/tests/rust-logo-128x128-blk.png")
+            .unwrap());
+    }
+}
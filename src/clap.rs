@@ -0,0 +1,165 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Command line arguments with the [`clap` crate](https://crates.io/crates/clap)
+//!
+//! Tools that let users choose [`Flags`] can parse them with [`FlagsValueParser`] instead of mapping names themselves.
+//! Flags are named like their constants in kebab-case, e.g. `mime-type` for [`Flags::MIME_TYPE`],
+//! and separated by commas, e.g. `mime-type,error,compress`.
+//! Names are case-insensitive and an empty value is [`Flags::empty()`].
+//!
+//! [`Flags`] implement [`ValueParserFactory`], so [`clap::value_parser!`](::clap::value_parser) and
+//! the derive API pick up the parser by themselves.
+//!
+//! Requires the `clap` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use clap::{Arg, Command};
+//! use magic::cookie::Flags;
+//!
+//! let command = Command::new("file").arg(
+//!     Arg::new("flags")
+//!         .long("flags")
+//!         .value_parser(clap::value_parser!(Flags)),
+//! );
+//!
+//! let matches = command.try_get_matches_from(["file", "--flags", "mime-type,error,compress"])?;
+//! assert_eq!(
+//!     matches.get_one::<Flags>("flags"),
+//!     Some(&(Flags::MIME_TYPE | Flags::ERROR | Flags::COMPRESS))
+//! );
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ffi::OsStr;
+
+use ::clap::builder::{TypedValueParser, ValueParserFactory};
+use ::clap::error::ErrorKind;
+use ::clap::{Arg, Command, Error};
+
+use crate::cookie::Flags;
+
+/// Separator of flag names in a value
+const SEPARATOR: char = ',';
+
+/// Parser of comma-separated flag names into [`Flags`], see mod [`clap`](crate::clap)
+///
+/// The parser has no possible values, e.g. for shell completions, since a value may name several flags.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlagsValueParser;
+
+impl FlagsValueParser {
+    /// Creates a parser
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl TypedValueParser for FlagsValueParser {
+    type Value = Flags;
+
+    fn parse_ref(&self, cmd: &Command, arg: Option<&Arg>, value: &OsStr) -> Result<Flags, Error> {
+        let value = value
+            .to_str()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidUtf8).with_cmd(cmd))?;
+
+        let mut flags = Flags::empty();
+        for name in value.split(SEPARATOR).map(str::trim) {
+            if name.is_empty() {
+                continue;
+            }
+            let flag = from_name(name).ok_or_else(|| {
+                let arg = arg.map_or_else(|| "...".to_string(), ToString::to_string);
+                Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "invalid value '{}' for '{}': unknown flag '{}'",
+                        value, arg, name
+                    ),
+                )
+                .with_cmd(cmd)
+            })?;
+            flags |= flag;
+        }
+        Ok(flags)
+    }
+}
+
+impl ValueParserFactory for Flags {
+    type Parser = FlagsValueParser;
+
+    fn value_parser() -> Self::Parser {
+        FlagsValueParser::new()
+    }
+}
+
+/// Returns the flag named `name` in any case, with either dashes or underscores
+fn from_name(name: &str) -> Option<Flags> {
+    Flags::from_name(&name.to_ascii_uppercase().replace('-', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use ::clap::builder::TypedValueParser;
+    use ::clap::error::ErrorKind;
+    use ::clap::{Arg, Command};
+
+    use super::FlagsValueParser;
+    use crate::cookie::Flags;
+
+    #[test]
+    fn parse() {
+        let command = Command::new("file");
+        let parse =
+            |value: &str| FlagsValueParser::new().parse_ref(&command, None, OsStr::new(value));
+
+        assert_eq!(
+            parse("mime-type,error,compress").unwrap(),
+            Flags::MIME_TYPE | Flags::ERROR | Flags::COMPRESS
+        );
+        assert_eq!(
+            parse("No_Check_Builtin, EXTENSION").unwrap(),
+            Flags::NO_CHECK_BUILTIN | Flags::EXTENSION
+        );
+        assert_eq!(parse("").unwrap(), Flags::empty());
+
+        let err = parse("mime-type,mime-typo").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidValue);
+        assert!(err.to_string().contains("mime-typo"));
+    }
+
+    #[test]
+    fn possible_values() {
+        // single names would reject lists of flags in shells that validate completions
+        assert!(FlagsValueParser::new().possible_values().is_none());
+    }
+
+    #[test]
+    fn command() {
+        let command = || {
+            Command::new("file").arg(
+                Arg::new("flags")
+                    .long("flags")
+                    .value_parser(::clap::value_parser!(Flags)),
+            )
+        };
+
+        let matches = command()
+            .try_get_matches_from(["file", "--flags", "mime,continue"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<Flags>("flags"),
+            Some(&(Flags::MIME | Flags::CONTINUE))
+        );
+
+        assert!(command()
+            .try_get_matches_from(["file", "--flags", "mime,contiue"])
+            .is_err());
+    }
+}
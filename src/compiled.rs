@@ -0,0 +1,258 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Headers of compiled ".mgc" databases
+//!
+//! `libmagic` only reports that one of the buffers of [`Cookie::load_buffers()`](crate::Cookie::load_buffers) is invalid,
+//! but not which one. [`Cookie::load_buffers()`](crate::Cookie::load_buffers) therefore checks the [`Header`] of each buffer first,
+//! see [`LoadError::buffer()`](crate::cookie::LoadError::buffer).
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::compiled::{ByteOrder, Header};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let database = std::fs::read("data/tests/db-images-png-precompiled.mgc")?;
+//! let header = Header::parse(&database)?;
+//! assert_eq!(header.version(), 16);
+//! assert_eq!(header.byte_order(), ByteOrder::Little);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Once;
+
+/// Magic number at the start of compiled databases, stored in the byte order of the compiling machine
+pub const MAGIC_NUMBER: u32 = 0xF11E_041C;
+
+/// Byte order of a compiled database
+///
+/// `libmagic` can load databases of either byte order, but ones compiled on a machine with another byte order are swapped while loading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    /// Little-endian, e.g. compiled on x86-64 or AArch64
+    Little,
+    /// Big-endian, e.g. compiled on s390x
+    Big,
+}
+
+impl ByteOrder {
+    /// Returns the byte order of the current machine
+    pub fn native() -> Self {
+        if cfg!(target_endian = "little") {
+            Self::Little
+        } else {
+            Self::Big
+        }
+    }
+}
+
+/// Error of [`Header::parse()`] or of the header check in [`Cookie::load_buffers()`](crate::Cookie::load_buffers)
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HeaderError {
+    /// The buffer is shorter than the header
+    #[error("compiled database of {len} bytes is too short for a header")]
+    Truncated { len: usize },
+    /// The buffer does not start with the [`MAGIC_NUMBER`] in either byte order, i.e. it is no compiled database
+    #[error("compiled database has no magic number")]
+    BadMagic,
+    /// The compiled database has a format version other than the supported one of the `libmagic` in use
+    #[error(
+        "compiled database is version {version} but `libmagic` supports only version {supported}"
+    )]
+    Version { version: u32, supported: u32 },
+}
+
+/// Header of a compiled database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Header {
+    version: u32,
+    byte_order: ByteOrder,
+}
+
+/// Length of the magic number and format version
+const HEADER_LEN: usize = 8;
+
+impl Header {
+    /// Parses the header at the start of the compiled database `buffer`
+    ///
+    /// This does not check whether the format version is supported, see [`supported_version()`].
+    ///
+    /// # Errors
+    ///
+    /// If `buffer` is too short, a [`HeaderError::Truncated`] will be returned.
+    ///
+    /// If `buffer` does not start with the [`MAGIC_NUMBER`], a [`HeaderError::BadMagic`] will be returned.
+    pub fn parse(buffer: &[u8]) -> Result<Self, HeaderError> {
+        if buffer.len() < HEADER_LEN {
+            return Err(HeaderError::Truncated { len: buffer.len() });
+        }
+        let mut magic = [0; 4];
+        magic.copy_from_slice(&buffer[0..4]);
+        let mut version = [0; 4];
+        version.copy_from_slice(&buffer[4..8]);
+
+        if u32::from_le_bytes(magic) == MAGIC_NUMBER {
+            Ok(Self {
+                version: u32::from_le_bytes(version),
+                byte_order: ByteOrder::Little,
+            })
+        } else if u32::from_be_bytes(magic) == MAGIC_NUMBER {
+            Ok(Self {
+                version: u32::from_be_bytes(version),
+                byte_order: ByteOrder::Big,
+            })
+        } else {
+            Err(HeaderError::BadMagic)
+        }
+    }
+
+    /// Returns the format version, e.g. `18` for `libmagic` 5.44
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Returns the byte order of the machine that compiled the database
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+}
+
+/// Value of [`SUPPORTED_VERSION`] while it is unknown
+const UNKNOWN_VERSION: u64 = u64::MAX;
+
+/// Format version returned from [`supported_version()`], or [`UNKNOWN_VERSION`]
+static SUPPORTED_VERSION: AtomicU64 = AtomicU64::new(UNKNOWN_VERSION);
+
+/// Guards the probe of [`supported_version()`], which only runs once per process
+static SUPPORTED_VERSION_PROBE: Once = Once::new();
+
+/// Returns the format version of compiled databases that the `libmagic` in use supports
+///
+/// `libmagic` does not expose this, so it is determined by loading a probe database of an invalid version.
+/// The probe only runs on the first call, later calls return its cached result.
+/// Returns `None` if `libmagic` does not report it as expected.
+pub fn supported_version() -> Option<u32> {
+    SUPPORTED_VERSION_PROBE.call_once(|| {
+        if let Some(version) = probe_supported_version() {
+            SUPPORTED_VERSION.store(u64::from(version), Ordering::Relaxed);
+        }
+    });
+    u32::try_from(SUPPORTED_VERSION.load(Ordering::Relaxed)).ok()
+}
+
+/// Determines the result of [`supported_version()`] by loading a probe database
+fn probe_supported_version() -> Option<u32> {
+    let mut probe = MAGIC_NUMBER.to_ne_bytes().to_vec();
+    probe.extend_from_slice(&0u32.to_ne_bytes());
+
+    let mut cookie = crate::ffi::open(0).ok()?;
+    let res = crate::ffi::load_buffers(&cookie, &[&probe]);
+    crate::ffi::close(&mut cookie);
+
+    // e.g. "File 5.44 supports only version 18 magic files. `buffer' is version 0"
+    let explanation = match res {
+        Err(crate::ffi::CookieError::Libmagic { explanation, .. }) => explanation,
+        _ => return None,
+    };
+    number_after(&explanation.to_string_lossy(), "supports only version ")
+}
+
+/// Returns the number right after the first `pattern` in the `libmagic` error `explanation`
+///
+/// This parses e.g. the versions of "File 5.44 supports only version 18 magic files. `foo.mgc' is version 16".
+pub(crate) fn number_after(explanation: &str, pattern: &str) -> Option<u32> {
+    let start = explanation.find(pattern)? + pattern.len();
+    explanation[start..]
+        .split(|c: char| !c.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Checks the headers of all `buffers`, returning the index and error of the first invalid one
+pub(crate) fn check(buffers: &[&[u8]]) -> Result<(), (usize, HeaderError)> {
+    for (index, buffer) in buffers.iter().enumerate() {
+        let header = Header::parse(buffer).map_err(|err| (index, err))?;
+
+        if let Some(supported) = supported_version() {
+            if header.version() != supported {
+                let err = HeaderError::Version {
+                    version: header.version(),
+                    supported,
+                };
+                return Err((index, err));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, number_after, ByteOrder, Header, HeaderError, MAGIC_NUMBER};
+
+    #[test]
+    fn parse() {
+        let mut buffer = MAGIC_NUMBER.to_be_bytes().to_vec();
+        buffer.extend_from_slice(&18u32.to_be_bytes());
+        let header = Header::parse(&buffer).unwrap();
+        assert_eq!(header.version(), 18);
+        assert_eq!(header.byte_order(), ByteOrder::Big);
+
+        assert_eq!(
+            Header::parse(&buffer[..4]),
+            Err(HeaderError::Truncated { len: 4 })
+        );
+        assert_eq!(
+            Header::parse(b"0\tstring\tfoo\tFoo\n"),
+            Err(HeaderError::BadMagic)
+        );
+    }
+
+    #[test]
+    fn check_index() {
+        let supported = super::supported_version().unwrap();
+        let mut valid = MAGIC_NUMBER.to_ne_bytes().to_vec();
+        valid.extend_from_slice(&supported.to_ne_bytes());
+        let mut outdated = MAGIC_NUMBER.to_ne_bytes().to_vec();
+        outdated.extend_from_slice(&1u32.to_ne_bytes());
+
+        assert_eq!(check(&[&valid, &valid]), Ok(()));
+        assert_eq!(
+            check(&[&valid, &outdated]),
+            Err((
+                1,
+                HeaderError::Version {
+                    version: 1,
+                    supported
+                }
+            ))
+        );
+        assert_eq!(
+            check(&[b"magic", &outdated]),
+            Err((0, HeaderError::Truncated { len: 5 }))
+        );
+    }
+
+    #[test]
+    fn explanation_numbers() {
+        let explanation = "File 5.44 supports only version 18 magic files. `foo.mgc' is version 16";
+        assert_eq!(
+            number_after(explanation, "supports only version "),
+            Some(18)
+        );
+        assert_eq!(number_after(explanation, "' is version "), Some(16));
+        assert_eq!(number_after(explanation, "File "), Some(5));
+        assert_eq!(number_after(explanation, "line "), None);
+    }
+
+    #[test]
+    fn supported_version_cached() {
+        assert_eq!(super::supported_version(), super::supported_version());
+    }
+}
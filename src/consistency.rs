@@ -0,0 +1,232 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Detection of files whose extension does not match their content
+//!
+//! A `.jpg` file that is really a PHP script is a classic way to smuggle code past naive upload filters,
+//! and mislabeled files in a repository can break tools that trust the extension.
+//! [`check()`] compares the extension of a file with what `libmagic` detects for its content
+//! and reports each [`Mismatch`], e.g. for security scanning or CI checks.
+//!
+//! Two kinds of evidence are compared:
+//! - the extensions that the `libmagic` database knows for the content, see [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION)
+//! - the MIME types that common extensions stand for, e.g. `image/jpeg` for `.jpg`, with the detected MIME type
+//!
+//! For declared content types and filenames of uploads, see mod [`spoof`](crate::spoof).
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::consistency::{check_results, Mismatch};
+//!
+//! // results of `Flags::MIME_TYPE` and `Flags::EXTENSION` for a PHP script
+//! let consistency = check_results("avatar.jpg", "text/x-php", "???");
+//! assert!(!consistency.is_consistent());
+//! assert_eq!(consistency.mismatches(), [Mismatch::MimeType]);
+//! ```
+
+use std::path::Path;
+
+use crate::cookie::Load;
+use crate::kind::identified;
+use crate::normalize::{normalize, Normalization};
+use crate::query::QueryError;
+use crate::spoof::{extension_mismatch, parse_extensions};
+use crate::Cookie;
+
+/// MIME types that common extensions stand for
+const EXTENSION_TYPES: &[(&str, &[&str])] = &[
+    ("bmp", &["image/bmp", "image/x-ms-bmp"]),
+    ("gif", &["image/gif"]),
+    ("gz", &["application/gzip", "application/x-gzip"]),
+    ("jpe", &["image/jpeg"]),
+    ("jpeg", &["image/jpeg"]),
+    ("jpg", &["image/jpeg"]),
+    ("mp3", &["audio/mpeg"]),
+    ("mp4", &["video/mp4"]),
+    ("pdf", &["application/pdf"]),
+    ("png", &["image/png"]),
+    ("tif", &["image/tiff"]),
+    ("tiff", &["image/tiff"]),
+    ("webp", &["image/webp"]),
+    ("zip", &["application/zip"]),
+];
+
+/// Kind of disagreement between the extension of a file and its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Mismatch {
+    /// The extension is not one of the extensions that `libmagic` knows for the content
+    ///
+    /// Content without known extensions never mismatches.
+    Extension,
+    /// The extension stands for MIME types of which none was detected
+    ///
+    /// Only common extensions stand for MIME types, and unidentified content never mismatches,
+    /// see [`identified()`](crate::kind::identified).
+    MimeType,
+}
+
+/// Outcome of [`check()`] and [`check_results()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Consistency {
+    extension: Option<String>,
+    mime_type: String,
+    extensions: Vec<String>,
+    mismatches: Vec<Mismatch>,
+}
+
+impl Consistency {
+    /// Returns the extension of the file, `None` if it has none
+    pub fn extension(&self) -> Option<&str> {
+        self.extension.as_deref()
+    }
+
+    /// Returns the detected MIME type, normalized without parameters
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// Returns the extensions that `libmagic` knows for the content
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// Returns all mismatches, empty if the extension matches the content
+    pub fn mismatches(&self) -> &[Mismatch] {
+        &self.mismatches
+    }
+
+    /// Returns whether the extension matches the content
+    ///
+    /// Files without extension are always consistent.
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+/// Compares the extension of `filename` with the results of an analysis of its content
+///
+/// `mime_type` is the result of [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) and
+/// `extension` the result of [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION), e.g. "jpeg/jpg/jpe/jfif".
+/// Extensions are compared case-insensitively.
+pub fn check_results(filename: &str, mime_type: &str, extension: &str) -> Consistency {
+    compare(filename, mime_type, parse_extensions(extension))
+}
+
+fn compare(filename: &str, mime_type: &str, extensions: Vec<String>) -> Consistency {
+    let mime_type = normalize(mime_type, Normalization::MimeType);
+    let actual = crate::spoof::extension(filename).map(str::to_string);
+
+    let mut mismatches = Vec::new();
+    if let Some(actual) = &actual {
+        if extension_mismatch(actual, &extensions) {
+            mismatches.push(Mismatch::Extension);
+        }
+
+        let expected = EXTENSION_TYPES
+            .iter()
+            .find(|(extension, _)| extension.eq_ignore_ascii_case(actual))
+            .map(|(_, mime_types)| *mime_types);
+        if let Some(expected) = expected {
+            if identified(&mime_type).is_some() && !expected.contains(&mime_type.as_str()) {
+                mismatches.push(Mismatch::MimeType);
+            }
+        }
+    }
+
+    Consistency {
+        extension: actual,
+        mime_type,
+        extensions,
+        mismatches,
+    }
+}
+
+/// Analyzes the contents of the file `path` with the `cookie` and compares them with its extension
+///
+/// The flags of the cookie do not matter, see [`Cookie::query()`](Cookie::query).
+/// See [`check_results()`].
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+///
+/// let consistency = magic::consistency::check(&cookie, "data/tests/rust-logo-128x128-blk.png")?;
+/// assert!(consistency.is_consistent());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// See [`Query::of_file()`](crate::query::Query::of_file).
+pub fn check<P: AsRef<Path>>(cookie: &Cookie<Load>, path: P) -> Result<Consistency, QueryError> {
+    let path = path.as_ref();
+    let mime_type = cookie.query().mime().of_file(path)?;
+    let extensions = cookie.query().extensions().of_file(path)?;
+
+    let filename = path
+        .file_name()
+        .map(|filename| filename.to_string_lossy())
+        .unwrap_or_default();
+    Ok(compare(&filename, &mime_type, extensions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, check_results, Mismatch};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn results() {
+        let consistency = check_results("logo.PNG", "image/png; charset=binary", "png");
+        assert!(consistency.is_consistent());
+        assert_eq!(consistency.extension(), Some("PNG"));
+        assert_eq!(consistency.mime_type(), "image/png");
+        assert_eq!(consistency.extensions(), ["png"]);
+
+        assert_eq!(
+            check_results("photo.jpg", "image/png", "png").mismatches(),
+            [Mismatch::Extension, Mismatch::MimeType]
+        );
+        assert_eq!(
+            check_results("shell.jpg", "text/x-php", "???").mismatches(),
+            [Mismatch::MimeType]
+        );
+        assert_eq!(
+            check_results("archive.tar", "image/png", "png").mismatches(),
+            [Mismatch::Extension]
+        );
+
+        assert!(check_results("README", "image/png", "png").is_consistent());
+        assert!(check_results("script.py", "text/x-python", "???").is_consistent());
+        assert!(check_results("broken.jpg", "application/octet-stream", "???").is_consistent());
+    }
+
+    #[test]
+    fn check_file() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let consistency = check(&cookie, path).unwrap();
+        assert!(consistency.is_consistent());
+        assert_eq!(consistency.mime_type(), "image/png");
+
+        let renamed =
+            std::env::temp_dir().join(format!("magic-consistency-{}.jpg", std::process::id()));
+        std::fs::copy(path, &renamed).unwrap();
+        let consistency = check(&cookie, &renamed);
+        std::fs::remove_file(&renamed).unwrap();
+        assert_eq!(
+            consistency.unwrap().mismatches(),
+            [Mismatch::Extension, Mismatch::MimeType]
+        );
+
+        assert!(check(&cookie, "data/tests/doesntexist.png").is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of compressed files with in-process pure-Rust decompression
+//!
+//! [`Flags::COMPRESS`](crate::cookie::Flags::COMPRESS) lets `libmagic` look inside compressed files,
+//! but it might do so by spawning external decompression programs.
+//! That is a security and portability problem, e.g. in sandboxes without those programs.
+//!
+//! [`Cookie::file_decompressed()`](Cookie::file_decompressed) instead decompresses in-process
+//! and analyzes the container and its content separately.
+//!
+//! Each compression format requires its feature:
+//! - `gzip` for gzip via the [`flate2` crate](https://crates.io/crates/flate2)
+//! - `xz` for xz via the [`xz2` crate](https://crates.io/crates/xz2)
+//! - `zstd` for Zstandard via the [`zstd` crate](https://crates.io/crates/zstd)
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use std::io::Read;
+use std::path::Path;
+
+/// Maximum number of decompressed bytes that are analyzed
+///
+/// This bounds the memory usage for e.g. decompression bombs.
+pub const DECOMPRESSED_LIMIT: usize = 1024 * 1024;
+
+/// Compression format detected by [`Cookie::file_decompressed()`](Cookie::file_decompressed)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Compression {
+    /// gzip, requires the `gzip` feature
+    Gzip,
+    /// xz, requires the `xz` feature
+    Xz,
+    /// Zstandard, requires the `zstd` feature
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the compression format of `data` by looking at its leading magic bytes
+    ///
+    /// Only formats whose feature is enabled are detected.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data {
+            #[cfg(feature = "gzip")]
+            [0x1f, 0x8b, ..] => Some(Self::Gzip),
+            #[cfg(feature = "xz")]
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(Self::Xz),
+            #[cfg(feature = "zstd")]
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn decoder<'r, R: Read + 'r>(
+        &self,
+        reader: R,
+    ) -> std::io::Result<Box<dyn Read + 'r>> {
+        match self {
+            #[cfg(feature = "gzip")]
+            Self::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "xz")]
+            Self::Xz => Ok(Box::new(xz2::read::XzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+            #[allow(unreachable_patterns)]
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "compression feature not enabled",
+            )),
+        }
+    }
+}
+
+/// Error within [`Cookie::file_decompressed()`](Cookie::file_decompressed)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum DecompressError {
+    /// Reading or decompressing the file failed
+    #[error("could not read compressed file")]
+    Read(#[source] std::io::Error),
+    /// Analyzing the container or its content failed
+    #[error("could not analyze compressed file")]
+    Analyze(#[source] Error),
+}
+
+/// Result of [`Cookie::file_decompressed()`](Cookie::file_decompressed)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decompressed {
+    container: String,
+    compression: Option<Compression>,
+    content: Option<String>,
+}
+
+impl Decompressed {
+    /// Returns the textual result of the analysis of the file itself, e.g. "gzip compressed data"
+    pub fn container(&self) -> &str {
+        &self.container
+    }
+
+    /// Returns the detected compression format, if any
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Returns the textual result of the analysis of the decompressed content, if the file was compressed
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns textual descriptions of the file `filename` and of its decompressed content
+    ///
+    /// The file itself is analyzed with [`file()`](Cookie::file).
+    /// If it is compressed in one of the enabled formats, up to [`DECOMPRESSED_LIMIT`] bytes
+    /// are decompressed in-process and analyzed with [`buffer()`](Cookie::buffer).
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// let decompressed = cookie.file_decompressed("data/tests/rust-logo-128x128-blk.png")?;
+    /// // not compressed
+    /// assert_eq!(decompressed.content(), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the file could not be read or decompressed, a [`DecompressError::Read`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`DecompressError::Analyze`] will be returned.
+    pub fn file_decompressed<P: AsRef<Path>>(
+        &self,
+        filename: P,
+    ) -> Result<Decompressed, DecompressError> {
+        let filename = filename.as_ref();
+        let container = self.file(filename).map_err(DecompressError::Analyze)?;
+
+        let mut file = std::fs::File::open(filename).map_err(DecompressError::Read)?;
+        let mut header = Vec::with_capacity(8);
+        file.by_ref()
+            .take(8)
+            .read_to_end(&mut header)
+            .map_err(DecompressError::Read)?;
+
+        let compression = Compression::detect(&header);
+        let content = match compression {
+            None => None,
+            Some(compression) => {
+                let compressed = std::io::Cursor::new(header).chain(file);
+                let decoder = compression
+                    .decoder(compressed)
+                    .map_err(DecompressError::Read)?;
+
+                let mut decompressed = Vec::new();
+                decoder
+                    .take(DECOMPRESSED_LIMIT as u64)
+                    .read_to_end(&mut decompressed)
+                    .map_err(DecompressError::Read)?;
+
+                Some(
+                    self.buffer(&decompressed)
+                        .map_err(DecompressError::Analyze)?,
+                )
+            }
+        };
+
+        Ok(Decompressed {
+            container,
+            compression,
+            content,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+
+    #[test]
+    fn detect() {
+        assert_eq!(Compression::detect(b"\x89PNG\r\n\x1a\n"), None);
+        assert_eq!(Compression::detect(b""), None);
+
+        #[cfg(feature = "gzip")]
+        assert_eq!(
+            Compression::detect(b"\x1f\x8b\x08\x00"),
+            Some(Compression::Gzip)
+        );
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            Compression::detect(b"\x28\xb5\x2f\xfd"),
+            Some(Compression::Zstd)
+        );
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn file_decompressed_gzip() {
+        use crate::cookie::Flags;
+        use std::io::Write;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let path = std::env::temp_dir().join("rust-magic-test-file_decompressed.png.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(&png).unwrap();
+        encoder.finish().unwrap();
+
+        let decompressed = cookie.file_decompressed(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(decompressed.compression(), Some(Compression::Gzip));
+        assert_eq!(
+            decompressed.content(),
+            Some("PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced")
+        );
+    }
+}
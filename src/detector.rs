@@ -0,0 +1,455 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Single entry point for content detection in applications
+//!
+//! A [`Detector`] wraps a loaded [`Cookie`] and combines it with
+//! - limits for untrusted input, see [`Detector::max_len()`]
+//! - an optional cache of results by content, see [`Detector::cache()`]
+//! - an optional [`Policy`], see [`Detector::policy()`]
+//! - [spoofing checks](crate::spoof) of uploads, see [`Detector::check_upload()`]
+//!
+//! Each detection analyzes the content for its description, media types and extensions, see [`Outcome`].
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::policy::{Decision, Pattern, Policy};
+//! use magic::Detector;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+//! let detector = Detector::new(cookie)
+//!     .cache(1024)
+//!     .policy(Policy::new(Decision::Deny).allow(Pattern::mime_type("image/*")));
+//!
+//! let png = std::fs::read("data/tests/rust-logo-128x128-blk.png")?;
+//! let upload = detector.check_upload(&png, Some("image/png"), Some("logo.png"))?;
+//! assert_eq!(upload.outcome().mime_type(), "image/png");
+//! assert_eq!(upload.decision(), Decision::Allow);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::{
+    DatabasePaths, Error, Flags, FlagsGuard, Load, LoadError, Open, OpenError, SetFlagsError,
+};
+use crate::normalize::{split_matches, CONTINUE_SEPARATOR};
+use crate::policy::{Decision, Policy};
+use crate::polyglot::GENERIC_RESULTS;
+use crate::spoof::{Detection, Reason, Verdict, UNKNOWN_EXTENSION};
+use crate::Cookie;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Default maximum number of bytes that are analyzed, see [`Detector::max_len()`]
+pub const DEFAULT_MAX_LEN: usize = 1024 * 1024;
+
+/// Error within [`Detector`] functions
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum DetectorError {
+    /// Opening the cookie failed
+    #[error("could not open cookie")]
+    Open(#[source] OpenError),
+    /// Loading the default databases failed
+    #[error("could not load databases")]
+    Load(#[source] LoadError<Open>),
+    /// Reading the file failed
+    #[error("could not read file")]
+    Read(#[source] std::io::Error),
+    /// Setting the flags for an analysis failed
+    #[error("could not set flags to analyze content")]
+    SetFlags(#[source] SetFlagsError),
+    /// Analyzing the content failed
+    #[error("could not analyze content")]
+    Analyze(#[source] Error),
+}
+
+/// Results of a single detection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    description: String,
+    detection: Detection,
+    decision: Option<Decision>,
+    truncated: bool,
+}
+
+impl Outcome {
+    /// Returns the textual description, e.g. "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Returns the media type of the strongest match, e.g. `image/png`
+    pub fn mime_type(&self) -> &str {
+        self.detection.mime_type()
+    }
+
+    /// Returns the media types of all matches
+    pub fn mime_types(&self) -> &[String] {
+        self.detection.mime_types()
+    }
+
+    /// Returns the known filename extensions, empty if there are none
+    pub fn extensions(&self) -> &[String] {
+        self.detection.extensions()
+    }
+
+    /// Returns the decision of the [`Detector::policy()`], if there is one
+    pub fn decision(&self) -> Option<Decision> {
+        self.decision
+    }
+
+    /// Returns whether the content was longer than [`Detector::max_len()`] and only its start was analyzed
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// Result of [`Detector::check_upload()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upload {
+    outcome: Outcome,
+    verdict: Verdict,
+    decision: Decision,
+}
+
+impl Upload {
+    /// Returns the results of the detection of the content
+    pub fn outcome(&self) -> &Outcome {
+        &self.outcome
+    }
+
+    /// Returns the reasons for suspicion of the declared content type and filename
+    pub fn verdict(&self) -> &Verdict {
+        &self.verdict
+    }
+
+    /// Returns the combined decision for the upload
+    ///
+    /// This is the decision of the [`Detector::policy()`], or [`Decision::Allow`] without a policy, escalated by the [`verdict()`](Upload::verdict):
+    /// A [`Reason::DisguisedExecutable`] always leads to [`Decision::Deny`],
+    /// any other reason for suspicion turns [`Decision::Allow`] into [`Decision::Review`].
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+}
+
+/// Bounded cache of outcomes by content
+#[derive(Debug)]
+struct Cache {
+    capacity: usize,
+    // randomly keyed, so that colliding content can not be crafted
+    hasher: RandomState,
+    outcomes: HashMap<(u64, usize), Outcome>,
+    order: VecDeque<(u64, usize)>,
+}
+
+impl Cache {
+    fn key(&self, content: &[u8]) -> (u64, usize) {
+        use std::hash::{BuildHasher, Hash, Hasher};
+
+        let mut hasher = self.hasher.build_hasher();
+        content.hash(&mut hasher);
+        (hasher.finish(), content.len())
+    }
+
+    fn insert(&mut self, key: (u64, usize), outcome: Outcome) {
+        if self.outcomes.insert(key, outcome).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.outcomes.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Content detection with limits, cache and policy
+///
+/// A detector is [`Send`] and [`Sync`], the analyses of detections on a shared detector are serialized.
+#[derive(Debug)]
+pub struct Detector {
+    cookie: Cookie<Load>,
+    max_len: usize,
+    cache: Option<Mutex<Cache>>,
+    policy: Option<Policy>,
+}
+
+impl Detector {
+    /// Creates a detector with the default databases, see [`DatabasePaths::default()`]
+    ///
+    /// # Errors
+    ///
+    /// If the cookie can not be opened, a [`DetectorError::Open`] will be returned.
+    ///
+    /// If the default databases can not be loaded, a [`DetectorError::Load`] will be returned.
+    pub fn open() -> Result<Self, DetectorError> {
+        let cookie = Cookie::open(Flags::empty())
+            .map_err(DetectorError::Open)?
+            .load(DatabasePaths::default())
+            .map_err(DetectorError::Load)?;
+        Ok(Self::new(cookie))
+    }
+
+    /// Creates a detector with the databases of `cookie`, without cache and policy
+    ///
+    /// Each analysis applies its own flags along with [`Flags::ERROR`] until it is done,
+    /// the flags of `cookie` are left unchanged, see [`Cookie::with_flags()`].
+    pub fn new(cookie: Cookie<Load>) -> Self {
+        Self {
+            cookie,
+            max_len: DEFAULT_MAX_LEN,
+            cache: None,
+            policy: None,
+        }
+    }
+
+    /// Limits the analysis to the first `max_len` bytes of content
+    ///
+    /// Files are only read up to this limit. Defaults to [`DEFAULT_MAX_LEN`].
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Caches the outcomes of up to `capacity` distinct contents, evicting the oldest ones first
+    ///
+    /// Contents are compared by a randomly keyed hash and their length.
+    pub fn cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Mutex::new(Cache {
+            capacity,
+            hasher: RandomState::new(),
+            outcomes: HashMap::new(),
+            order: VecDeque::new(),
+        }));
+        self
+    }
+
+    /// Evaluates each detection with `policy`, see [`Outcome::decision()`]
+    pub fn policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Detects the content of the file at `path`
+    ///
+    /// Only the first [`max_len()`](Detector::max_len) bytes of regular files are read.
+    /// Other files such as directories or devices are analyzed by their metadata.
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be read, a [`DetectorError::Read`] will be returned.
+    ///
+    /// If the flags can not be set, a [`DetectorError::SetFlags`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`DetectorError::Analyze`] will be returned.
+    pub fn detect_path<P: AsRef<Path>>(&self, path: P) -> Result<Outcome, DetectorError> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path).map_err(DetectorError::Read)?;
+        if !metadata.is_file() {
+            return self.analyze(None, false, |guard| guard.file(path));
+        }
+
+        let file = std::fs::File::open(path).map_err(DetectorError::Read)?;
+        let mut content = Vec::new();
+        file.take(self.max_len as u64 + 1)
+            .read_to_end(&mut content)
+            .map_err(DetectorError::Read)?;
+        self.detect_bytes(&content)
+    }
+
+    /// Detects the content of `bytes`
+    ///
+    /// Only the first [`max_len()`](Detector::max_len) bytes are analyzed.
+    ///
+    /// # Errors
+    ///
+    /// If the flags can not be set, a [`DetectorError::SetFlags`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`DetectorError::Analyze`] will be returned.
+    pub fn detect_bytes(&self, bytes: &[u8]) -> Result<Outcome, DetectorError> {
+        let truncated = bytes.len() > self.max_len;
+        let content = &bytes[..bytes.len().min(self.max_len)];
+        self.analyze(Some(content), truncated, |guard| guard.buffer(content))
+    }
+
+    /// Detects the content of the upload `bytes` and checks it against the client's `declared_type` and `filename`
+    ///
+    /// See [`Upload::decision()`] for how the policy and the [spoofing checks](crate::spoof) are combined.
+    ///
+    /// # Errors
+    ///
+    /// See [`detect_bytes()`](Detector::detect_bytes).
+    pub fn check_upload(
+        &self,
+        bytes: &[u8],
+        declared_type: Option<&str>,
+        filename: Option<&str>,
+    ) -> Result<Upload, DetectorError> {
+        let outcome = self.detect_bytes(bytes)?;
+        let verdict = crate::spoof::check(declared_type, filename, &outcome.detection);
+
+        let decision = match (outcome.decision.unwrap_or(Decision::Allow), &verdict) {
+            (_, verdict) if verdict.has(Reason::DisguisedExecutable) => Decision::Deny,
+            (Decision::Allow, verdict) if verdict.is_suspicious() => Decision::Review,
+            (decision, _) => decision,
+        };
+
+        Ok(Upload {
+            outcome,
+            verdict,
+            decision,
+        })
+    }
+
+    /// Analyzes the `content` with `query` for each kind of result, unless it is cached
+    ///
+    /// Without `content`, `query` analyzes a special file by its metadata.
+    /// It is called with each kind's flags applied.
+    fn analyze<F>(
+        &self,
+        content: Option<&[u8]>,
+        truncated: bool,
+        query: F,
+    ) -> Result<Outcome, DetectorError>
+    where
+        F: Fn(&FlagsGuard<'_>) -> Result<String, Error>,
+    {
+        // only contents can be cached, not metadata of special files
+        let key = match (&self.cache, content) {
+            (Some(cache), Some(content)) => {
+                let cache = cache
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner);
+                let key = cache.key(content);
+                if let Some(outcome) = cache.outcomes.get(&key) {
+                    let mut outcome = outcome.clone();
+                    outcome.truncated = truncated;
+                    return Ok(outcome);
+                }
+                Some(key)
+            }
+            _ => None,
+        };
+
+        let (description, mime_type, extension) = {
+            let mut results = Vec::with_capacity(3);
+            for flags in [
+                Flags::empty(),
+                Flags::MIME_TYPE | Flags::CONTINUE,
+                Flags::EXTENSION,
+            ] {
+                // `libmagic` fails to read special files such as directories for extensions
+                if flags == Flags::EXTENSION && content.is_none() {
+                    results.push(UNKNOWN_EXTENSION.to_string());
+                    continue;
+                }
+                let guard = self
+                    .cookie
+                    .with_flags(Flags::ERROR | flags)
+                    .map_err(DetectorError::SetFlags)?;
+                results.push(query(&guard).map_err(DetectorError::Analyze)?);
+            }
+            let extension = results.pop().unwrap_or_default();
+            // further generic matches such as "application/octet-stream" are no distinct identities
+            let mime_type = split_matches(&results.pop().unwrap_or_default())
+                .enumerate()
+                .filter(|(index, mime_type)| *index == 0 || !GENERIC_RESULTS.contains(mime_type))
+                .map(|(_, mime_type)| mime_type)
+                .collect::<Vec<_>>()
+                .join(CONTINUE_SEPARATOR);
+            let description = results.pop().unwrap_or_default();
+            (description, mime_type, extension)
+        };
+
+        let decision = self
+            .policy
+            .as_ref()
+            .map(|policy| policy.evaluate(&mime_type, Some(&description)).decision());
+        let outcome = Outcome {
+            description,
+            detection: Detection::from_results(&mime_type, &extension),
+            decision,
+            truncated,
+        };
+
+        if let (Some(cache), Some(key)) = (&self.cache, key) {
+            cache
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .insert(key, outcome.clone());
+        }
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Detector;
+    use crate::policy::{Category, Decision, Pattern, Policy};
+
+    fn detector() -> Detector {
+        let cookie = crate::Cookie::open(Default::default()).unwrap();
+        let databases = ["data/tests/db-images-png", "data/tests/db-python"];
+        Detector::new(cookie.load(databases).unwrap())
+    }
+
+    #[test]
+    fn detect() {
+        let detector = detector().cache(1).max_len(64);
+
+        let outcome = detector
+            .detect_path("data/tests/rust-logo-128x128-blk.png")
+            .unwrap();
+        assert_eq!(outcome.mime_type(), "image/png");
+        assert!(outcome.truncated());
+        assert_eq!(outcome.decision(), None);
+
+        let script = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        let outcome = detector.detect_bytes(script).unwrap();
+        assert_eq!(
+            outcome.description(),
+            "Python script, ASCII text executable"
+        );
+        assert_eq!(outcome.mime_type(), "text/x-python");
+        assert!(!outcome.truncated());
+        assert_eq!(detector.detect_bytes(script).unwrap(), outcome);
+
+        let outcome = detector.detect_path("data/tests").unwrap();
+        assert_eq!(outcome.mime_type(), "inode/directory");
+
+        // the flags of the analyses were only applied temporarily
+        assert_eq!(detector.cookie.flags(), crate::cookie::Flags::empty());
+    }
+
+    #[test]
+    fn check_upload() {
+        let detector = detector().policy(
+            Policy::new(Decision::Review)
+                .deny(Pattern::Category(Category::Executable))
+                .allow(Pattern::mime_type("image/*"))
+                .allow(Pattern::mime_type("text/x-python")),
+        );
+
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let upload = detector
+            .check_upload(&png, Some("image/png"), Some("logo.png"))
+            .unwrap();
+        assert!(!upload.verdict().is_suspicious());
+        assert_eq!(upload.decision(), Decision::Allow);
+
+        let script = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        let upload = detector
+            .check_upload(script, Some("image/png"), Some("logo.png"))
+            .unwrap();
+        assert_eq!(upload.outcome().decision(), Some(Decision::Allow));
+        assert!(upload.verdict().is_suspicious());
+        assert_eq!(upload.decision(), Decision::Review);
+    }
+}
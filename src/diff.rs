@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Compare analysis results of two configurations
+//!
+//! Before rolling out e.g. a database update, different flags or a new `libmagic` version,
+//! [`compare()`] analyzes a corpus of files under the old and the new configuration and reports which results changed.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let old = magic::Cookie::open(Default::default())?;
+//! let old = old.load(["data/tests/db-python"])?;
+//!
+//! let new = magic::Cookie::open(Default::default())?;
+//! let new = new.load(["data/tests/db-python", "data/tests/db-images-png"])?;
+//!
+//! let diff = magic::diff::compare(["data/tests/rust-logo-128x128-blk.png"], &old, &new);
+//! for change in diff.changes() {
+//!     println!("{}: {:?} -> {:?}", change.path().display(), change.a(), change.b());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use std::path::{Path, PathBuf};
+
+/// Changed analysis result of a single file
+///
+/// Returned from [`Diff::changes()`](Diff::changes)
+#[derive(Debug)]
+pub struct Change {
+    path: PathBuf,
+    a: Result<String, Error>,
+    b: Result<String, Error>,
+}
+
+impl Change {
+    /// Returns the path of the analyzed file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the result of the first configuration
+    pub fn a(&self) -> &Result<String, Error> {
+        &self.a
+    }
+
+    /// Returns the result of the second configuration
+    pub fn b(&self) -> &Result<String, Error> {
+        &self.b
+    }
+}
+
+/// Outcome of [`compare()`]
+#[derive(Debug)]
+pub struct Diff {
+    changes: Vec<Change>,
+    unchanged: usize,
+}
+
+impl Diff {
+    /// Returns the files whose analysis results changed, in corpus order
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Returns the number of files whose analysis results did not change
+    pub fn unchanged(&self) -> usize {
+        self.unchanged
+    }
+
+    /// Returns whether no analysis results changed
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Analyzes each file in `corpus` with cookies `a` and `b` and reports the differences
+///
+/// A result counts as changed if the textual results differ or if only one of the cookies returned an error.
+/// Files for which both cookies return an error count as unchanged.
+pub fn compare<I, P>(corpus: I, a: &Cookie<Load>, b: &Cookie<Load>) -> Diff
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut diff = Diff {
+        changes: Vec::new(),
+        unchanged: 0,
+    };
+
+    for path in corpus {
+        let path = path.as_ref();
+        let result_a = a.file(path);
+        let result_b = b.file(path);
+
+        let changed = match (&result_a, &result_b) {
+            (Ok(result_a), Ok(result_b)) => result_a != result_b,
+            (Err(_), Err(_)) => false,
+            _ => true,
+        };
+
+        match changed {
+            true => diff.changes.push(Change {
+                path: path.to_path_buf(),
+                a: result_a,
+                b: result_b,
+            }),
+            false => diff.unchanged += 1,
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    const CORPUS: [&str; 2] = [
+        "data/tests/rust-logo-128x128-blk.png",
+        "data/tests/db-python",
+    ];
+
+    #[test]
+    fn compare_same() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let diff = super::compare(CORPUS, &cookie, &cookie);
+        assert!(diff.is_empty());
+        assert_eq!(diff.unchanged(), CORPUS.len());
+    }
+
+    #[test]
+    fn compare_flags() {
+        let databases = ["data/tests/db-images-png"];
+        let a = Cookie::open(Flags::ERROR).unwrap();
+        let a = a.load(databases).unwrap();
+        let b = Cookie::open(Flags::ERROR | Flags::MIME_TYPE).unwrap();
+        let b = b.load(databases).unwrap();
+
+        let diff = super::compare(CORPUS, &a, &b);
+        assert_eq!(diff.changes().len(), CORPUS.len());
+
+        let change = &diff.changes()[0];
+        assert_eq!(change.path(), std::path::Path::new(CORPUS[0]));
+        assert_eq!(change.b().as_ref().unwrap(), "image/png");
+    }
+}
@@ -0,0 +1,81 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Decoding of text content with the [`encoding_rs` crate](https://crates.io/crates/encoding_rs)
+//!
+//! [`Charset::encoding()`] maps the result of [`Flags::MIME_ENCODING`](crate::cookie::Flags::MIME_ENCODING)
+//! to an [`Encoding`], which decodes the analyzed content to a [`str`].
+//! `libmagic` names are mapped as per the [WHATWG Encoding Standard](https://encoding.spec.whatwg.org/),
+//! except for "us-ascii", which is decoded as its superset UTF-8 instead of windows-1252.
+//!
+//! Requires the `encoding` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+//!
+//! let buffer = b"gr\xfcn gr\xfcner am gr\xfcnsten\n";
+//! let charset = cookie.charset_buffer(buffer)?;
+//! if let Some(encoding) = charset.encoding() {
+//!     let (text, _, _) = encoding.decode(buffer);
+//!     assert_eq!(text, "gr\u{fc}n gr\u{fc}ner am gr\u{fc}nsten\n");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use encoding_rs::Encoding;
+
+use crate::charset::Charset;
+
+/// Returns the encoding of a [`Flags::MIME_ENCODING`](crate::cookie::Flags::MIME_ENCODING) `result`
+///
+/// See [`Charset::encoding()`].
+pub fn for_result(result: &str) -> Option<&'static Encoding> {
+    Charset::from_encoding(result).encoding()
+}
+
+impl Charset {
+    /// Returns the encoding to decode content of this charset, if supported by `encoding_rs`
+    ///
+    /// Returns `None` for [`Charset::Binary`], for [`Charset::Unknown8Bit`] and for encodings that `encoding_rs` does not support,
+    /// e.g. [`Charset::Ebcdic`] or "utf-7".
+    ///
+    /// Requires the `encoding` feature.
+    pub fn encoding(&self) -> Option<&'static Encoding> {
+        match self {
+            Self::Binary | Self::Unknown8Bit | Self::Ebcdic => None,
+            // ASCII is a subset of UTF-8, while the WHATWG label "us-ascii" means windows-1252
+            Self::UsAscii | Self::Utf8 => Some(encoding_rs::UTF_8),
+            Self::Utf16Le => Some(encoding_rs::UTF_16LE),
+            Self::Utf16Be => Some(encoding_rs::UTF_16BE),
+            Self::Iso8859_1 => Some(encoding_rs::WINDOWS_1252),
+            Self::Other(other) => Encoding::for_label(other.as_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::for_result;
+    use crate::charset::Charset;
+
+    #[test]
+    fn encoding() {
+        assert_eq!(Charset::Utf8.encoding(), Some(encoding_rs::UTF_8));
+        assert_eq!(Charset::UsAscii.encoding(), Some(encoding_rs::UTF_8));
+        assert_eq!(
+            Charset::Iso8859_1.encoding(),
+            Some(encoding_rs::WINDOWS_1252)
+        );
+        assert_eq!(Charset::Binary.encoding(), None);
+        assert_eq!(Charset::Unknown8Bit.encoding(), None);
+        assert_eq!(for_result("utf-16le"), Some(encoding_rs::UTF_16LE));
+        assert_eq!(for_result("utf-7"), None);
+
+        let (text, _, _) = Charset::Iso8859_1.encoding().unwrap().decode(b"gr\xfcn");
+        assert_eq!(text, "gr\u{fc}n");
+    }
+}
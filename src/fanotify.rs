@@ -0,0 +1,352 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! On-access analysis of files on whole mounts
+//!
+//! [`Monitor`] subscribes to [fanotify(7)](https://man7.org/linux/man-pages/man7/fanotify.7.html) events of mounts
+//! via the [`nix` crate](https://crates.io/crates/nix), e.g. for content monitoring agents.
+//! Unlike [`watch`](crate::watch), this sees files touched by any process anywhere on the mount,
+//! but requires the `CAP_SYS_ADMIN` capability.
+//!
+//! [`Monitor::spawn()`] reads events on a background thread and analyzes the touched files on a pool of
+//! worker threads, one per given [`Cookie`]. The content is read via the file descriptor of the event,
+//! so files that are renamed or deleted in the meantime are still analyzed.
+//!
+//! Only available on Linux. Requires the `fanotify` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use magic::fanotify::{Monitor, Trigger};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut cookies = Vec::new();
+//! for _ in 0..4 {
+//!     cookies.push(magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?);
+//! }
+//!
+//! let monitor = Monitor::new()?;
+//! monitor.add_mount("/home", Trigger::CloseWrite)?;
+//! for event in monitor.spawn(cookies)? {
+//!     let event = event?;
+//!     println!("{} by {}: {:?}", event.path().display(), event.pid(), event.result());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use nix::sys::fanotify::{EventFFlags, Fanotify, InitFlags, MarkFlags, MaskFlags};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of bytes that are read from a touched file and analyzed
+pub const FILE_PREFIX_LEN: usize = 1024 * 1024;
+
+/// Kind of file access that leads to an analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Trigger {
+    /// A file that was opened for writing is closed, i.e. its content might have changed
+    CloseWrite,
+    /// A file is opened, or a file that was opened for writing is closed
+    ///
+    /// This also analyzes files that are only read, which can be many.
+    OpenOrCloseWrite,
+}
+
+impl Trigger {
+    fn mask(self) -> MaskFlags {
+        match self {
+            Self::CloseWrite => MaskFlags::FAN_CLOSE_WRITE,
+            Self::OpenOrCloseWrite => MaskFlags::FAN_OPEN | MaskFlags::FAN_CLOSE_WRITE,
+        }
+    }
+}
+
+/// Analysis result of a touched file
+///
+/// Returned from [`Events`]
+#[derive(Debug)]
+pub struct Event {
+    path: PathBuf,
+    pid: i32,
+    result: Result<String, Error>,
+}
+
+impl Event {
+    /// Returns the path of the file at the time of the event
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the process ID that touched the file
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// Returns the textual result of the analysis of the file
+    pub fn result(&self) -> &Result<String, Error> {
+        &self.result
+    }
+}
+
+/// Error of [`Monitor`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum FanotifyError {
+    /// Initializing fanotify, marking a mount or reading events failed
+    #[error("could not monitor mounts")]
+    Fanotify(#[source] nix::Error),
+    /// Reading a touched file failed
+    #[error("could not read touched file")]
+    Read(#[source] std::io::Error),
+    /// No cookies were given to analyze files with
+    #[error("could not spawn workers without cookies")]
+    NoCookies,
+}
+
+/// Monitors mounts for file accesses
+#[derive(Debug)]
+pub struct Monitor {
+    fanotify: Fanotify,
+}
+
+/// File of an event, waiting for a worker
+struct Job {
+    file: std::fs::File,
+    path: PathBuf,
+    pid: i32,
+}
+
+impl Monitor {
+    /// Creates a new monitor without any mounts
+    ///
+    /// # Errors
+    ///
+    /// If fanotify could not be initialized, e.g. due to missing capabilities, a [`FanotifyError::Fanotify`] will be returned.
+    pub fn new() -> Result<Self, FanotifyError> {
+        let fanotify = Fanotify::init(
+            InitFlags::FAN_CLASS_NOTIF | InitFlags::FAN_CLOEXEC,
+            EventFFlags::O_RDONLY | EventFFlags::O_LARGEFILE | EventFFlags::O_CLOEXEC,
+        )
+        .map_err(FanotifyError::Fanotify)?;
+
+        Ok(Self { fanotify })
+    }
+
+    /// Starts monitoring the whole mount that contains `path` for accesses as per `trigger`
+    ///
+    /// # Errors
+    ///
+    /// If the mount could not be marked, a [`FanotifyError::Fanotify`] will be returned.
+    pub fn add_mount<P: AsRef<Path>>(
+        &self,
+        path: P,
+        trigger: Trigger,
+    ) -> Result<(), FanotifyError> {
+        self.fanotify
+            .mark(
+                MarkFlags::FAN_MARK_ADD | MarkFlags::FAN_MARK_MOUNT,
+                trigger.mask(),
+                None,
+                Some(path.as_ref()),
+            )
+            .map_err(FanotifyError::Fanotify)
+    }
+
+    /// Starts reading events and analyzing the touched files with one worker thread per cookie of `cookies`
+    ///
+    /// Accesses by the current process itself, e.g. of the workers, are ignored.
+    /// The threads stop once the returned [`Events`] are dropped and the next event arrives.
+    ///
+    /// # Errors
+    ///
+    /// If `cookies` is empty, a [`FanotifyError::NoCookies`] will be returned.
+    pub fn spawn(self, cookies: Vec<Cookie<Load>>) -> Result<Events, FanotifyError> {
+        if cookies.is_empty() {
+            return Err(FanotifyError::NoCookies);
+        }
+
+        let (job_sender, job_receiver) = channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (sender, receiver) = channel();
+
+        for cookie in cookies {
+            let job_receiver = Arc::clone(&job_receiver);
+            let sender = sender.clone();
+            std::thread::spawn(move || work(&cookie, &job_receiver, &sender));
+        }
+        std::thread::spawn(move || read(&self.fanotify, &job_sender, &sender));
+
+        Ok(Events { receiver })
+    }
+}
+
+/// Reads events and passes their files to the workers
+fn read(fanotify: &Fanotify, jobs: &Sender<Job>, sender: &Sender<Result<Event, FanotifyError>>) {
+    let own_pid = std::process::id() as i32;
+    loop {
+        let events = match fanotify.read_events() {
+            Ok(events) => events,
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(err) => {
+                let _ = sender.send(Err(FanotifyError::Fanotify(err)));
+                return;
+            }
+        };
+
+        for event in events {
+            if event.pid() == own_pid {
+                continue;
+            }
+            // events without descriptor signal a queue overflow, which can only be skipped
+            let fd = match event.fd() {
+                Some(fd) => fd,
+                None => continue,
+            };
+
+            let job = fd
+                .try_clone_to_owned()
+                .map(std::fs::File::from)
+                .and_then(|file| {
+                    use std::os::unix::io::AsRawFd;
+
+                    let link = format!("/proc/self/fd/{}", file.as_raw_fd());
+                    let path = std::fs::read_link(link)?;
+                    Ok(Job {
+                        file,
+                        path,
+                        pid: event.pid(),
+                    })
+                });
+            let sent = match job {
+                Ok(job) => jobs.send(job).is_ok(),
+                Err(err) => sender.send(Err(FanotifyError::Read(err))).is_ok(),
+            };
+            if !sent {
+                return;
+            }
+        }
+    }
+}
+
+/// Analyzes the files of jobs until there are no more
+fn work(
+    cookie: &Cookie<Load>,
+    jobs: &Mutex<Receiver<Job>>,
+    sender: &Sender<Result<Event, FanotifyError>>,
+) {
+    loop {
+        let job = {
+            let jobs = jobs
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            match jobs.recv() {
+                Ok(job) => job,
+                Err(_) => return,
+            }
+        };
+
+        let event = read_prefix(&job.file).map(|prefix| Event {
+            path: job.path,
+            pid: job.pid,
+            result: cookie.buffer(&prefix),
+        });
+        if sender.send(event).is_err() {
+            return;
+        }
+    }
+}
+
+fn read_prefix(file: &std::fs::File) -> Result<Vec<u8>, FanotifyError> {
+    let mut prefix = vec![0; FILE_PREFIX_LEN];
+    let mut len = 0;
+    while len < prefix.len() {
+        match file.read_at(&mut prefix[len..], len as u64) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(FanotifyError::Read(err)),
+        }
+    }
+    prefix.truncate(len);
+    Ok(prefix)
+}
+
+/// Blocking iterator of analyzed file accesses
+///
+/// Returned from [`Monitor::spawn()`]
+#[derive(Debug)]
+pub struct Events {
+    receiver: Receiver<Result<Event, FanotifyError>>,
+}
+
+impl Iterator for Events {
+    type Item = Result<Event, FanotifyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FanotifyError, Monitor, Trigger};
+
+    #[test]
+    fn spawn_without_cookies() {
+        // fanotify requires `CAP_SYS_ADMIN`, which tests usually do not have
+        let monitor = match Monitor::new() {
+            Ok(monitor) => monitor,
+            Err(_) => return,
+        };
+
+        assert!(matches!(
+            monitor.spawn(Vec::new()),
+            Err(FanotifyError::NoCookies)
+        ));
+    }
+
+    #[test]
+    fn monitor_close_write() {
+        use crate::cookie::Flags;
+
+        // fanotify requires `CAP_SYS_ADMIN`, which tests usually do not have
+        let monitor = match Monitor::new() {
+            Ok(monitor) => monitor,
+            Err(_) => return,
+        };
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let directory = std::env::temp_dir();
+        monitor.add_mount(&directory, Trigger::CloseWrite).unwrap();
+        let mut events = monitor.spawn(vec![cookie]).unwrap();
+
+        // written by a child process, since accesses of the test process itself are ignored
+        let path = directory.join("rust-magic-test-monitor_close_write.py");
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("printf \"#!/usr/bin/env python\\nprint('Hello, world!')\" > \"$0\"")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let event = events
+            .find(|event| event.as_ref().unwrap().path() == path)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            event.result().as_ref().unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+}
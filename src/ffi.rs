@@ -11,27 +11,55 @@ use magic_sys as libmagic;
 
 #[derive(Debug)]
 // non-copy wrapper around raw pointer
-#[repr(transparent)]
-pub(crate) struct Cookie(libmagic::magic_t);
+/// Opened `magic_t` instance, along with the flags last set on it
+///
+/// The flags are tracked for `libmagic` versions that lack `magic_getflags()`.
+pub(crate) struct Cookie(libmagic::magic_t, libc::c_int);
+
+// `libmagic` cookies are not bound to the thread that opened them, they must only not be used concurrently
+unsafe impl Send for Cookie {}
 
 impl Cookie {
     pub fn new(cookie: &mut Self) -> Self {
-        Self(cookie.0)
+        Self(cookie.0, cookie.1)
+    }
+
+    /// Returns the flags last set on this cookie, without asking `libmagic`
+    pub fn flags(&self) -> libc::c_int {
+        self.1
     }
 }
 
 /// Error for opened `magic_t` instance
 #[derive(thiserror::Error, Debug)]
-#[error("magic cookie error ({}): {}",
-match .errno {
-    Some(errno) => format!("OS errno: {}", errno),
-    None => "no OS errno".to_string(),
-},
-.explanation.to_string_lossy()
-)]
-pub(crate) struct CookieError {
-    explanation: std::ffi::CString,
-    errno: Option<std::io::Error>,
+pub(crate) enum CookieError {
+    /// Last error of `libmagic`
+    #[error("magic cookie error ({}): {}",
+    match .errno {
+        Some(errno) => format!("OS errno: {}", errno),
+        None => "no OS errno".to_string(),
+    },
+    .explanation.to_string_lossy()
+    )]
+    Libmagic {
+        explanation: std::ffi::CString,
+        errno: Option<std::io::Error>,
+    },
+    /// `libmagic` violated its API contract
+    #[error("`libmagic` API violation: {}", .description)]
+    ApiViolation { description: String },
+    /// Path that can not be passed to `libmagic`
+    #[error("path contains NUL byte")]
+    InvalidPath(#[source] std::ffi::NulError),
+    /// Database paths that can not be passed to `libmagic`
+    #[error("invalid database paths")]
+    InvalidDatabasePath(#[source] crate::cookie::InvalidDatabasePathError),
+    /// Compiled database with an invalid header
+    #[error("invalid compiled database")]
+    DatabaseHeader(#[source] crate::compiled::HeaderError),
+    /// `libmagic` is too old for the operation
+    #[error("unsupported `libmagic` version")]
+    UnsupportedVersion(#[source] crate::version::UnsupportedLibmagicVersion),
 }
 
 fn last_error(cookie: &Cookie) -> Option<CookieError> {
@@ -42,7 +70,7 @@ fn last_error(cookie: &Cookie) -> Option<CookieError> {
         None
     } else {
         let c_str = unsafe { std::ffi::CStr::from_ptr(error) };
-        Some(CookieError {
+        Some(CookieError::Libmagic {
             explanation: c_str.into(),
             errno: match errno {
                 0 => None,
@@ -52,11 +80,10 @@ fn last_error(cookie: &Cookie) -> Option<CookieError> {
     }
 }
 
-fn api_violation(cookie: &Cookie, description: String) -> ! {
-    panic!(
-        "`libmagic` API violation for magic cookie {:?}: {}",
-        cookie, description
-    );
+fn api_violation(cookie: &Cookie, description: String) -> CookieError {
+    CookieError::ApiViolation {
+        description: format!("magic cookie {:?}: {}", cookie, description),
+    }
 }
 
 fn expect_error(cookie: &Cookie, description: String) -> CookieError {
@@ -66,17 +93,74 @@ fn expect_error(cookie: &Cookie, description: String) -> CookieError {
     }
 }
 
+/// Converts `path` for `libmagic`
+///
+/// See [`path_bytes()`] for how paths are converted.
+pub(crate) fn path(path: &std::path::Path) -> Result<std::ffi::CString, CookieError> {
+    std::ffi::CString::new(path_bytes(path).into_owned()).map_err(CookieError::InvalidPath)
+}
+
+/// Returns the bytes of `path` as `libmagic` expects them
+///
+/// On Unix, paths are arbitrary bytes and passed as they are.
+/// On other platforms, e.g. Windows where `libmagic` uses the narrow C runtime functions,
+/// paths are converted to UTF-8, which is lossy only for ill-formed UTF-16 like unpaired surrogates.
+pub(crate) fn path_bytes(path: &std::path::Path) -> std::borrow::Cow<'_, [u8]> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::borrow::Cow::Borrowed(path.as_os_str().as_bytes())
+    }
+    #[cfg(not(unix))]
+    {
+        match path.to_string_lossy() {
+            std::borrow::Cow::Borrowed(path) => std::borrow::Cow::Borrowed(path.as_bytes()),
+            std::borrow::Cow::Owned(path) => std::borrow::Cow::Owned(path.into_bytes()),
+        }
+    }
+}
+
+/// Converts a path from `libmagic`, the inverse of [`path_bytes()`]
+pub(crate) fn path_from_bytes(bytes: &[u8]) -> std::path::PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::ffi::OsStr::from_bytes(bytes).into()
+    }
+    #[cfg(not(unix))]
+    {
+        String::from_utf8_lossy(bytes).into_owned().into()
+    }
+}
+
 pub(crate) fn close(cookie: &mut Cookie) {
     unsafe { libmagic::magic_close(cookie.0) }
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error.
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error.
 pub(crate) fn file(
     cookie: &Cookie,
     filename: &std::ffi::CStr, // TODO: Support NULL
-) -> Result<std::ffi::CString, CookieError> {
+) -> Result<Vec<u8>, CookieError> {
+    // the result is owned by the cookie and only valid until the next call, so copy it once
+    file_result(cookie, filename).map(|c_str| c_str.to_bytes().to_vec())
+}
+
+/// Like [`file()`] but borrows the result, which stays valid while `cookie` is exclusively borrowed
+pub(crate) fn file_ref<'a>(
+    cookie: &'a mut Cookie,
+    filename: &std::ffi::CStr,
+) -> Result<&'a std::ffi::CStr, CookieError> {
+    file_result(cookie, filename)
+}
+
+/// The result is only valid until the next call on `cookie`, callers need to copy it or borrow `cookie` exclusively
+fn file_result<'a>(
+    cookie: &'a Cookie,
+    filename: &std::ffi::CStr,
+) -> Result<&'a std::ffi::CStr, CookieError> {
     let filename_ptr = filename.as_ptr();
     let res = unsafe { libmagic::magic_file(cookie.0, filename_ptr) };
 
@@ -86,15 +170,28 @@ pub(crate) fn file(
             "`magic_file()` did not set last error".to_string(),
         ))
     } else {
-        let c_str = unsafe { std::ffi::CStr::from_ptr(res) };
-        Ok(c_str.into())
+        Ok(unsafe { std::ffi::CStr::from_ptr(res) })
     }
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error.
-pub(crate) fn buffer(cookie: &Cookie, buffer: &[u8]) -> Result<std::ffi::CString, CookieError> {
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error.
+pub(crate) fn buffer(cookie: &Cookie, buffer: &[u8]) -> Result<Vec<u8>, CookieError> {
+    // the result is owned by the cookie and only valid until the next call, so copy it once
+    buffer_result(cookie, buffer).map(|c_str| c_str.to_bytes().to_vec())
+}
+
+/// Like [`buffer()`] but borrows the result, which stays valid while `cookie` is exclusively borrowed
+pub(crate) fn buffer_ref<'a>(
+    cookie: &'a mut Cookie,
+    buffer: &[u8],
+) -> Result<&'a std::ffi::CStr, CookieError> {
+    buffer_result(cookie, buffer)
+}
+
+/// The result is only valid until the next call on `cookie`, callers need to copy it or borrow `cookie` exclusively
+fn buffer_result<'a>(cookie: &'a Cookie, buffer: &[u8]) -> Result<&'a std::ffi::CStr, CookieError> {
     let buffer_ptr = buffer.as_ptr();
     let buffer_len = buffer.len() as libc::size_t;
     let res = unsafe { libmagic::magic_buffer(cookie.0, buffer_ptr, buffer_len) };
@@ -105,28 +202,89 @@ pub(crate) fn buffer(cookie: &Cookie, buffer: &[u8]) -> Result<std::ffi::CString
             "`magic_buffer()` did not set last error".to_string(),
         ))
     } else {
+        Ok(unsafe { std::ffi::CStr::from_ptr(res) })
+    }
+}
+
+/// # Errors
+///
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error.
+pub(crate) fn descriptor(cookie: &Cookie, fd: libc::c_int) -> Result<Vec<u8>, CookieError> {
+    let res = unsafe { libmagic::magic_descriptor(cookie.0, fd) };
+
+    if res.is_null() {
+        Err(expect_error(
+            cookie,
+            "`magic_descriptor()` did not set last error".to_string(),
+        ))
+    } else {
+        // the result is owned by the cookie and only valid until the next call, so copy it once
         let c_str = unsafe { std::ffi::CStr::from_ptr(res) };
-        Ok(c_str.into())
+        Ok(c_str.to_bytes().to_vec())
     }
 }
 
-pub(crate) fn setflags(cookie: &Cookie, flags: libc::c_int) -> Result<(), SetFlagsError> {
+pub(crate) fn setflags(cookie: &mut Cookie, flags: libc::c_int) -> Result<(), SetFlagsError> {
     let ret = unsafe { libmagic::magic_setflags(cookie.0, flags) };
     match ret {
         -1 => Err(SetFlagsError { flags }),
-        _ => Ok(()),
+        _ => {
+            cookie.1 = flags;
+            Ok(())
+        }
     }
 }
 
+/// Requires `libmagic` 5.29 or later, see [`Cookie::flags()`] otherwise
+pub(crate) fn getflags(cookie: &Cookie) -> libc::c_int {
+    unsafe { libmagic::magic_getflags(cookie.0) }
+}
+
 #[derive(thiserror::Error, Debug)]
 #[error("could not set magic cookie flags {}", .flags)]
 pub(crate) struct SetFlagsError {
     flags: libc::c_int,
 }
 
-/// # Panics
+pub(crate) fn setparam(
+    cookie: &Cookie,
+    param: libc::c_int,
+    value: libc::size_t,
+) -> Result<(), ParamError> {
+    let value_ptr = &value as *const libc::size_t as *const libc::c_void;
+    let ret = unsafe { libmagic::magic_setparam(cookie.0, param, value_ptr) };
+    match ret {
+        -1 => Err(ParamError {
+            param,
+            errno: std::io::Error::last_os_error(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn getparam(cookie: &Cookie, param: libc::c_int) -> Result<libc::size_t, ParamError> {
+    let mut value: libc::size_t = 0;
+    let value_ptr = &mut value as *mut libc::size_t as *mut libc::c_void;
+    let ret = unsafe { libmagic::magic_getparam(cookie.0, param, value_ptr) };
+    match ret {
+        -1 => Err(ParamError {
+            param,
+            errno: std::io::Error::last_os_error(),
+        }),
+        _ => Ok(value),
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("could not access magic cookie parameter {}: {}", .param, .errno)]
+pub(crate) struct ParamError {
+    param: libc::c_int,
+    errno: std::io::Error,
+}
+
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
 pub(crate) fn check(cookie: &Cookie, filename: Option<&std::ffi::CStr>) -> Result<(), CookieError> {
     let filename_ptr = filename.map_or_else(std::ptr::null, std::ffi::CStr::as_ptr);
     let res = unsafe { libmagic::magic_check(cookie.0, filename_ptr) };
@@ -137,16 +295,16 @@ pub(crate) fn check(cookie: &Cookie, filename: Option<&std::ffi::CStr>) -> Resul
             cookie,
             "`magic_check()` did not set last error".to_string(),
         )),
-        res => api_violation(
+        res => Err(api_violation(
             cookie,
             format!("expected 0 or -1 but `magic_check()` returned {}", res),
-        ),
+        )),
     }
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
 pub(crate) fn compile(
     cookie: &Cookie,
     filename: Option<&std::ffi::CStr>,
@@ -160,16 +318,16 @@ pub(crate) fn compile(
             cookie,
             "`magic_compile()` did not set last error".to_string(),
         )),
-        res => api_violation(
+        res => Err(api_violation(
             cookie,
             format!("Expected 0 or -1 but `magic_compile()` returned {}", res),
-        ),
+        )),
     }
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
 pub(crate) fn list(cookie: &Cookie, filename: Option<&std::ffi::CStr>) -> Result<(), CookieError> {
     let filename_ptr = filename.map_or_else(std::ptr::null, std::ffi::CStr::as_ptr);
     let res = unsafe { libmagic::magic_list(cookie.0, filename_ptr) };
@@ -180,16 +338,16 @@ pub(crate) fn list(cookie: &Cookie, filename: Option<&std::ffi::CStr>) -> Result
             cookie,
             "`magic_list()` did not set last error".to_string(),
         )),
-        res => api_violation(
+        res => Err(api_violation(
             cookie,
             format!("Expected 0 or -1 but `magic_list()` returned {}", res),
-        ),
+        )),
     }
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
 pub(crate) fn load(cookie: &Cookie, filename: Option<&std::ffi::CStr>) -> Result<(), CookieError> {
     let filename_ptr = filename.map_or_else(std::ptr::null, std::ffi::CStr::as_ptr);
     let res = unsafe { libmagic::magic_load(cookie.0, filename_ptr) };
@@ -200,16 +358,16 @@ pub(crate) fn load(cookie: &Cookie, filename: Option<&std::ffi::CStr>) -> Result
             cookie,
             "`magic_load()` did not set last error".to_string(),
         )),
-        res => api_violation(
+        res => Err(api_violation(
             cookie,
             format!("Expected 0 or -1 but `magic_load()` returned {}", res),
-        ),
+        )),
     }
 }
 
-/// # Panics
+/// # Errors
 ///
-/// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+/// Errors with [`CookieError::ApiViolation`] if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
 pub(crate) fn load_buffers(cookie: &Cookie, buffers: &[&[u8]]) -> Result<(), CookieError> {
     let mut ffi_buffers: Vec<*const u8> = Vec::with_capacity(buffers.len());
     let mut ffi_sizes: Vec<libc::size_t> = Vec::with_capacity(buffers.len());
@@ -233,13 +391,13 @@ pub(crate) fn load_buffers(cookie: &Cookie, buffers: &[&[u8]]) -> Result<(), Coo
             cookie,
             "`magic_load_buffers()` did not set last error".to_string(),
         )),
-        res => api_violation(
+        res => Err(api_violation(
             cookie,
             format!(
                 "Expected 0 or -1 but `magic_load_buffers()` returned {}",
                 res
             ),
-        ),
+        )),
     }
 }
 
@@ -254,7 +412,7 @@ pub(crate) fn open(flags: libc::c_int) -> Result<Cookie, OpenError> {
             errno: std::io::Error::last_os_error(),
         })
     } else {
-        Ok(Cookie(cookie))
+        Ok(Cookie(cookie, flags))
     }
 }
 
@@ -271,6 +429,32 @@ impl OpenError {
     }
 }
 
+// `FILE_LOAD` of `file.h`, like `magic_load()` without paths this prefers `~/.magic.mgc`
+const FILE_LOAD: libc::c_int = 0;
+// `FILE_CHECK` of `file.h`, unlike `FILE_LOAD` this does not prefer `~/.magic.mgc`
+const FILE_CHECK: libc::c_int = 1;
+
+/// Returns the value of the `MAGIC` environment variable if set, otherwise the default database path `libmagic` was built with
+pub(crate) fn getpath() -> Option<std::ffi::CString> {
+    getpath_action(FILE_CHECK)
+}
+
+/// Returns the database path that `magic_load()` without paths uses, including `~/.magic.mgc` if it exists
+pub(crate) fn getpath_load() -> Option<std::ffi::CString> {
+    getpath_action(FILE_LOAD)
+}
+
+fn getpath_action(action: libc::c_int) -> Option<std::ffi::CString> {
+    let res = unsafe { libmagic::magic_getpath(std::ptr::null(), action) };
+
+    if res.is_null() {
+        None
+    } else {
+        let c_str = unsafe { std::ffi::CStr::from_ptr(res) };
+        Some(c_str.into())
+    }
+}
+
 pub(crate) fn version() -> libc::c_int {
     unsafe { libmagic::magic_version() }
 }
@@ -0,0 +1,114 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! One-shot MIME type detection with a global cookie
+//!
+//! [`detect_file()`] and [`detect_buffer()`] return the MIME type of a file or buffer without any setup.
+//! They share one global cookie, which is opened with [`Flags::MIME_TYPE`] and loaded with [`DatabasePaths::default()`]
+//! on first use. If that fails, the next call tries again.
+//!
+//! The global cookie is [`Sync`] like any [`Cookie`], so the functions can be called from any thread.
+//! The analyses are serialized by the internal lock of the cookie though, see [`Cookie`].
+//! For parallel analyses, use e.g. [`thread_local::with_cookie()`](crate::thread_local::with_cookie) instead.
+//!
+//! Both functions are also available at the crate root. Requires the `global` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mime = magic::detect_file("data/tests/rust-logo-128x128-blk.png")?;
+//! assert_eq!(mime, "image/png");
+//!
+//! let mime = magic::detect_buffer(b"%PDF-1.7")?;
+//! assert_eq!(mime, "application/pdf");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use once_cell::sync::OnceCell;
+
+use crate::cookie::{DatabasePaths, Error, Flags, Load, LoadError, Open, OpenError};
+use crate::Cookie;
+
+/// Error within [`detect_file()`] or [`detect_buffer()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum GlobalError {
+    /// Opening the global cookie failed
+    #[error("could not open global cookie")]
+    Open(#[source] OpenError),
+    /// Loading the default databases failed
+    #[error("could not load default databases")]
+    Load(#[source] LoadError<Open>),
+    /// Analyzing the file or buffer failed
+    #[error("could not analyze data")]
+    Analyze(#[source] Error),
+}
+
+static COOKIE: OnceCell<Cookie<Load>> = OnceCell::new();
+
+/// Returns the global cookie, which is opened and loaded on first use
+fn cookie() -> Result<&'static Cookie<Load>, GlobalError> {
+    COOKIE.get_or_try_init(|| {
+        Cookie::open(Flags::MIME_TYPE)
+            .map_err(GlobalError::Open)?
+            .load(DatabasePaths::default())
+            .map_err(GlobalError::Load)
+    })
+}
+
+/// Returns the MIME type of the contents of the file `filename`, e.g. `image/png`
+///
+/// See [`Cookie::file()`](Cookie::file).
+///
+/// # Errors
+///
+/// If the global cookie can not be opened or loaded, a [`GlobalError::Open`] or [`GlobalError::Load`] will be returned.
+///
+/// If there was an `libmagic` internal error, a [`GlobalError::Analyze`] will be returned.
+#[doc(alias = "magic_file")]
+pub fn detect_file<P: AsRef<Path>>(filename: P) -> Result<String, GlobalError> {
+    cookie()?.file(filename).map_err(GlobalError::Analyze)
+}
+
+/// Returns the MIME type of the contents of the `buffer`, e.g. `image/png`
+///
+/// See [`Cookie::buffer()`](Cookie::buffer).
+///
+/// # Errors
+///
+/// If the global cookie can not be opened or loaded, a [`GlobalError::Open`] or [`GlobalError::Load`] will be returned.
+///
+/// If there was an `libmagic` internal error, a [`GlobalError::Analyze`] will be returned.
+#[doc(alias = "magic_buffer")]
+pub fn detect_buffer(buffer: &[u8]) -> Result<String, GlobalError> {
+    cookie()?.buffer(buffer).map_err(GlobalError::Analyze)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_buffer, detect_file, GlobalError};
+
+    #[test]
+    fn detect() {
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        assert_eq!(detect_file(path).unwrap(), "image/png");
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(detect_buffer(&data).unwrap(), "image/png");
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(move || detect_file(path).unwrap()))
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), "image/png");
+        }
+
+        assert!(matches!(
+            detect_file("data/tests/\0.png"),
+            Err(GlobalError::Analyze(_))
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of open Windows file handles
+//!
+//! Windows services often hold a file `HANDLE` instead of a path, e.g. from an upload or a filter driver.
+//! [`Cookie::handle()`](Cookie::handle) reads a prefix of the file via the handle and analyzes it with
+//! [`Cookie::buffer()`](Cookie::buffer), since `libmagic` itself only accepts paths or CRT file descriptors.
+//!
+//! Only available on Windows.
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use std::mem::ManuallyDrop;
+use std::os::windows::fs::FileExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle};
+
+/// Maximum number of bytes that are read from the handle and analyzed
+pub const HANDLE_PREFIX_LEN: usize = 1024 * 1024;
+
+/// Error within [`Cookie::handle()`](Cookie::handle)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum HandleError {
+    /// Reading from the handle failed
+    #[error("could not read from handle")]
+    Read(#[source] std::io::Error),
+    /// Analyzing the data read failed
+    #[error("could not analyze data read from handle")]
+    Analyze(#[source] Error),
+}
+
+impl Cookie<Load> {
+    /// Returns a textual description of the contents of the file open as `handle`
+    ///
+    /// This accepts anything with a raw `HANDLE`, e.g. a [`File`](std::fs::File) or a `BorrowedHandle`.
+    /// Up to [`HANDLE_PREFIX_LEN`] bytes are read from the start of the file, regardless of the current file pointer.
+    /// Note that this moves the file pointer of the handle.
+    /// The handle needs read access and must not be opened for asynchronous (overlapped) I/O.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// let file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+    /// let file_description = cookie.handle(&file)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If reading from the handle failed, a [`HandleError::Read`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`HandleError::Analyze`] will be returned.
+    pub fn handle<H: AsRawHandle>(&self, handle: &H) -> Result<String, HandleError> {
+        // the handle is only borrowed, so the file must not close it on drop
+        let file =
+            ManuallyDrop::new(unsafe { std::fs::File::from_raw_handle(handle.as_raw_handle()) });
+
+        let mut prefix = vec![0; HANDLE_PREFIX_LEN];
+        let mut len = 0;
+        while len < prefix.len() {
+            match file.seek_read(&mut prefix[len..], len as u64) {
+                Ok(0) => break,
+                Ok(read) => len += read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(HandleError::Read(err)),
+            }
+        }
+        prefix.truncate(len);
+
+        self.buffer(&prefix).map_err(HandleError::Analyze)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn handle() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png").unwrap();
+        assert_eq!(
+            cookie.handle(&file).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+    }
+}
@@ -0,0 +1,40 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Internal registry of result post-processing hooks, see [`Cookie::add_hook()`](crate::Cookie::add_hook)
+
+/// Single hook that rewrites a textual result
+pub(crate) type Hook = Box<dyn Fn(String) -> String + Send + Sync>;
+
+/// Hooks of a cookie, applied in the order they were added
+#[derive(Default)]
+pub(crate) struct Hooks {
+    hooks: Vec<Hook>,
+}
+
+impl Hooks {
+    pub(crate) fn push(&mut self, hook: Hook) {
+        self.hooks.push(hook);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.hooks.clear();
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Passes `result` through all hooks
+    pub(crate) fn apply(&self, result: String) -> String {
+        self.hooks.iter().fold(result, |result, hook| hook(result))
+    }
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("len", &self.hooks.len())
+            .finish()
+    }
+}
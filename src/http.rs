@@ -0,0 +1,149 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! `Content-Type` headers for the [`http` crate](https://crates.io/crates/http)
+//!
+//! Servers that set the `Content-Type` of responses from sniffed content must not pass `libmagic` results on as they are:
+//! those might be non-standard, e.g. `inode/x-empty`, or not even valid media types.
+//! [`content_type()`] turns a MIME result into a sanitized [`HeaderValue`]
+//! and falls back to [`FALLBACK_CONTENT_TYPE`] for anything that is unfit.
+//!
+//! Requires the `http` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+//!
+//! let value = cookie.content_type("data/tests/rust-logo-128x128-blk.png")?;
+//! assert_eq!(value, "image/png");
+//!
+//! let value = magic::http::content_type("inode/directory; charset=binary");
+//! assert_eq!(value, "application/octet-stream");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::Path;
+
+use ::http::header::HeaderValue;
+
+use crate::cookie::Load;
+use crate::media_type::{Conformance, Mime, MimeError};
+use crate::Cookie;
+
+/// Content type for content that can not be described more specifically
+pub const FALLBACK_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Returns a `Content-Type` header value for a [`Flags::MIME`](crate::cookie::Flags::MIME) or
+/// [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) `result`
+///
+/// The result is parsed as [`Mime`] and written in canonical form, e.g. with a lowercase type.
+/// The parameter `charset=binary` is removed, since it is meaningless in HTTP.
+/// Invalid results, e.g. of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE), and [non-standard](Conformance::Nonstandard)
+/// types such as `inode/directory` become [`FALLBACK_CONTENT_TYPE`].
+pub fn content_type(result: &str) -> HeaderValue {
+    match Mime::parse(result) {
+        Ok(mime) => from_mime(mime),
+        Err(_) => HeaderValue::from_static(FALLBACK_CONTENT_TYPE),
+    }
+}
+
+/// Returns a `Content-Type` header value for `mime`, see [`content_type()`]
+fn from_mime(mut mime: Mime) -> HeaderValue {
+    if mime.conformance() == Conformance::Nonstandard {
+        return HeaderValue::from_static(FALLBACK_CONTENT_TYPE);
+    }
+    if mime
+        .parameter("charset")
+        .map_or(false, |charset| charset.eq_ignore_ascii_case("binary"))
+    {
+        mime.remove_parameter("charset");
+    }
+    HeaderValue::from_str(&mime.to_string())
+        .unwrap_or_else(|_| HeaderValue::from_static(FALLBACK_CONTENT_TYPE))
+}
+
+impl Cookie<Load> {
+    /// Returns a `Content-Type` header value for the contents of the file `filename`
+    ///
+    /// The file is analyzed with [`mime()`](Cookie::mime), see [`content_type()`] for the sanitization.
+    ///
+    /// # Errors
+    ///
+    /// If the flags can not be applied, a [`MimeError::Flags`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`MimeError::Analyze`] will be returned.
+    ///
+    /// Invalid results are no error but become [`FALLBACK_CONTENT_TYPE`].
+    #[doc(alias = "magic_file")]
+    pub fn content_type<P: AsRef<Path>>(&self, filename: P) -> Result<HeaderValue, MimeError> {
+        match self.mime(filename) {
+            Ok(mime) => Ok(from_mime(mime)),
+            Err(MimeError::Invalid(_)) => Ok(HeaderValue::from_static(FALLBACK_CONTENT_TYPE)),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns a `Content-Type` header value for the contents of the `buffer`
+    ///
+    /// See [`content_type()`](Cookie::content_type).
+    ///
+    /// # Errors
+    ///
+    /// See [`content_type()`](Cookie::content_type).
+    #[doc(alias = "magic_buffer")]
+    pub fn content_type_buffer(&self, buffer: &[u8]) -> Result<HeaderValue, MimeError> {
+        match self.mime_buffer(buffer) {
+            Ok(mime) => Ok(from_mime(mime)),
+            Err(MimeError::Invalid(_)) => Ok(HeaderValue::from_static(FALLBACK_CONTENT_TYPE)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::content_type;
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(content_type("Image/PNG; charset=binary"), "image/png");
+        assert_eq!(
+            content_type("text/plain; charset=us-ascii"),
+            "text/plain; charset=us-ascii"
+        );
+        assert_eq!(
+            content_type("text/x-python; charset=utf-8"),
+            "text/x-python; charset=utf-8"
+        );
+        assert_eq!(
+            content_type("inode/x-empty; charset=binary"),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            content_type("image/gif\n- application/zip"),
+            "application/octet-stream"
+        );
+        assert_eq!(content_type(""), "application/octet-stream");
+    }
+
+    #[test]
+    fn cookie_content_type() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        assert_eq!(cookie.content_type(path).unwrap(), "image/png");
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(cookie.content_type_buffer(&data).unwrap(), "image/png");
+        assert_eq!(
+            cookie.content_type("data/tests").unwrap(),
+            "application/octet-stream"
+        );
+        assert!(cookie.content_type("data/tests/\0.png").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Interning of analysis results for large scans
+//!
+//! Scanning millions of files produces millions of results, but only few distinct ones.
+//! An [`Interner`] stores each distinct result once and hands out cheap [`Arc<str>`](std::sync::Arc) clones,
+//! so memory stays bounded by the number of distinct results instead of the number of files.
+//! Batched analyses intern their results with [`Files::interned()`](crate::batch::Files::interned).
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::intern::Interner;
+//!
+//! let mut interner = Interner::new();
+//! let a = interner.intern("PNG image data");
+//! let b = interner.intern(&String::from("PNG image data"));
+//! assert!(std::sync::Arc::ptr_eq(&a, &b));
+//! assert_eq!(interner.len(), 1);
+//! ```
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Set of distinct results
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    results: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    /// Creates an empty interner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the shared copy of `result`, storing it first if it is new
+    pub fn intern(&mut self, result: &str) -> Arc<str> {
+        match self.results.get(result) {
+            Some(interned) => Arc::clone(interned),
+            None => {
+                let interned: Arc<str> = Arc::from(result);
+                self.results.insert(Arc::clone(&interned));
+                interned
+            }
+        }
+    }
+
+    /// Returns the number of distinct results
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    /// Returns whether there are no results
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    /// Removes results that are no longer used outside of the interner
+    pub fn shrink(&mut self) {
+        self.results
+            .retain(|interned| Arc::strong_count(interned) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interner;
+
+    #[test]
+    fn intern() {
+        let mut interner = Interner::new();
+        assert!(interner.is_empty());
+
+        let png = interner.intern("image/png");
+        let python = interner.intern("text/x-python");
+        assert!(std::sync::Arc::ptr_eq(&png, &interner.intern("image/png")));
+        assert_eq!(&*python, "text/x-python");
+        assert_eq!(interner.len(), 2);
+
+        drop(python);
+        interner.shrink();
+        assert_eq!(interner.len(), 1);
+    }
+}
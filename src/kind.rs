@@ -0,0 +1,368 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Typed results for special files
+//!
+//! Built-in checks of `libmagic` report directories, symbolic links, named pipes, sockets, devices and empty files
+//! as non-standard `inode/*` MIME types, e.g. `inode/directory`, or as textual descriptions such as "fifo (named pipe)".
+//! [`ContentKind`] tells those [`InodeKind`]s apart from actual content without matching strings.
+//! [`symlink_target()`] returns the target of a symbolic link from its description.
+//! [`identified()`] and [`ContentKind::Unknown`] separate the generic fallback results of `libmagic`,
+//! "data" and `application/octet-stream`, from actual classifications.
+//!
+//! Note that symbolic links are only reported without [`Flags::SYMLINK`](crate::cookie::Flags::SYMLINK),
+//! and devices only without [`Flags::DEVICES`](crate::cookie::Flags::DEVICES), since `libmagic` follows or reads them otherwise.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use magic::kind::{ContentKind, InodeKind};
+//!
+//! let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+//!
+//! let kind = cookie.content_kind("data/tests")?;
+//! assert_eq!(kind, ContentKind::Inode(InodeKind::Directory));
+//!
+//! let kind = cookie.content_kind("data/tests/rust-logo-128x128-blk.png")?;
+//! assert_eq!(kind, ContentKind::Content("image/png".to_string()));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::cookie::Load;
+use crate::normalize::{normalize, Normalization};
+use crate::polyglot::GENERIC_RESULTS;
+use crate::query::QueryError;
+use crate::Cookie;
+
+/// Kind of special file, as reported by the built-in checks of `libmagic`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InodeKind {
+    /// Directory, `inode/directory`
+    Directory,
+    /// Symbolic link, also a broken one, `inode/symlink`
+    Symlink,
+    /// Named pipe, `inode/fifo`
+    Fifo,
+    /// Unix domain socket, `inode/socket`
+    Socket,
+    /// Block special device, `inode/blockdevice`
+    BlockDevice,
+    /// Character special device, `inode/chardevice`
+    CharDevice,
+    /// Empty regular file, `inode/x-empty`
+    Empty,
+}
+
+/// All [`InodeKind`]s
+const INODE_KINDS: &[InodeKind] = &[
+    InodeKind::Directory,
+    InodeKind::Symlink,
+    InodeKind::Fifo,
+    InodeKind::Socket,
+    InodeKind::BlockDevice,
+    InodeKind::CharDevice,
+    InodeKind::Empty,
+];
+
+impl InodeKind {
+    /// Returns the kind of a [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) result such as `inode/directory`
+    ///
+    /// Parameters such as `; charset=binary` are ignored. Returns `None` for other MIME types.
+    pub fn from_mime(result: &str) -> Option<Self> {
+        let mime_type = normalize(result, Normalization::MimeType);
+        INODE_KINDS
+            .iter()
+            .find(|kind| kind.mime_type() == mime_type)
+            .copied()
+    }
+
+    /// Returns the kind of a textual description such as "directory" or "symbolic link to target"
+    ///
+    /// Returns `None` for descriptions of other content.
+    pub fn from_description(result: &str) -> Option<Self> {
+        let result = strip_permissions(result);
+        if result == "directory" {
+            Some(Self::Directory)
+        } else if symlink_target(result).is_some() {
+            Some(Self::Symlink)
+        } else if result == "fifo (named pipe)" {
+            Some(Self::Fifo)
+        } else if result == "socket" {
+            Some(Self::Socket)
+        } else if result.starts_with("block special") {
+            Some(Self::BlockDevice)
+        } else if result.starts_with("character special") {
+            Some(Self::CharDevice)
+        } else if result == "empty" {
+            Some(Self::Empty)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the `inode/*` MIME type of `libmagic`, e.g. `inode/directory`
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Directory => "inode/directory",
+            Self::Symlink => "inode/symlink",
+            Self::Fifo => "inode/fifo",
+            Self::Socket => "inode/socket",
+            Self::BlockDevice => "inode/blockdevice",
+            Self::CharDevice => "inode/chardevice",
+            Self::Empty => "inode/x-empty",
+        }
+    }
+}
+
+/// Returns the target of a symbolic link from a textual description such as "symbolic link to ../foo"
+///
+/// Broken links such as "broken symbolic link to ../foo" are supported, as is the quoting
+/// "symbolic link to \`../foo'" of older `libmagic` versions.
+/// Returns `None` for descriptions of other content.
+///
+/// Without [`Flags::RAW`](crate::cookie::Flags::RAW), `libmagic` escapes non-printable characters of the target
+/// as octal `\ooo`, which are returned as is.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+///
+/// use magic::kind::symlink_target;
+///
+/// assert_eq!(symlink_target("symbolic link to ../foo").as_deref(), Some(Path::new("../foo")));
+/// assert_eq!(symlink_target("PNG image data"), None);
+/// ```
+pub fn symlink_target(result: &str) -> Option<PathBuf> {
+    let result = strip_permissions(result);
+    let target = result
+        .strip_prefix("symbolic link to ")
+        .or_else(|| result.strip_prefix("broken symbolic link to "))?;
+    let target = target
+        .strip_prefix('`')
+        .and_then(|target| target.strip_suffix('\''))
+        .unwrap_or(target);
+    if target.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(target))
+    }
+}
+
+/// Strips the permission bits that are reported before the kind, e.g. "sticky, directory"
+fn strip_permissions(result: &str) -> &str {
+    let mut result = result.trim();
+    while let Some(rest) = ["setuid, ", "setgid, ", "sticky, "]
+        .iter()
+        .find_map(|prefix| result.strip_prefix(prefix))
+    {
+        result = rest;
+    }
+    result
+}
+
+/// Returns the `result` unless it is a generic fallback of `libmagic` for unidentified content
+///
+/// The fallbacks are the description "data" and the MIME type `application/octet-stream` with any parameters.
+///
+/// # Examples
+///
+/// ```
+/// use magic::kind::identified;
+///
+/// assert_eq!(identified("PNG image data"), Some("PNG image data"));
+/// assert_eq!(identified("data"), None);
+/// assert_eq!(identified("application/octet-stream; charset=binary"), None);
+/// ```
+pub fn identified(result: &str) -> Option<&str> {
+    let normalized = normalize(result, Normalization::MimeType);
+    if GENERIC_RESULTS.contains(&normalized.as_str()) {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// Result of [`Cookie::content_kind()`](Cookie::content_kind)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ContentKind {
+    /// Special file without content to analyze
+    Inode(InodeKind),
+    /// Content that `libmagic` could not identify, `application/octet-stream`
+    Unknown,
+    /// Identified content with its normalized MIME type, e.g. `image/png`
+    Content(String),
+}
+
+impl ContentKind {
+    /// Returns the kind of a [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) result
+    pub fn from_mime(result: &str) -> Self {
+        if let Some(kind) = InodeKind::from_mime(result) {
+            return Self::Inode(kind);
+        }
+        match identified(result) {
+            Some(mime_type) => Self::Content(normalize(mime_type, Normalization::MimeType)),
+            None => Self::Unknown,
+        }
+    }
+
+    /// Returns the kind of special file, if any
+    pub fn inode(&self) -> Option<InodeKind> {
+        match self {
+            Self::Inode(kind) => Some(*kind),
+            Self::Unknown | Self::Content(_) => None,
+        }
+    }
+
+    /// Returns whether `libmagic` could not identify the content
+    pub fn is_unknown(&self) -> bool {
+        *self == Self::Unknown
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns whether `filename` is a special file or unidentified, or the MIME type of its contents otherwise
+    ///
+    /// The file is analyzed with [`Query::mime()`](crate::query::Query::mime) and without following symbolic links
+    /// or reading devices. See mod [`kind`](crate::kind).
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_file()`](crate::query::Query::of_file).
+    pub fn content_kind<P: AsRef<Path>>(&self, filename: P) -> Result<ContentKind, QueryError> {
+        let mime_type = self.query().mime().of_file(filename)?;
+        Ok(ContentKind::from_mime(&mime_type))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{ContentKind, InodeKind};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn from_results() {
+        assert_eq!(
+            InodeKind::from_mime("inode/symlink; charset=binary"),
+            Some(InodeKind::Symlink)
+        );
+        assert_eq!(InodeKind::from_mime("image/png"), None);
+        assert_eq!(
+            InodeKind::from_description("broken symbolic link to /nowhere"),
+            Some(InodeKind::Symlink)
+        );
+        assert_eq!(
+            InodeKind::from_description("character special (1/3)"),
+            Some(InodeKind::CharDevice)
+        );
+        assert_eq!(
+            InodeKind::from_description("fifo (named pipe)"),
+            Some(InodeKind::Fifo)
+        );
+        assert_eq!(
+            InodeKind::from_description("sticky, directory"),
+            Some(InodeKind::Directory)
+        );
+        assert_eq!(InodeKind::from_description("PNG image data"), None);
+        assert_eq!(InodeKind::BlockDevice.mime_type(), "inode/blockdevice");
+    }
+
+    #[test]
+    fn unknown() {
+        assert_eq!(super::identified(" data\n"), None);
+        assert_eq!(super::identified("Application/Octet-Stream"), None);
+        assert_eq!(super::identified("text/plain"), Some("text/plain"));
+        assert!(ContentKind::from_mime("application/octet-stream; charset=binary").is_unknown());
+        assert_eq!(
+            ContentKind::from_mime("Image/PNG; charset=binary"),
+            ContentKind::Content("image/png".to_string())
+        );
+    }
+
+    #[test]
+    fn symlink_target() {
+        assert_eq!(
+            super::symlink_target("symbolic link to ../foo bar"),
+            Some(PathBuf::from("../foo bar"))
+        );
+        assert_eq!(
+            super::symlink_target("broken symbolic link to /nowhere"),
+            Some(PathBuf::from("/nowhere"))
+        );
+        assert_eq!(
+            super::symlink_target("symbolic link to `target'"),
+            Some(PathBuf::from("target"))
+        );
+        assert_eq!(super::symlink_target("symbolic link to "), None);
+        assert_eq!(super::symlink_target("directory"), None);
+    }
+
+    #[test]
+    fn content_kind() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        assert_eq!(
+            cookie.content_kind("data/tests").unwrap(),
+            ContentKind::Inode(InodeKind::Directory)
+        );
+        assert_eq!(
+            cookie
+                .content_kind("data/tests/rust-logo-128x128-blk.png")
+                .unwrap(),
+            ContentKind::Content("image/png".to_string())
+        );
+
+        let path = std::env::temp_dir().join("rust-magic-test-content_kind_unknown");
+        std::fs::write(&path, [0x00, 0xff, 0x10, 0x80, 0x01, 0xfe]).unwrap();
+        assert_eq!(cookie.content_kind(&path).unwrap(), ContentKind::Unknown);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn content_kind_special() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        assert_eq!(
+            cookie.content_kind("/dev/null").unwrap().inode(),
+            Some(InodeKind::CharDevice)
+        );
+
+        let link = std::env::temp_dir().join("rust-magic-test-content_kind");
+        let _ = std::fs::remove_file(&link);
+        let target = std::env::current_dir()
+            .unwrap()
+            .join("data/tests/rust-logo-128x128-blk.png");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        assert_eq!(
+            super::symlink_target(&cookie.query().of_file(&link).unwrap()),
+            Some(target)
+        );
+        assert_eq!(
+            cookie.content_kind(&link).unwrap(),
+            ContentKind::Inode(InodeKind::Symlink)
+        );
+        std::fs::remove_file(&link).unwrap();
+
+        // broken links are an error for descriptions with `Flags::ERROR`, but not for MIME types
+        std::os::unix::fs::symlink("/nowhere", &link).unwrap();
+        assert_eq!(
+            cookie.content_kind(&link).unwrap(),
+            ContentKind::Inode(InodeKind::Symlink)
+        );
+        std::fs::remove_file(&link).unwrap();
+    }
+}
@@ -35,17 +35,16 @@
 //!
 //! ```rust
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
-//! # use std::convert::TryInto;
 //! // open a new configuration with flags
 //! let cookie = magic::Cookie::open(magic::cookie::Flags::ERROR)?;
 //!
 //! // load a specific database
 //! // (so exact test text assertion below works regardless of the system's default database version)
-//! let database = ["data/tests/db-images-png"].try_into()?;
+//! let database = ["data/tests/db-images-png"];
 //! // you can instead load the default database
-//! //let database = Default::default();
+//! //let database = magic::cookie::DatabasePaths::default();
 //!
-//! let cookie = cookie.load(&database)?;
+//! let cookie = cookie.load(database)?;
 //!
 //! // analyze a test file
 //! let file_to_analyze = "data/tests/rust-logo-128x128-blk.png";
@@ -67,14 +66,13 @@
 //! ```
 //! ```rust
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
-//! # use std::convert::TryInto;
 //! // open a new configuration with flags for mime type and encoding
 //! let flags = magic::cookie::Flags::MIME_TYPE | magic::cookie::Flags::MIME_ENCODING;
 //! let cookie = magic::Cookie::open(flags)?;
 //!
 //! // load a specific database
-//! let database = ["data/tests/db-images-png"].try_into()?;
-//! let cookie = cookie.load(&database)?;
+//! let database = ["data/tests/db-images-png"];
+//! let cookie = cookie.load(database)?;
 //!
 //! // analyze a test file
 //! let file_to_analyze = "data/tests/rust-logo-128x128-blk.png";
@@ -97,14 +95,13 @@
 //! ```
 //! ```rust
 //! # fn main() -> Result<(), Box<dyn std::error::Error>> {
-//! # use std::convert::TryInto;
 //! // open a new configuration with flags for filename extension
 //! let flags = magic::cookie::Flags::EXTENSION;
 //! let cookie = magic::Cookie::open(flags)?;
 //!
 //! // load a specific database
-//! let database = ["data/tests/db-images-png"].try_into()?;
-//! let cookie = cookie.load(&database)?;
+//! let database = ["data/tests/db-images-png"];
+//! let cookie = cookie.load(database)?;
 //!
 //! // analyze a test file
 //! let file_to_analyze = "data/tests/rust-logo-128x128-blk.png";
@@ -128,8 +125,12 @@
 //! * [`Cookie::load()`](Cookie::load), [`Cookie::load_buffers()`](Cookie::load_buffers)
 //! * [`Cookie::file()`](Cookie::file), [`Cookie::buffer()`](Cookie::buffer)
 //!
-//! Note that while some `libmagic` functions return somewhat structured text, e.g. MIME types and file extensions,
-//! the `magic` crate does not attempt to parse them into Rust data types since the format is not guaranteed by the C FFI API.
+//! Some `libmagic` results are somewhat structured text, e.g. MIME types and file extensions.
+//! [`Cookie::query()`](Cookie::query) returns typed results such as lists of extensions, see mod [`query`],
+//! and mod [`media_type`] parses MIME results into [`Mime`](media_type::Mime), see also mods [`charset`] and [`kind`].
+//! Note that the format of those results is not guaranteed by the C FFI API: parsing is best-effort,
+//! results that do not fit the expected format are reported as errors or unknown values,
+//! and the plain text functions such as [`Cookie::file()`](Cookie::file) remain the source of truth.
 //!
 //! Check the [crate README](https://crates.io/crates/magic) for required dependencies and MSRV.
 //!
@@ -164,20 +165,164 @@
 #![deny(unsafe_code)]
 
 mod ffi;
+mod hook;
+
+#[cfg(feature = "actix-web")]
+pub mod actix_web;
+pub mod analysis;
+#[cfg(any(feature = "zip", feature = "tar"))]
+pub mod archive;
+#[cfg(all(feature = "axum", feature = "tokio"))]
+pub mod axum;
+pub mod batch;
+pub mod builder;
+pub mod charset;
+#[cfg(feature = "clap")]
+pub mod clap;
+pub mod compiled;
+pub mod consistency;
+#[cfg(any(feature = "gzip", feature = "xz", feature = "zstd"))]
+pub mod decompress;
+pub mod detector;
+pub mod diff;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+#[cfg(all(target_os = "linux", feature = "fanotify"))]
+pub mod fanotify;
+#[cfg(feature = "global")]
+pub mod global;
+#[cfg(windows)]
+pub mod handle;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod intern;
+pub mod kind;
+#[cfg(feature = "mail")]
+pub mod mail;
+pub mod media_type;
+#[cfg(feature = "mime")]
+pub mod mime;
+#[cfg(any(
+    feature = "gzip",
+    feature = "xz",
+    feature = "zstd",
+    feature = "zip",
+    feature = "tar"
+))]
+pub mod nested;
+pub mod normalize;
+#[cfg(feature = "object_store")]
+pub mod object;
+pub mod policy;
+pub mod polyglot;
+pub mod prefix;
+pub mod query;
+#[cfg(feature = "rayon")]
+pub mod rayon;
+pub mod report;
+#[cfg(feature = "schemars")]
+pub mod schemars;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod set;
+pub mod short;
+pub mod sniff;
+pub mod spoof;
+pub mod summary;
+pub mod sync;
+pub mod thread_local;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "tower")]
+pub mod tower;
+#[cfg(feature = "blocking")]
+pub mod unblock;
+#[cfg(all(target_os = "linux", feature = "uring"))]
+pub mod uring;
+pub mod version;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(all(target_os = "linux", feature = "xattr"))]
+pub mod xattr;
+
+#[cfg(feature = "test-support")]
+pub mod test_support;
 
 /// Returns the version of the `libmagic` C library as reported by itself.
 ///
 /// # Examples
 /// A version of "5.41" is returned as `541`.
+///
+/// See [`Version`] for a structured version.
 #[doc(alias = "magic_version")]
 pub fn libmagic_version() -> libc::c_int {
     crate::ffi::version()
 }
 
+/// Returns the version of the `libmagic` C library
+///
+/// # Examples
+///
+/// ```
+/// use magic::Version;
+///
+/// if magic::version() >= Version::new(5, 40) {
+///     println!("using libmagic {}", magic::version());
+/// }
+/// ```
+#[doc(alias = "magic_version")]
+pub fn version() -> Version {
+    Version::libmagic()
+}
+
+/// Checks that the `libmagic` C library is at least the `required` version
+///
+/// APIs of this crate that need a newer `libmagic` check this themselves,
+/// this is for applications that rely on the behaviour of a certain version.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// magic::require_version(magic::Version::new(5, 4))?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If `libmagic` is older than `required`, a [`version::UnsupportedLibmagicVersion`] will be returned.
+pub fn require_version(
+    required: Version,
+) -> Result<(), crate::version::UnsupportedLibmagicVersion> {
+    crate::version::require(required)
+}
+
+/// Returns the database paths that [`DatabasePaths::default()`](cookie::DatabasePaths::default) actually loads
+///
+/// This resolves the [`DATABASE_PATHS_ENV`](cookie::DATABASE_PATHS_ENV) environment variable,
+/// a `~/.magic.mgc` database of the user and the default database path `libmagic` was built with, in that order.
+/// Returns an empty `Vec` if `libmagic` does not know a default.
+///
+/// # Examples
+///
+/// ```
+/// for database in magic::default_database_path() {
+///     println!("loading default database {}", database.display());
+/// }
+/// ```
+#[doc(alias = "magic_getpath")]
+pub fn default_database_path() -> Vec<std::path::PathBuf> {
+    match crate::ffi::getpath_load() {
+        None => Vec::new(),
+        Some(filenames) => crate::cookie::split_database_paths(&filenames).collect(),
+    }
+}
+
 /// Functionality for [`Cookie`]
 pub mod cookie {
-    use std::convert::TryFrom;
-    use std::ffi::CString;
+    use std::convert::{TryFrom, TryInto};
+    use std::ffi::{CStr, CString};
     use std::path::Path;
 
     use magic_sys as libmagic;
@@ -480,6 +625,109 @@ pub mod cookie {
         }
     }
 
+    /// Limits for the analysis of a [`Cookie`]
+    ///
+    /// Unlike [`Flags`], parameters have a value, see [`Cookie::set_parameter()`](Cookie::set_parameter)
+    /// and [`Cookie::parameter()`](Cookie::parameter).
+    /// Lower values bound the effort for untrusted input, higher values allow to analyze more unusual files.
+    ///
+    /// NOTE: The parameter descriptions are mostly copied from `man libmagic 3`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum Parameter {
+        /// How many levels of recursion will be followed for indirect magic entries
+        #[doc(alias = "MAGIC_PARAM_INDIR_MAX")]
+        IndirMax,
+        /// The maximum number of calls for name/use
+        #[doc(alias = "MAGIC_PARAM_NAME_MAX")]
+        NameMax,
+        /// How many ELF program sections will be processed
+        #[doc(alias = "MAGIC_PARAM_ELF_PHNUM_MAX")]
+        ElfPhnumMax,
+        /// How many ELF sections will be processed
+        #[doc(alias = "MAGIC_PARAM_ELF_SHNUM_MAX")]
+        ElfShnumMax,
+        /// How many ELF notes will be processed
+        #[doc(alias = "MAGIC_PARAM_ELF_NOTES_MAX")]
+        ElfNotesMax,
+        /// The length limit for regex searches
+        #[doc(alias = "MAGIC_PARAM_REGEX_MAX")]
+        RegexMax,
+        /// The maximum number of bytes to read from a file
+        #[doc(alias = "MAGIC_PARAM_BYTES_MAX")]
+        BytesMax,
+        /// The maximum number of bytes to scan for encoding detection
+        ///
+        /// Available since `libmagic` 5.38.
+        #[doc(alias = "MAGIC_PARAM_ENCODING_MAX")]
+        EncodingMax,
+        /// The maximum size of ELF sections to process
+        ///
+        /// Available since `libmagic` 5.45.
+        #[doc(alias = "MAGIC_PARAM_ELF_SHSIZE_MAX")]
+        ElfShsizeMax,
+    }
+
+    // not defined by all `magic-sys` versions, so that the crate still builds against older `libmagic`
+    const MAGIC_PARAM_ENCODING_MAX: libc::c_int = 7;
+    const MAGIC_PARAM_ELF_SHSIZE_MAX: libc::c_int = 8;
+
+    impl Parameter {
+        /// Returns the first `libmagic` version that provides this parameter
+        ///
+        /// Accessing a parameter with an older `libmagic` returns a [`cookie::ParameterError`](ParameterError)
+        /// of kind [`ParameterErrorKind::UnsupportedParameter`].
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use magic::cookie::Parameter;
+        ///
+        /// if magic::Version::libmagic() >= Parameter::EncodingMax.since() {
+        ///     // safe to use `Parameter::EncodingMax`
+        /// }
+        /// ```
+        pub const fn since(&self) -> crate::version::Version {
+            match self {
+                Self::IndirMax
+                | Self::NameMax
+                | Self::ElfPhnumMax
+                | Self::ElfShnumMax
+                | Self::ElfNotesMax
+                | Self::RegexMax
+                | Self::BytesMax => crate::version::Capability::GetParam.since(),
+                Self::EncodingMax => crate::version::Version::new(5, 38),
+                Self::ElfShsizeMax => crate::version::Version::new(5, 45),
+            }
+        }
+
+        /// Returns the raw parameter if the `libmagic` in use provides it
+        fn check(self) -> Result<libc::c_int, ParameterError> {
+            let version = crate::version::Version::libmagic();
+            if version < self.since() {
+                return Err(ParameterError {
+                    parameter: self,
+                    source: ParameterErrorSource::Unsupported(version),
+                });
+            }
+            Ok(self.raw())
+        }
+
+        fn raw(self) -> libc::c_int {
+            match self {
+                Self::IndirMax => libmagic::MAGIC_PARAM_INDIR_MAX,
+                Self::NameMax => libmagic::MAGIC_PARAM_NAME_MAX,
+                Self::ElfPhnumMax => libmagic::MAGIC_PARAM_ELF_PHNUM_MAX,
+                Self::ElfShnumMax => libmagic::MAGIC_PARAM_ELF_SHNUM_MAX,
+                Self::ElfNotesMax => libmagic::MAGIC_PARAM_ELF_NOTES_MAX,
+                Self::RegexMax => libmagic::MAGIC_PARAM_REGEX_MAX,
+                Self::BytesMax => libmagic::MAGIC_PARAM_BYTES_MAX,
+                Self::EncodingMax => MAGIC_PARAM_ENCODING_MAX,
+                Self::ElfShsizeMax => MAGIC_PARAM_ELF_SHSIZE_MAX,
+            }
+        }
+    }
+
     /// Invalid [`DatabasePaths`]
     ///
     /// This is returned from [`DatabasePaths::new()`](DatabasePaths::new)
@@ -487,12 +735,22 @@ pub mod cookie {
     #[error("invalid database files path")]
     pub struct InvalidDatabasePathError {}
 
+    impl From<std::convert::Infallible> for InvalidDatabasePathError {
+        fn from(never: std::convert::Infallible) -> Self {
+            match never {}
+        }
+    }
+
     /// Magic database file paths
     ///
     /// `libmagic` requires database file paths for certain operations on a [`Cookie`] that must:
     /// - be a valid C string
     /// - not contain ":" (colon), since that is used to separate multiple file paths (on all platforms)
     ///
+    /// On Unix, paths are passed to `libmagic` byte-exact, even if they are not valid UTF-8.
+    /// On Windows, `libmagic` only supports narrow strings, so paths are converted to UTF-8.
+    /// This also applies to the file paths of [`Cookie::file()`](Cookie::file).
+    ///
     /// Those operations are [`Cookie::load()`](Cookie::load), [`Cookie::compile()`](Cookie::compile), [`Cookie::check()`](Cookie::check), [`Cookie::list()`](Cookie::list).\
     /// [`Cookie::file()`](Cookie::file) does not take database file paths but the single file to inspect instead.
     ///
@@ -511,7 +769,8 @@ pub mod cookie {
     /// # use magic::cookie::DatabasePaths;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// // `: DatabasePaths` type annotation is only needed for these examples
-    /// // if you pass it to Cookie::load() etc., Rust will figure it out
+    /// // if you pass it to Cookie::compile() etc., Rust will figure it out
+    /// // Cookie::load() even accepts all of these without converting them first
     ///
     /// // construct default unnamed database paths
     /// let database: DatabasePaths = Default::default();
@@ -544,12 +803,28 @@ pub mod cookie {
     /// # Ok(())
     /// # }
     /// ```
+    #[derive(Debug)]
     pub struct DatabasePaths {
         filenames: Option<CString>,
     }
 
     const DATABASE_FILENAME_SEPARATOR: &str = ":";
 
+    /// Splits colon-separated database `filenames` of `libmagic` into paths, skipping empty ones
+    pub(crate) fn split_database_paths(
+        filenames: &CStr,
+    ) -> impl Iterator<Item = std::path::PathBuf> + '_ {
+        let separator = DATABASE_FILENAME_SEPARATOR.as_bytes()[0];
+        filenames
+            .to_bytes()
+            .split(move |&byte| byte == separator)
+            .filter(|database| !database.is_empty())
+            .map(crate::ffi::path_from_bytes)
+    }
+
+    /// Environment variable that overwrites the default database paths, see [`DatabasePaths::default()`](DatabasePaths::default)
+    pub const DATABASE_PATHS_ENV: &str = "MAGIC";
+
     impl DatabasePaths {
         /// Create a new database paths instance
         ///
@@ -566,13 +841,14 @@ pub mod cookie {
             I: IntoIterator<Item = P>,
             P: AsRef<Path>,
         {
-            // this is not the most efficient nor correct for Windows, but consistent with previous behaviour
-
-            let filename = paths
-                .into_iter()
-                .map(|f| f.as_ref().to_string_lossy().into_owned())
-                .collect::<Vec<String>>()
-                .join(DATABASE_FILENAME_SEPARATOR);
+            // paths are passed byte-exact on Unix, see `ffi::path_bytes()` for other platforms
+            let mut filename = Vec::new();
+            for (index, path) in paths.into_iter().enumerate() {
+                if index > 0 {
+                    filename.extend_from_slice(DATABASE_FILENAME_SEPARATOR.as_bytes());
+                }
+                filename.extend_from_slice(&crate::ffi::path_bytes(path.as_ref()));
+            }
 
             Ok(Self {
                 filenames: match filename.is_empty() {
@@ -581,6 +857,90 @@ pub mod cookie {
                 },
             })
         }
+
+        /// Returns the paths of the [`DATABASE_PATHS_ENV`] environment variable, if it is set
+        ///
+        /// Unlike [`Default::default()`](DatabasePaths::default), this makes the use of the environment explicit,
+        /// e.g. to log the paths before loading them.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// use magic::cookie::DatabasePaths;
+        ///
+        /// let databases = match DatabasePaths::from_env()? {
+        ///     Some(databases) => {
+        ///         println!("using databases from environment: {:?}", databases);
+        ///         databases
+        ///     }
+        ///     None => DatabasePaths::default(),
+        /// };
+        /// let cookie = magic::Cookie::open(Default::default())?.load(databases)?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// If the environment variable is not a valid C string, a [`cookie::InvalidDatabasePathError`](InvalidDatabasePathError) will be returned.
+        pub fn from_env() -> Result<Option<Self>, InvalidDatabasePathError> {
+            match std::env::var_os(DATABASE_PATHS_ENV) {
+                None => Ok(None),
+                Some(value) => Self::try_from(value).map(Some),
+            }
+        }
+
+        /// Returns the default database path that `libmagic` was built with, if the environment does not overwrite it
+        ///
+        /// Unlike [`Default::default()`](DatabasePaths::default), this never silently uses the [`DATABASE_PATHS_ENV`]
+        /// environment variable, e.g. for services in untrusted environments.
+        /// `libmagic` always prefers the environment variable when asked for its default, so if it is set this returns `None`
+        /// instead of the paths from the environment.
+        ///
+        /// Note that this also ignores a `~/.magic.mgc` database of the user, unlike [`Default::default()`](DatabasePaths::default).
+        #[doc(alias = "magic_getpath")]
+        pub fn system_default_ignoring_env() -> Option<Self> {
+            if std::env::var_os(DATABASE_PATHS_ENV).is_some() {
+                return None;
+            }
+
+            crate::ffi::getpath().map(|filenames| Self {
+                filenames: Some(filenames),
+            })
+        }
+
+        /// Returns the explicit paths that [`Default::default()`](DatabasePaths::default) resolves to
+        ///
+        /// Unlike [`Default::default()`](DatabasePaths::default), the paths are resolved right away instead of by [`Cookie::load()`](Cookie::load),
+        /// e.g. to validate them before loading, see [`default_database_path()`](crate::default_database_path).
+        /// Returns `None` if `libmagic` does not know a default.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// use magic::cookie::DatabasePaths;
+        ///
+        /// if let Some(databases) = DatabasePaths::default_resolved() {
+        ///     println!("loading default databases {:?}", databases);
+        /// }
+        /// ```
+        #[doc(alias = "magic_getpath")]
+        pub fn default_resolved() -> Option<Self> {
+            crate::ffi::getpath_load().map(|filenames| Self {
+                filenames: Some(filenames),
+            })
+        }
+
+        /// Returns the paths that [`Cookie::load()`](Cookie::load) loads for these database paths
+        ///
+        /// Default database paths are resolved, see [`default_database_path()`](crate::default_database_path).
+        pub(crate) fn resolve(&self) -> Vec<std::path::PathBuf> {
+            match &self.filenames {
+                Some(filenames) => split_database_paths(filenames).collect(),
+                None => crate::default_database_path(),
+            }
+        }
     }
 
     impl Default for DatabasePaths {
@@ -594,6 +954,8 @@ pub mod cookie {
         /// $ cargo run --example file-ish -- data/tests/rust-logo-128x128-blk.png
         /// ```
         /// This is a feature of `libmagic` itself, not of this Rust crate.
+        /// See [`from_env()`](DatabasePaths::from_env) and [`system_default_ignoring_env()`](DatabasePaths::system_default_ignoring_env)
+        /// for explicit control over the environment variable.
         ///
         /// Note that the `file` CLI (which uses `libmagic`) prints the location of its default database with:
         /// ```shell
@@ -611,6 +973,14 @@ pub mod cookie {
         }
     }
 
+    impl From<&DatabasePaths> for DatabasePaths {
+        fn from(value: &DatabasePaths) -> Self {
+            Self {
+                filenames: value.filenames.clone(),
+            }
+        }
+    }
+
     impl<P: AsRef<std::path::Path>, const N: usize> TryFrom<[P; N]> for DatabasePaths {
         type Error = InvalidDatabasePathError;
 
@@ -667,6 +1037,7 @@ pub mod cookie {
     ///
     /// Most functions on a [`Cookie`] can return an error from `libmagic`,
     /// which unfortunately is not very structured.
+    /// See [`kind()`](Error::kind) for the little structure there is.
     #[derive(thiserror::Error, Debug)]
     #[error("magic cookie error in `libmagic` function {}", .function)]
     pub struct Error {
@@ -675,6 +1046,72 @@ pub mod cookie {
         source: crate::ffi::CookieError,
     }
 
+    impl Error {
+        /// Returns the kind of this error
+        pub fn kind(&self) -> ErrorKind {
+            ErrorKind::of(&self.source)
+        }
+    }
+
+    /// Kind of [`cookie::Error`](Error) or [`cookie::LoadError`](LoadError)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// Error reported by `libmagic`, e.g. an unreadable file or invalid database
+        Libmagic,
+        /// `libmagic` violated its API contract, e.g. by not setting the last error
+        ///
+        /// This indicates a bug in `libmagic` or an incompatible `libmagic` version.
+        ApiViolation,
+        /// The given path contains a NUL byte and can not be passed to `libmagic`
+        ///
+        /// For [`cookie::LoadError`](LoadError) this is an invalid [`DatabasePaths`] conversion.
+        InvalidPath,
+        /// A database file of a [`cookie::LoadError`](LoadError) does not exist
+        ///
+        /// See [`LoadError::database()`](LoadError::database) for the path.
+        DatabaseMissing,
+        /// A database file of a [`cookie::LoadError`](LoadError) exists but can not be read, e.g. due to permissions
+        ///
+        /// See [`LoadError::database()`](LoadError::database) for the path.
+        DatabaseUnreadable,
+        /// The databases of a [`cookie::LoadError`](LoadError) contain no valid magic, e.g. due to a syntax error
+        ///
+        /// The `line` of the error is only known if `libmagic` reports it.
+        /// Details of syntax errors are usually only printed as warnings to stderr by `libmagic`.
+        DatabaseSyntax { line: Option<usize> },
+        /// A compiled database of a [`cookie::LoadError`](LoadError) has a format `version`
+        /// other than the `supported` one of the `libmagic` in use
+        ///
+        /// The database needs to be compiled again with a compatible `libmagic` version.
+        DatabaseVersion { version: u32, supported: u32 },
+        /// A buffer of a [`cookie::LoadError`](LoadError) is no compiled database
+        ///
+        /// See [`LoadError::buffer()`](LoadError::buffer) for the index of the buffer.
+        DatabaseFormat,
+        /// The `libmagic` in use is too old for the operation, see [`version::UnsupportedLibmagicVersion`](crate::version::UnsupportedLibmagicVersion)
+        UnsupportedLibmagicVersion,
+    }
+
+    impl ErrorKind {
+        fn of(error: &crate::ffi::CookieError) -> Self {
+            match error {
+                crate::ffi::CookieError::Libmagic { .. } => Self::Libmagic,
+                crate::ffi::CookieError::ApiViolation { .. } => Self::ApiViolation,
+                crate::ffi::CookieError::InvalidPath(_)
+                | crate::ffi::CookieError::InvalidDatabasePath(_) => Self::InvalidPath,
+                crate::ffi::CookieError::DatabaseHeader(
+                    crate::compiled::HeaderError::Version { version, supported },
+                ) => Self::DatabaseVersion {
+                    version: *version,
+                    supported: *supported,
+                },
+                crate::ffi::CookieError::DatabaseHeader(_) => Self::DatabaseFormat,
+                crate::ffi::CookieError::UnsupportedVersion(_) => Self::UnsupportedLibmagicVersion,
+            }
+        }
+    }
+
     #[doc(hidden)]
     #[derive(Debug)]
     pub enum Open {}
@@ -700,7 +1137,7 @@ pub mod cookie {
     ///
     /// A "cookie" is `libmagic` lingo for a combined configuration of
     /// - [`cookie::Flags`](crate::cookie::Flags)
-    /// - parameters (not implemented yet)
+    /// - [`cookie::Parameter`](crate::cookie::Parameter)s
     /// - loaded datbases, e.g. [`cookie::DatabasePaths`](crate::cookie::DatabasePaths)
     ///
     /// A cookie advances through 2 states: opened, then loaded.
@@ -710,24 +1147,39 @@ pub mod cookie {
     /// let cookie = magic::Cookie::open(magic::cookie::Flags::default())?;
     ///
     /// // advance cookie into loaded state
-    /// let cookie = cookie.load(&magic::cookie::DatabasePaths::default())?;
+    /// let cookie = cookie.load(magic::cookie::DatabasePaths::default())?;
     /// # Ok(())
     /// # }
     /// ```
     ///
+    /// Alternatively, [`Cookie::builder()`](Cookie::builder) opens and loads a cookie in one step.
+    ///
     /// In either state, you can use operations that do not require
     /// already loaded magic databases:
     /// - [`Cookie::load()`](Cookie::load), [`Cookie::load_buffers()`](Cookie::load_buffers) to load databases and transition into the loaded state
-    /// - [`Cookie::set_flags()`](Cookie::set_flags) to overwrite the initial flags given in [`Cookie::open()`](Cookie::open)
+    /// - [`Cookie::set_flags()`](Cookie::set_flags) to overwrite the initial flags given in [`Cookie::open()`](Cookie::open), [`Cookie::flags()`](Cookie::flags) to read them
+    /// - [`Cookie::set_parameter()`](Cookie::set_parameter), [`Cookie::parameter()`](Cookie::parameter) to change and read parameters
     /// - [`Cookie::compile()`](Cookie::compile), [`Cookie::check()`](Cookie::check), [`Cookie::list()`](Cookie::list) to operate on magic database files
+    ///   (or their single file shorthands [`Cookie::compile_path()`](Cookie::compile_path), [`Cookie::check_path()`](Cookie::check_path), [`Cookie::list_path()`](Cookie::list_path))
     ///
     /// Once in the loaded state, you can perform magic "queries":
-    /// - [`Cookie::file()`](Cookie::file), [`Cookie::buffer()`](Cookie::buffer)
+    /// - [`Cookie::file()`](Cookie::file), [`Cookie::buffer()`](Cookie::buffer), [`Cookie::file_handle()`](Cookie::file_handle) (only on Unix)
+    /// - [`Cookie::file_raw()`](Cookie::file_raw), [`Cookie::buffer_raw()`](Cookie::buffer_raw) for descriptions that are not valid UTF-8
+    /// - [`Cookie::file_cstr()`](Cookie::file_cstr) for paths that are C strings already
+    /// - [`Cookie::file_ref()`](Cookie::file_ref), [`Cookie::buffer_ref()`](Cookie::buffer_ref) to borrow results instead of copying them
+    /// - [`Cookie::files()`](Cookie::files), [`Cookie::buffers()`](Cookie::buffers) to analyze many files or buffers in a batch
+    /// - [`Cookie::with_flags()`](Cookie::with_flags) to query with temporary flags
+    ///
+    /// A cookie is [`Send`] and [`Sync`], e.g. it can be shared in an [`Arc`](std::sync::Arc) across threads.
+    /// `libmagic` cookies can not be used concurrently though, so operations on a shared cookie are serialized by an internal lock.
+    /// For parallel queries, use one cookie per thread instead.
+    /// To serialize a sequence of operations, see [`sync::SharedCookie`](crate::sync::SharedCookie).
     #[derive(Debug)]
     #[doc(alias = "magic_t")]
     #[doc(alias = "magic_set")]
     pub struct Cookie<S: State> {
-        cookie: crate::ffi::Cookie,
+        cookie: std::sync::Mutex<crate::ffi::Cookie>,
+        hooks: crate::hook::Hooks,
         marker: std::marker::PhantomData<S>,
     }
 
@@ -738,16 +1190,15 @@ pub mod cookie {
     /// # Examples
     ///
     /// ```
-    /// # use std::convert::TryInto;
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// let cookie = magic::Cookie::open(Default::default())?;
-    /// let database = "data/tests/db-images-png".try_into()?;
+    /// let database = "data/tests/db-images-png";
     /// // try to load an existing database, consuming and returning early
-    /// let cookie = cookie.load(&database)?;
+    /// let cookie = cookie.load(database)?;
     ///
-    /// let database = "doesntexist.mgc".try_into()?;
+    /// let database = "doesntexist.mgc";
     /// // load a database that does not exist
-    /// let cookie = match cookie.load(&database) {
+    /// let cookie = match cookie.load(database) {
     ///     Err(err) => {
     ///         println!("whoopsie: {:?}", err);
     ///         // recover the loaded cookie without dropping it
@@ -756,9 +1207,9 @@ pub mod cookie {
     ///     Ok(cookie) => cookie,
     /// };
     ///
-    /// let database = "data/tests/db-python".try_into()?;
+    /// let database = "data/tests/db-python";
     /// // try to load another existing database
-    /// let cookie = cookie.load(&database)?;
+    /// let cookie = cookie.load(database)?;
     /// # Ok(())
     /// # }
     /// ```
@@ -768,21 +1219,184 @@ pub mod cookie {
         function: &'static str,
         //#[backtrace]
         source: crate::ffi::CookieError,
+        kind: ErrorKind,
+        database: Option<std::path::PathBuf>,
+        buffer: Option<usize>,
         cookie: Cookie<S>,
     }
 
     impl<S: State> LoadError<S> {
+        fn new(
+            function: &'static str,
+            source: crate::ffi::CookieError,
+            databases: Option<&DatabasePaths>,
+            cookie: Cookie<S>,
+        ) -> Self {
+            let (kind, database) = classify_load(&source, databases);
+            Self {
+                function,
+                source,
+                kind,
+                database,
+                buffer: None,
+                cookie,
+            }
+        }
+
+        /// Returns the kind of this error
+        ///
+        /// Errors of `libmagic` are classified by its explanation and by checking the database files,
+        /// e.g. as [`ErrorKind::DatabaseMissing`]. Unclassified errors are of kind [`ErrorKind::Libmagic`].
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+
+        /// Returns the path of the database file that caused this error, if known
+        ///
+        /// This is known for kinds [`ErrorKind::DatabaseMissing`] and [`ErrorKind::DatabaseUnreadable`],
+        /// and for [`ErrorKind::DatabaseVersion`] if `libmagic` reports it.
+        pub fn database(&self) -> Option<&Path> {
+            self.database.as_deref()
+        }
+
+        /// Returns the index of the buffer that caused this error within [`Cookie::load_buffers()`](Cookie::load_buffers), if known
+        ///
+        /// This is known if the header of a buffer is invalid, see [`compiled::Header`](crate::compiled::Header).
+        pub fn buffer(&self) -> Option<usize> {
+            self.buffer
+        }
+
         /// Returns the cookie in its original state
         pub fn cookie(self) -> Cookie<S> {
             self.cookie
         }
     }
 
+    impl<S: State> Cookie<S> {
+        /// Locks the raw cookie for an operation
+        fn lock(&self) -> std::sync::MutexGuard<'_, crate::ffi::Cookie> {
+            // the `libmagic` calls do not panic, and poisoning would not leave any Rust state inconsistent anyway
+            self.cookie
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+        }
+
+        /// Returns the raw cookie without locking, since `self` is exclusively borrowed
+        fn cookie_mut(&mut self) -> &mut crate::ffi::Cookie {
+            self.cookie
+                .get_mut()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+        }
+    }
+
+    /// Classifies the error of loading `databases` into a kind and the causing database file
+    ///
+    /// `libmagic` reports most failures as "could not find any valid magic files!", so the database files are checked as well.
+    /// Without `databases`, e.g. for buffers, only the explanation is classified.
+    fn classify_load(
+        source: &crate::ffi::CookieError,
+        databases: Option<&DatabasePaths>,
+    ) -> (ErrorKind, Option<std::path::PathBuf>) {
+        let explanation = match source {
+            crate::ffi::CookieError::Libmagic { explanation, .. } => explanation.to_string_lossy(),
+            _ => return (ErrorKind::of(source), None),
+        };
+
+        // e.g. "File 5.44 supports only version 18 magic files. `foo.mgc' is version 16"
+        let number_after = |pattern| crate::compiled::number_after(&explanation, pattern);
+        if let (Some(supported), Some(version)) = (
+            number_after("supports only version "),
+            number_after("' is version "),
+        ) {
+            let database = explanation
+                .find('`')
+                .and_then(|start| {
+                    let end = explanation.rfind("' is version ")?;
+                    explanation.get(start + 1..end)
+                })
+                // buffers are all named "buffer"
+                .filter(|database| databases.is_some() && !database.is_empty())
+                .map(std::path::PathBuf::from);
+            return (ErrorKind::DatabaseVersion { version, supported }, database);
+        }
+
+        // e.g. "line 12: ..." for errors within a database file
+        if explanation.starts_with("line ") {
+            if let Some(line) = number_after("line ") {
+                return (
+                    ErrorKind::DatabaseSyntax {
+                        line: Some(line as usize),
+                    },
+                    None,
+                );
+            }
+        }
+
+        let filenames = match databases {
+            Some(DatabasePaths {
+                filenames: Some(filenames),
+            }) => Some(filenames.clone()),
+            Some(DatabasePaths { filenames: None }) => crate::ffi::getpath(),
+            None => None,
+        };
+        let filenames = match filenames {
+            Some(filenames) => filenames,
+            None => return (ErrorKind::Libmagic, None),
+        };
+        for database in split_database_paths(&filenames) {
+            // `libmagic` prefers the compiled database with a ".mgc" suffix, if it exists
+            let mut compiled = database.clone().into_os_string();
+            compiled.push(".mgc");
+            let database = match std::fs::metadata(&compiled) {
+                Ok(_) => std::path::PathBuf::from(compiled),
+                Err(_) => database,
+            };
+            match std::fs::metadata(&database) {
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    return (ErrorKind::DatabaseMissing, Some(database))
+                }
+                Err(_) => return (ErrorKind::DatabaseUnreadable, Some(database)),
+                Ok(metadata) if metadata.is_dir() => {
+                    if std::fs::read_dir(&database).is_err() {
+                        return (ErrorKind::DatabaseUnreadable, Some(database));
+                    }
+                }
+                Ok(_) => {
+                    if std::fs::File::open(&database).is_err() {
+                        return (ErrorKind::DatabaseUnreadable, Some(database));
+                    }
+                }
+            }
+        }
+
+        if explanation.contains("could not find any valid magic files") {
+            (ErrorKind::DatabaseSyntax { line: None }, None)
+        } else {
+            (ErrorKind::Libmagic, None)
+        }
+    }
+
+    /// Converts a result of `libmagic` without copying it again, unless it is invalid UTF-8
+    fn into_string(result: Vec<u8>) -> String {
+        String::from_utf8(result)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+    }
+
+    /// Converts a borrowed result of `libmagic`, only copying it if it is invalid UTF-8 or there are `hooks`
+    fn borrow_str<'a>(result: &'a CStr, hooks: &crate::hook::Hooks) -> std::borrow::Cow<'a, str> {
+        let result = String::from_utf8_lossy(result.to_bytes());
+        if hooks.is_empty() {
+            result
+        } else {
+            std::borrow::Cow::Owned(hooks.apply(result.into_owned()))
+        }
+    }
+
     impl<S: State> Drop for Cookie<S> {
         /// Closes the loaded magic database files and deallocates any resources used
         #[doc(alias = "magic_close")]
         fn drop(&mut self) {
-            crate::ffi::close(&mut self.cookie);
+            crate::ffi::close(self.cookie_mut());
         }
     }
 
@@ -815,8 +1429,19 @@ pub mod cookie {
         /// If there was an `libmagic` internal error allocating a new cookie, a [`cookie::OpenError`](OpenError) will be returned.
         ///
         /// If the given `flags` are unsupported on the current platform, a [`cookie::OpenError`](OpenError) will be returned.
+        ///
+        /// If the given `flags` need a newer `libmagic`, e.g. [`Flags::EXTENSION`], a [`cookie::OpenError`](OpenError)
+        /// with an [`OpenError::unsupported_version()`](OpenError::unsupported_version) will be returned.
         #[doc(alias = "magic_open")]
         pub fn open(flags: Flags) -> Result<Cookie<Open>, OpenError> {
+            if let Err(err) = require_flags(flags) {
+                return Err(OpenError {
+                    flags,
+                    kind: OpenErrorKind::UnsupportedVersion,
+                    source: OpenErrorSource::UnsupportedVersion(err),
+                });
+            }
+
             match crate::ffi::open(flags.bits()) {
                 Err(err) => Err(OpenError {
                     flags,
@@ -824,11 +1449,12 @@ pub mod cookie {
                         std::io::ErrorKind::InvalidInput => OpenErrorKind::UnsupportedFlags,
                         _ => OpenErrorKind::Errno,
                     },
-                    source: err,
+                    source: OpenErrorSource::Libmagic(err),
                 }),
                 Ok(cookie) => {
                     let cookie = Cookie {
-                        cookie,
+                        cookie: std::sync::Mutex::new(cookie),
+                        hooks: Default::default(),
                         marker: std::marker::PhantomData,
                     };
                     Ok(cookie)
@@ -850,7 +1476,7 @@ pub mod cookie {
         /// ```
         /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
         /// // open a new cookie with default flags and database
-        /// let cookie = magic::Cookie::open(Default::default())?.load(&Default::default())?;
+        /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
         ///
         /// let file_description = cookie.file("data/tests/rust-logo-128x128-blk.png");
         /// # Ok(())
@@ -861,14 +1487,18 @@ pub mod cookie {
         ///
         /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
         ///
-        /// # Panics
-        ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error.
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
         #[doc(alias = "magic_file")]
         pub fn file<P: AsRef<Path>>(&self, filename: P) -> Result<String, Error> {
-            let c_string = CString::new(filename.as_ref().to_string_lossy().into_owned()).unwrap();
-            match crate::ffi::file(&self.cookie, c_string.as_c_str()) {
-                Ok(res) => Ok(res.to_string_lossy().to_string()),
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_file",
+                source: err,
+            })?;
+            // the lock is released before the hooks run
+            let res = crate::ffi::file(&self.lock(), c_string.as_c_str());
+            match res {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
                 Err(err) => Err(Error {
                     function: "magic_file",
                     source: err,
@@ -885,7 +1515,7 @@ pub mod cookie {
         /// ```
         /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
         /// // open a new cookie with default flags and database
-        /// let cookie = magic::Cookie::open(Default::default())?.load(&Default::default())?;
+        /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
         ///
         /// let buffer = b"%PDF-\xE2\x80\xA6";
         /// let buffer_description = cookie.buffer(buffer);
@@ -897,138 +1527,654 @@ pub mod cookie {
         ///
         /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
         ///
-        /// # Panics
-        ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error.
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
         #[doc(alias = "magic_buffer")]
         pub fn buffer(&self, buffer: &[u8]) -> Result<String, Error> {
-            match crate::ffi::buffer(&self.cookie, buffer) {
-                Ok(res) => Ok(res.to_string_lossy().to_string()),
+            // the lock is released before the hooks run
+            let res = crate::ffi::buffer(&self.lock(), buffer);
+            match res {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
                 Err(err) => Err(Error {
                     function: "magic_buffer",
                     source: err,
                 }),
             }
         }
-    }
 
-    /// Operations that are valid in any state
-    impl<S: State> Cookie<S> {
-        /// Loads the given database `filenames` for further queries
-        ///
-        /// Adds ".mgc" to the database filenames as appropriate.
+        /// Returns the textual descriptions of the contents of all `buffers`, in the same order
         ///
-        /// Calling `load()` or [`load_buffers()`](Cookie::load_buffers) replaces the previously loaded database/s.
+        /// Each result is the same as that of [`buffer()`](Cookie::buffer), and an error for one buffer does not stop the others.
+        /// Unlike calling [`buffer()`](Cookie::buffer) in a loop, the cookie is locked only once for all buffers,
+        /// e.g. for services that classify many payloads per request. See [`files()`](Cookie::files) for files.
         ///
-        /// This is equivalent to the using the `file` CLI:
-        /// ```shell
-        /// $ file --magic-file 'data/tests/db-images-png:data/tests/db-python' --version
-        /// file-5.39
-        /// magic file from data/tests/db-images-png:data/tests/db-python
-        /// ```
+        /// Requires to [`load()`](Cookie::load) databases before calling.
         ///
         /// # Examples
-        /// ```rust
-        /// # use std::convert::TryInto;
-        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-        /// // open a new cookie with default flags
-        /// let cookie = magic::Cookie::open(Default::default())?;
-        ///
-        /// // load the default unnamed database
-        /// let database = Default::default();
-        /// let cookie = cookie.load(&database)?;
         ///
-        /// // load databases from files
-        /// let databases = ["data/tests/db-images-png", "data/tests/db-python"].try_into()?;
-        /// let cookie = cookie.load(&databases)?;
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with default flags and database
+        /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
         ///
-        /// // load precompiled database from file
-        /// let database = "data/tests/db-images-png-precompiled.mgc".try_into()?;
-        /// let cookie = cookie.load(&database)?;
+        /// let payloads: [&[u8]; 2] = [b"%PDF-\xE2\x80\xA6", b"\x89PNG\r\n\x1a\n"];
+        /// for result in cookie.buffers(&payloads) {
+        ///     println!("{}", result?);
+        /// }
         /// # Ok(())
         /// # }
         /// ```
-        ///
-        /// # Errors
-        ///
-        /// If there was an `libmagic` internal error, a [`cookie::LoadError`](LoadError) will be returned,
-        /// which contains the cookie in its original state.
-        ///
-        /// # Panics
-        ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
-        #[doc(alias = "magic_load")]
-        #[doc(alias = "--magic-file")]
-        pub fn load(self, filenames: &DatabasePaths) -> Result<Cookie<Load>, LoadError<S>> {
-            match crate::ffi::load(&self.cookie, filenames.filenames.as_deref()) {
-                Err(err) => Err(LoadError {
-                    function: "magic_load",
-                    source: err,
-                    cookie: self,
-                }),
-                Ok(_) => {
-                    let mut cookie = std::mem::ManuallyDrop::new(self);
-
-                    let cookie = Cookie {
-                        cookie: crate::ffi::Cookie::new(&mut cookie.cookie),
-                        marker: std::marker::PhantomData,
-                    };
-                    Ok(cookie)
-                }
-            }
+        #[doc(alias = "magic_buffer")]
+        pub fn buffers(&self, buffers: &[&[u8]]) -> Vec<Result<String, Error>> {
+            let results: Vec<_> = {
+                let cookie = self.lock();
+                buffers
+                    .iter()
+                    .map(|buffer| crate::ffi::buffer(&cookie, buffer))
+                    .collect()
+            };
+            // the lock is released before the hooks run
+            results
+                .into_iter()
+                .map(|res| match res {
+                    Ok(res) => Ok(self.hooks.apply(into_string(res))),
+                    Err(err) => Err(Error {
+                        function: "magic_buffer",
+                        source: err,
+                    }),
+                })
+                .collect()
         }
 
-        /// Loads the given compiled databases `buffers` for further queries
+        /// Returns a textual description of the contents of the file `filename` given as C string
         ///
-        /// Databases need to be compiled with a compatible `libmagic` version.
+        /// Unlike [`file()`](Cookie::file), the path is passed to `libmagic` as is,
+        /// without converting and allocating it for every call, e.g. for hot loops over cached paths.
+        /// [`buffer()`](Cookie::buffer) needs no such variant, since it passes its buffer as is already.
         ///
-        /// This function can be used in environments where `libmagic` does
-        /// not have direct access to the filesystem, but can access the magic
-        /// database via shared memory or other IPC means.
+        /// Requires to [`load()`](Cookie::load) databases before calling.
         ///
-        /// Calling `load_buffers()` or [`load()`](Cookie::load) replaces the previously loaded database/s.
+        /// # Examples
         ///
-        /// # Errors
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with default flags and database
+        /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
         ///
-        /// If there was an `libmagic` internal error, a [`cookie::LoadError`](LoadError) will be returned,
-        /// which contains the cookie in its original state.
+        /// let filename = std::ffi::CString::new("data/tests/rust-logo-128x128-blk.png")?;
+        /// for _ in 0..3 {
+        ///     let file_description = cookie.file_cstr(&filename)?;
+        /// }
+        /// # Ok(())
+        /// # }
+        /// ```
         ///
-        /// # Panics
+        /// # Errors
         ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
-        #[doc(alias = "magic_load_buffers")]
-        pub fn load_buffers(self, buffers: &[&[u8]]) -> Result<Cookie<Load>, LoadError<S>> {
-            match crate::ffi::load_buffers(&self.cookie, buffers) {
-                Err(err) => Err(LoadError {
-                    function: "magic_load_buffers",
+        /// See [`file()`](Cookie::file).
+        #[doc(alias = "magic_file")]
+        pub fn file_cstr(&self, filename: &CStr) -> Result<String, Error> {
+            // the lock is released before the hooks run
+            let res = crate::ffi::file(&self.lock(), filename);
+            match res {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
+                Err(err) => Err(Error {
+                    function: "magic_file",
                     source: err,
-                    cookie: self,
                 }),
-                Ok(_) => {
-                    let mut cookie = std::mem::ManuallyDrop::new(self);
-
-                    let cookie = Cookie {
-                        cookie: crate::ffi::Cookie::new(&mut cookie.cookie),
-                        marker: std::marker::PhantomData,
-                    };
-                    Ok(cookie)
-                }
             }
         }
 
-        /// Sets the `flags` to use for this configuration
+        /// Returns a textual description of the contents of the file `filename`, borrowed from the cookie
         ///
-        /// Overwrites any previously set flags, e.g. those from [`load()`](Cookie::load).
+        /// Unlike [`file()`](Cookie::file), the result is not copied but borrowed from `libmagic`,
+        /// which keeps it until the next analysis. The exclusive borrow of the cookie ensures that.
+        /// This saves an allocation per analysis, e.g. for high-throughput scanners.
+        /// The result is only copied if it is invalid UTF-8 or hooks added with [`add_hook()`](Cookie::add_hook) rewrite it.
+        ///
+        /// Requires to [`load()`](Cookie::load) databases before calling.
         ///
         /// # Examples
-        /// ```rust
+        ///
+        /// ```
         /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-        /// // open a new cookie with initial default flags
-        /// let cookie = magic::Cookie::open(Default::default())?;
+        /// // open a new cookie with default flags and database
+        /// let mut cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
         ///
-        /// // overwrite the initial flags
-        /// let flags = magic::cookie::Flags::COMPRESS | magic::cookie::Flags::DEVICES;
-        /// cookie.set_flags(flags)?;
+        /// for filename in ["data/tests/rust-logo-128x128-blk.png", "Cargo.toml"] {
+        ///     let file_description = cookie.file_ref(filename)?;
+        ///     println!("{}: {}", filename, file_description);
+        /// }
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// See [`file()`](Cookie::file).
+        #[doc(alias = "magic_file")]
+        pub fn file_ref<P: AsRef<Path>>(
+            &mut self,
+            filename: P,
+        ) -> Result<std::borrow::Cow<'_, str>, Error> {
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_file",
+                source: err,
+            })?;
+            // not using `cookie_mut()`, since the hooks are borrowed as well
+            let cookie = self
+                .cookie
+                .get_mut()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            match crate::ffi::file_ref(cookie, c_string.as_c_str()) {
+                Ok(res) => Ok(borrow_str(res, &self.hooks)),
+                Err(err) => Err(Error {
+                    function: "magic_file",
+                    source: err,
+                }),
+            }
+        }
+
+        /// Returns a textual description of the contents of the `buffer`, borrowed from the cookie
+        ///
+        /// See [`file_ref()`](Cookie::file_ref) for how the result is borrowed.
+        ///
+        /// Requires to [`load()`](Cookie::load) databases before calling.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with default flags and database
+        /// let mut cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+        ///
+        /// let buffer = b"%PDF-\xE2\x80\xA6";
+        /// let buffer_description = cookie.buffer_ref(buffer)?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// See [`buffer()`](Cookie::buffer).
+        #[doc(alias = "magic_buffer")]
+        pub fn buffer_ref(&mut self, buffer: &[u8]) -> Result<std::borrow::Cow<'_, str>, Error> {
+            // not using `cookie_mut()`, since the hooks are borrowed as well
+            let cookie = self
+                .cookie
+                .get_mut()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            match crate::ffi::buffer_ref(cookie, buffer) {
+                Ok(res) => Ok(borrow_str(res, &self.hooks)),
+                Err(err) => Err(Error {
+                    function: "magic_buffer",
+                    source: err,
+                }),
+            }
+        }
+
+        /// Returns the description of the contents of the file `filename` as raw bytes
+        ///
+        /// Unlike [`file()`](Cookie::file), invalid UTF-8 is not replaced,
+        /// e.g. for descriptions with unprintable characters of [`Flags::RAW`].
+        /// Hooks added with [`add_hook()`](Cookie::add_hook) are not applied, since they operate on text.
+        ///
+        /// Requires to [`load()`](Cookie::load) databases before calling.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with raw flags and default database
+        /// let cookie = magic::Cookie::open(magic::cookie::Flags::RAW)?.load(magic::cookie::DatabasePaths::default())?;
+        ///
+        /// let file_description: Vec<u8> = cookie.file_raw("data/tests/rust-logo-128x128-blk.png")?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// See [`file()`](Cookie::file).
+        #[doc(alias = "magic_file")]
+        pub fn file_raw<P: AsRef<Path>>(&self, filename: P) -> Result<Vec<u8>, Error> {
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_file",
+                source: err,
+            })?;
+            crate::ffi::file(&self.lock(), c_string.as_c_str()).map_err(|err| Error {
+                function: "magic_file",
+                source: err,
+            })
+        }
+
+        /// Returns the description of the contents of the `buffer` as raw bytes
+        ///
+        /// Unlike [`buffer()`](Cookie::buffer), invalid UTF-8 is not replaced,
+        /// e.g. for descriptions with unprintable characters of [`Flags::RAW`].
+        /// Hooks added with [`add_hook()`](Cookie::add_hook) are not applied, since they operate on text.
+        ///
+        /// Requires to [`load()`](Cookie::load) databases before calling.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with raw flags and default database
+        /// let cookie = magic::Cookie::open(magic::cookie::Flags::RAW)?.load(magic::cookie::DatabasePaths::default())?;
+        ///
+        /// let buffer = b"%PDF-\xE2\x80\xA6";
+        /// let buffer_description: Vec<u8> = cookie.buffer_raw(buffer)?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// See [`buffer()`](Cookie::buffer).
+        #[doc(alias = "magic_buffer")]
+        pub fn buffer_raw(&self, buffer: &[u8]) -> Result<Vec<u8>, Error> {
+            crate::ffi::buffer(&self.lock(), buffer).map_err(|err| Error {
+                function: "magic_buffer",
+                source: err,
+            })
+        }
+
+        /// Returns a textual description of the contents of the open file `file`
+        ///
+        /// This accepts anything with a raw file descriptor, e.g. a [`File`](std::fs::File), a pipe, a socket or a `BorrowedFd`.
+        /// Unlike [`file()`](Cookie::file), the file is not looked up by path again, so it can not be swapped in between.
+        ///
+        /// `libmagic` reads from the current position of the file descriptor and restores it afterwards for seekable files.
+        /// Data read from pipes or sockets is consumed. The file descriptor is not closed.
+        ///
+        /// Requires to [`load()`](Cookie::load) databases before calling.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with default flags and database
+        /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+        ///
+        /// let file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+        /// let file_description = cookie.file_handle(&file);
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
+        #[cfg(unix)]
+        #[doc(alias = "magic_descriptor")]
+        pub fn file_handle<F: std::os::unix::io::AsRawFd>(
+            &self,
+            file: &F,
+        ) -> Result<String, Error> {
+            // the lock is released before the hooks run
+            let res = crate::ffi::descriptor(&self.lock(), file.as_raw_fd());
+            match res {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
+                Err(err) => Err(Error {
+                    function: "magic_descriptor",
+                    source: err,
+                }),
+            }
+        }
+
+        /// Returns a textual description of the contents of the standard input, like `file -`
+        ///
+        /// `libmagic` reads from the standard input file descriptor directly, so data that was already read
+        /// into the buffer of [`std::io::stdin()`] is not analyzed. Data read from a pipe is consumed.
+        ///
+        /// Requires to [`load()`](Cookie::load) databases before calling.
+        ///
+        /// # Examples
+        ///
+        /// ```no_run
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with default flags and database
+        /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+        ///
+        /// // e.g. `cat data/tests/rust-logo-128x128-blk.png | program`
+        /// let stdin_description = cookie.stdin()?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
+        #[doc(alias = "magic_descriptor")]
+        pub fn stdin(&self) -> Result<String, Error> {
+            // `STDIN_FILENO`, which is also the standard input of the CRT on Windows
+            const STDIN_FILENO: libc::c_int = 0;
+
+            // the lock is released before the hooks run
+            let res = crate::ffi::descriptor(&self.lock(), STDIN_FILENO);
+            match res {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
+                Err(err) => Err(Error {
+                    function: "magic_descriptor",
+                    source: err,
+                }),
+            }
+        }
+
+        /// Applies `flags` until the returned guard is dropped, which restores the previous flags
+        ///
+        /// The cookie stays locked for the lifetime of the guard, so other threads sharing the cookie
+        /// neither see the temporary flags nor change them in the meantime, but block until the guard is dropped.
+        /// Use the [`file()`](FlagsGuard::file) and [`buffer()`](FlagsGuard::buffer) functions of the guard for queries
+        /// and its [`flags()`](FlagsGuard::flags) for the temporary flags.
+        ///
+        /// The lock is not reentrant: calling any function of the cookie on the same thread while the guard is alive,
+        /// e.g. [`flags()`](Cookie::flags) or [`file()`](Cookie::file), deadlocks or panics.
+        ///
+        /// # Examples
+        ///
+        /// ```
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// use magic::cookie::Flags;
+        ///
+        /// let cookie = magic::Cookie::open(Flags::ERROR)?.load(["data/tests/db-images-png"])?;
+        ///
+        /// let mime = cookie
+        ///     .with_flags(Flags::ERROR | Flags::MIME_TYPE)?
+        ///     .file("data/tests/rust-logo-128x128-blk.png")?;
+        /// assert_eq!(mime, "image/png");
+        ///
+        /// // the previous flags are restored
+        /// assert_eq!(cookie.flags(), Flags::ERROR);
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// See [`set_flags()`](Cookie::set_flags).
+        pub fn with_flags(&self, flags: Flags) -> Result<FlagsGuard<'_>, SetFlagsError> {
+            self.apply_flags(|_| flags)
+        }
+
+        /// Like [`with_flags()`](Cookie::with_flags), but adds `flags` to the current ones
+        pub(crate) fn with_added_flags(
+            &self,
+            flags: Flags,
+        ) -> Result<FlagsGuard<'_>, SetFlagsError> {
+            self.apply_flags(|previous| previous | flags)
+        }
+
+        /// Locks the cookie with its current flags until the returned guard is dropped, see [`with_flags()`](Cookie::with_flags)
+        ///
+        /// Unlike [`with_added_flags()`](Cookie::with_added_flags), this neither changes the flags nor fails.
+        pub(crate) fn with_current_flags(&self) -> FlagsGuard<'_> {
+            let cookie = self.lock();
+            let previous = cookie.flags();
+            FlagsGuard {
+                cookie,
+                hooks: &self.hooks,
+                flags: Flags::from_bits_retain(previous),
+                previous,
+            }
+        }
+
+        /// Applies the flags returned for the previous ones, see [`with_flags()`](Cookie::with_flags)
+        fn apply_flags<F: FnOnce(Flags) -> Flags>(
+            &self,
+            f: F,
+        ) -> Result<FlagsGuard<'_>, SetFlagsError> {
+            let mut cookie = self.lock();
+            let previous = cookie.flags();
+            let flags = f(Flags::from_bits_retain(previous));
+            if let Err(err) = require_flags(flags) {
+                return Err(SetFlagsError {
+                    flags,
+                    source: SetFlagsErrorSource::UnsupportedVersion(err),
+                });
+            }
+
+            if let Err(err) = crate::ffi::setflags(&mut cookie, flags.bits()) {
+                return Err(SetFlagsError {
+                    flags: Flags::PRESERVE_ATIME,
+                    source: SetFlagsErrorSource::Libmagic(err),
+                });
+            }
+            Ok(FlagsGuard {
+                cookie,
+                hooks: &self.hooks,
+                flags,
+                previous,
+            })
+        }
+    }
+
+    /// Guard of temporarily applied flags, see [`Cookie::with_flags()`](Cookie::with_flags)
+    ///
+    /// Restores the previous flags when dropped.
+    #[derive(Debug)]
+    pub struct FlagsGuard<'c> {
+        cookie: std::sync::MutexGuard<'c, crate::ffi::Cookie>,
+        hooks: &'c crate::hook::Hooks,
+        flags: Flags,
+        previous: libc::c_int,
+    }
+
+    impl FlagsGuard<'_> {
+        /// Returns the temporary flags
+        ///
+        /// Use this instead of [`Cookie::flags()`](Cookie::flags), which deadlocks while the guard is alive.
+        pub fn flags(&self) -> Flags {
+            self.flags
+        }
+
+        /// Returns a textual description of the contents of the file `filename` with the temporary flags
+        ///
+        /// See [`Cookie::file()`](Cookie::file).
+        ///
+        /// # Errors
+        ///
+        /// See [`Cookie::file()`](Cookie::file).
+        #[doc(alias = "magic_file")]
+        pub fn file<P: AsRef<Path>>(&self, filename: P) -> Result<String, Error> {
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_file",
+                source: err,
+            })?;
+            match crate::ffi::file(&self.cookie, c_string.as_c_str()) {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
+                Err(err) => Err(Error {
+                    function: "magic_file",
+                    source: err,
+                }),
+            }
+        }
+
+        /// Returns a textual description of the contents of the `buffer` with the temporary flags
+        ///
+        /// See [`Cookie::buffer()`](Cookie::buffer).
+        ///
+        /// # Errors
+        ///
+        /// See [`Cookie::buffer()`](Cookie::buffer).
+        #[doc(alias = "magic_buffer")]
+        pub fn buffer(&self, buffer: &[u8]) -> Result<String, Error> {
+            match crate::ffi::buffer(&self.cookie, buffer) {
+                Ok(res) => Ok(self.hooks.apply(into_string(res))),
+                Err(err) => Err(Error {
+                    function: "magic_buffer",
+                    source: err,
+                }),
+            }
+        }
+    }
+
+    impl Drop for FlagsGuard<'_> {
+        fn drop(&mut self) {
+            // the previous flags were accepted by `libmagic` before
+            let _ = crate::ffi::setflags(&mut self.cookie, self.previous);
+        }
+    }
+
+    /// Operations that are valid in any state
+    impl<S: State> Cookie<S> {
+        /// Loads the given database `filenames` for further queries
+        ///
+        /// Adds ".mgc" to the database filenames as appropriate.
+        ///
+        /// `filenames` can be anything convertible to [`DatabasePaths`], e.g. a single path or an array of paths.
+        ///
+        /// Calling `load()` or [`load_buffers()`](Cookie::load_buffers) replaces the previously loaded database/s.
+        ///
+        /// This is equivalent to the using the `file` CLI:
+        /// ```shell
+        /// $ file --magic-file 'data/tests/db-images-png:data/tests/db-python' --version
+        /// file-5.39
+        /// magic file from data/tests/db-images-png:data/tests/db-python
+        /// ```
+        ///
+        /// # Examples
+        /// ```rust
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with default flags
+        /// let cookie = magic::Cookie::open(Default::default())?;
+        ///
+        /// // load the default unnamed database
+        /// let database = magic::cookie::DatabasePaths::default();
+        /// let cookie = cookie.load(database)?;
+        ///
+        /// // load databases from files
+        /// let databases = ["data/tests/db-images-png", "data/tests/db-python"];
+        /// let cookie = cookie.load(databases)?;
+        ///
+        /// // load precompiled database from file
+        /// let database = "data/tests/db-images-png-precompiled.mgc";
+        /// let cookie = cookie.load(database)?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::LoadError`](LoadError) will be returned,
+        /// which contains the cookie in its original state.
+        ///
+        /// If `filenames` can not be converted to [`DatabasePaths`],
+        /// a [`cookie::LoadError`](LoadError) of kind [`ErrorKind::InvalidPath`] will be returned.
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::LoadError`](LoadError) of kind [`ErrorKind::ApiViolation`] will be returned.
+        #[doc(alias = "magic_load")]
+        #[doc(alias = "--magic-file")]
+        pub fn load<D>(self, filenames: D) -> Result<Cookie<Load>, LoadError<S>>
+        where
+            D: TryInto<DatabasePaths>,
+            D::Error: Into<InvalidDatabasePathError>,
+        {
+            let filenames = match filenames.try_into() {
+                Ok(filenames) => filenames,
+                Err(err) => {
+                    return Err(LoadError::new(
+                        "magic_load",
+                        crate::ffi::CookieError::InvalidDatabasePath(err.into()),
+                        None,
+                        self,
+                    ))
+                }
+            };
+
+            let res = crate::ffi::load(&self.lock(), filenames.filenames.as_deref());
+            match res {
+                Err(err) => Err(LoadError::new("magic_load", err, Some(&filenames), self)),
+                Ok(_) => {
+                    let mut cookie = std::mem::ManuallyDrop::new(self);
+
+                    let cookie = Cookie {
+                        cookie: std::sync::Mutex::new(crate::ffi::Cookie::new(cookie.cookie_mut())),
+                        hooks: std::mem::take(&mut cookie.hooks),
+                        marker: std::marker::PhantomData,
+                    };
+                    Ok(cookie)
+                }
+            }
+        }
+
+        /// Loads the given compiled databases `buffers` for further queries
+        ///
+        /// Databases need to be compiled with a compatible `libmagic` version.
+        ///
+        /// This function can be used in environments where `libmagic` does
+        /// not have direct access to the filesystem, but can access the magic
+        /// database via shared memory or other IPC means.
+        ///
+        /// Calling `load_buffers()` or [`load()`](Cookie::load) replaces the previously loaded database/s.
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::LoadError`](LoadError) will be returned,
+        /// which contains the cookie in its original state.
+        ///
+        /// If the header of a buffer is invalid, a [`cookie::LoadError`](LoadError) of kind [`ErrorKind::DatabaseFormat`]
+        /// or [`ErrorKind::DatabaseVersion`] will be returned without calling `libmagic`,
+        /// with the index of the first invalid buffer in [`LoadError::buffer()`](LoadError::buffer).
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::LoadError`](LoadError) of kind [`ErrorKind::ApiViolation`] will be returned.
+        ///
+        /// If `libmagic` is older than 5.20, which lacks `magic_load_buffers()`,
+        /// a [`cookie::LoadError`](LoadError) of kind [`ErrorKind::UnsupportedLibmagicVersion`] will be returned.
+        #[doc(alias = "magic_load_buffers")]
+        pub fn load_buffers(self, buffers: &[&[u8]]) -> Result<Cookie<Load>, LoadError<S>> {
+            if let Err(err) =
+                crate::version::require(crate::version::Capability::LoadBuffers.since())
+            {
+                let source = crate::ffi::CookieError::UnsupportedVersion(err);
+                return Err(LoadError::new("magic_load_buffers", source, None, self));
+            }
+
+            if let Err((index, err)) = crate::compiled::check(buffers) {
+                let source = crate::ffi::CookieError::DatabaseHeader(err);
+                let mut err = LoadError::new("magic_load_buffers", source, None, self);
+                err.buffer = Some(index);
+                return Err(err);
+            }
+
+            let res = crate::ffi::load_buffers(&self.lock(), buffers);
+            match res {
+                Err(err) => Err(LoadError::new("magic_load_buffers", err, None, self)),
+                Ok(_) => {
+                    let mut cookie = std::mem::ManuallyDrop::new(self);
+
+                    let cookie = Cookie {
+                        cookie: std::sync::Mutex::new(crate::ffi::Cookie::new(cookie.cookie_mut())),
+                        hooks: std::mem::take(&mut cookie.hooks),
+                        marker: std::marker::PhantomData,
+                    };
+                    Ok(cookie)
+                }
+            }
+        }
+
+        /// Sets the `flags` to use for this configuration
+        ///
+        /// Overwrites any previously set flags, e.g. those from [`load()`](Cookie::load).
+        ///
+        /// # Examples
+        /// ```rust
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// // open a new cookie with initial default flags
+        /// let cookie = magic::Cookie::open(Default::default())?;
+        ///
+        /// // overwrite the initial flags
+        /// let flags = magic::cookie::Flags::COMPRESS | magic::cookie::Flags::DEVICES;
+        /// cookie.set_flags(flags)?;
         /// # Ok(())
         /// # }
         /// ```
@@ -1036,19 +2182,152 @@ pub mod cookie {
         /// # Errors
         ///
         /// If the given `flags` are unsupported on the current platform, an [`cookie::SetFlagsError`](SetFlagsError) will be returned.
+        ///
+        /// If the given `flags` need a newer `libmagic`, e.g. [`Flags::EXTENSION`], a [`cookie::SetFlagsError`](SetFlagsError)
+        /// with a [`SetFlagsError::unsupported_version()`](SetFlagsError::unsupported_version) will be returned.
         #[doc(alias = "magic_setflags")]
         pub fn set_flags(&self, flags: Flags) -> Result<(), SetFlagsError> {
-            let ret = crate::ffi::setflags(&self.cookie, flags.bits());
+            if let Err(err) = require_flags(flags) {
+                return Err(SetFlagsError {
+                    flags,
+                    source: SetFlagsErrorSource::UnsupportedVersion(err),
+                });
+            }
+
+            let ret = crate::ffi::setflags(&mut self.lock(), flags.bits());
             match ret {
                 // according to `libmagic` man page this is the only flag that could be unsupported
                 Err(err) => Err(SetFlagsError {
                     flags: Flags::PRESERVE_ATIME,
-                    source: err,
+                    source: SetFlagsErrorSource::Libmagic(err),
                 }),
                 Ok(_) => Ok(()),
             }
         }
 
+        /// Returns the flags currently used for this configuration
+        ///
+        /// With `libmagic` older than 5.29, which lacks `magic_getflags()`,
+        /// these are the flags last given to [`open()`](Cookie::open) or [`set_flags()`](Cookie::set_flags).
+        ///
+        /// # Examples
+        /// ```rust
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// use magic::cookie::Flags;
+        ///
+        /// let cookie = magic::Cookie::open(Flags::MIME_TYPE)?;
+        /// assert_eq!(cookie.flags(), Flags::MIME_TYPE);
+        ///
+        /// cookie.set_flags(Flags::MIME_TYPE | Flags::CONTINUE)?;
+        /// assert_eq!(cookie.flags(), Flags::MIME_TYPE | Flags::CONTINUE);
+        /// # Ok(())
+        /// # }
+        /// ```
+        #[doc(alias = "magic_getflags")]
+        pub fn flags(&self) -> Flags {
+            let cookie = self.lock();
+            let bits = if crate::version::Version::libmagic()
+                .supports(crate::version::Capability::GetFlags)
+            {
+                crate::ffi::getflags(&cookie)
+            } else {
+                cookie.flags()
+            };
+            Flags::from_bits_retain(bits)
+        }
+
+        /// Sets the `parameter` to `value` for this configuration
+        ///
+        /// Note that `libmagic` silently truncates values that exceed the range of some parameters.
+        ///
+        /// # Examples
+        /// ```rust
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// use magic::cookie::Parameter;
+        ///
+        /// let cookie = magic::Cookie::open(Default::default())?;
+        ///
+        /// // only read the first 64 KiB of files
+        /// cookie.set_parameter(Parameter::BytesMax, 64 * 1024)?;
+        /// assert_eq!(cookie.parameter(Parameter::BytesMax)?, 64 * 1024);
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// If the `parameter` is newer than the `libmagic` in use, see [`Parameter::since()`](Parameter::since),
+        /// a [`cookie::ParameterError`](ParameterError) of kind [`ParameterErrorKind::UnsupportedParameter`] will be returned.
+        ///
+        /// If `libmagic` rejects the `parameter`,
+        /// a [`cookie::ParameterError`](ParameterError) of kind [`ParameterErrorKind::Libmagic`] will be returned.
+        #[doc(alias = "magic_setparam")]
+        pub fn set_parameter(
+            &self,
+            parameter: Parameter,
+            value: usize,
+        ) -> Result<(), ParameterError> {
+            let raw = parameter.check()?;
+            crate::ffi::setparam(&self.lock(), raw, value).map_err(|err| ParameterError {
+                parameter,
+                source: ParameterErrorSource::Libmagic(err),
+            })
+        }
+
+        /// Returns the current value of the `parameter` for this configuration
+        ///
+        /// See [`set_parameter()`](Cookie::set_parameter) for an example.
+        ///
+        /// # Errors
+        ///
+        /// See [`set_parameter()`](Cookie::set_parameter).
+        #[doc(alias = "magic_getparam")]
+        pub fn parameter(&self, parameter: Parameter) -> Result<usize, ParameterError> {
+            let raw = parameter.check()?;
+            crate::ffi::getparam(&self.lock(), raw).map_err(|err| ParameterError {
+                parameter,
+                source: ParameterErrorSource::Libmagic(err),
+            })
+        }
+
+        /// Adds a `hook` that post-processes every textual result of this cookie
+        ///
+        /// Hooks receive the result of [`file()`](Cookie::file) and [`buffer()`](Cookie::buffer) and may rewrite or annotate it,
+        /// e.g. to rename descriptions or translate them. They are applied in the order they were added and are kept by [`load()`](Cookie::load).
+        /// Since the other helpers of this crate analyze via those functions, hooks apply to their results as well.
+        ///
+        /// Errors are not passed to hooks.
+        ///
+        /// Hooks run after the cookie was unlocked, so they may use the cookie themselves.
+        /// The functions of a [`FlagsGuard`] are the exception: the guard keeps the cookie locked,
+        /// so hooks that use the cookie deadlock when they run for its results.
+        ///
+        /// # Examples
+        /// ```rust
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// let mut cookie = magic::Cookie::open(magic::cookie::Flags::ERROR)?;
+        /// cookie.add_hook(|result| result.replace("PNG image data", "Portable Network Graphics"));
+        ///
+        /// let cookie = cookie.load(["data/tests/db-images-png"])?;
+        /// assert_eq!(
+        ///     cookie.file("data/tests/rust-logo-128x128-blk.png")?,
+        ///     "Portable Network Graphics, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        /// );
+        /// # Ok(())
+        /// # }
+        /// ```
+        pub fn add_hook<F>(&mut self, hook: F)
+        where
+            F: Fn(String) -> String + Send + Sync + 'static,
+        {
+            self.hooks.push(Box::new(hook));
+        }
+
+        /// Removes all hooks added with [`add_hook()`](Cookie::add_hook)
+        pub fn clear_hooks(&mut self) {
+            self.hooks.clear();
+        }
+
         // TODO: check, compile, list and load mostly do the same, refactor!
 
         /// Compiles the given database files `filenames` for faster access
@@ -1064,13 +2343,12 @@ pub mod cookie {
         ///
         /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
         ///
-        /// # Panics
-        ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
         #[doc(alias = "magic_compile")]
         #[doc(alias = "--compile")]
         pub fn compile(&self, filenames: &DatabasePaths) -> Result<(), Error> {
-            match crate::ffi::compile(&self.cookie, filenames.filenames.as_deref()) {
+            match crate::ffi::compile(&self.lock(), filenames.filenames.as_deref()) {
                 Err(err) => Err(Error {
                     function: "magic_compile",
                     source: err,
@@ -1085,12 +2363,11 @@ pub mod cookie {
         ///
         /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
         ///
-        /// # Panics
-        ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
         #[doc(alias = "magic_check")]
         pub fn check(&self, filenames: &DatabasePaths) -> Result<(), Error> {
-            match crate::ffi::check(&self.cookie, filenames.filenames.as_deref()) {
+            match crate::ffi::check(&self.lock(), filenames.filenames.as_deref()) {
                 Err(err) => Err(Error {
                     function: "magic_check",
                     source: err,
@@ -1110,13 +2387,112 @@ pub mod cookie {
         ///
         /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
         ///
-        /// # Panics
-        ///
-        /// Panics if `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data.
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
         #[doc(alias = "magic_list")]
         #[doc(alias = "--checking-printout")]
         pub fn list(&self, filenames: &DatabasePaths) -> Result<(), Error> {
-            match crate::ffi::list(&self.cookie, filenames.filenames.as_deref()) {
+            match crate::ffi::list(&self.lock(), filenames.filenames.as_deref()) {
+                Err(err) => Err(Error {
+                    function: "magic_list",
+                    source: err,
+                }),
+                Ok(_) => Ok(()),
+            }
+        }
+
+        /// Compiles the single database file `filename` for faster access
+        ///
+        /// This is a shorthand for [`compile()`](Cookie::compile) with exactly one database file,
+        /// without constructing [`DatabasePaths`] first.
+        ///
+        /// Note that `libmagic` itself still treats a ":" (colon) in `filename` as a separator for multiple database files.
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+        ///
+        /// If `filename` contains a NUL byte, a [`cookie::Error`](Error) of kind [`ErrorKind::InvalidPath`] will be returned.
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
+        #[doc(alias = "magic_compile")]
+        pub fn compile_path<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_compile",
+                source: err,
+            })?;
+            match crate::ffi::compile(&self.lock(), Some(c_string.as_c_str())) {
+                Err(err) => Err(Error {
+                    function: "magic_compile",
+                    source: err,
+                }),
+                Ok(_) => Ok(()),
+            }
+        }
+
+        /// Checks the validity of entries in the single database file `filename`
+        ///
+        /// This is a shorthand for [`check()`](Cookie::check) with exactly one database file,
+        /// without constructing [`DatabasePaths`] first.
+        ///
+        /// Note that `libmagic` itself still treats a ":" (colon) in `filename` as a separator for multiple database files.
+        ///
+        /// # Examples
+        /// ```rust
+        /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+        /// let cookie = magic::Cookie::open(Default::default())?;
+        ///
+        /// cookie.check_path("data/tests/db-images-png")?;
+        /// # Ok(())
+        /// # }
+        /// ```
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+        ///
+        /// If `filename` contains a NUL byte, a [`cookie::Error`](Error) of kind [`ErrorKind::InvalidPath`] will be returned.
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
+        #[doc(alias = "magic_check")]
+        pub fn check_path<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_check",
+                source: err,
+            })?;
+            match crate::ffi::check(&self.lock(), Some(c_string.as_c_str())) {
+                Err(err) => Err(Error {
+                    function: "magic_check",
+                    source: err,
+                }),
+                Ok(_) => Ok(()),
+            }
+        }
+
+        /// Dumps all magic entries in the single database file `filename` in a human readable format
+        ///
+        /// This is a shorthand for [`list()`](Cookie::list) with exactly one database file,
+        /// without constructing [`DatabasePaths`] first.
+        ///
+        /// Note that `libmagic` itself still treats a ":" (colon) in `filename` as a separator for multiple database files.
+        ///
+        /// # Errors
+        ///
+        /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+        ///
+        /// If `filename` contains a NUL byte, a [`cookie::Error`](Error) of kind [`ErrorKind::InvalidPath`] will be returned.
+        ///
+        /// If `libmagic` violates its API contract, e.g. by not setting the last error or returning undefined data,
+        /// a [`cookie::Error`](Error) of kind [`ErrorKind::ApiViolation`] will be returned.
+        #[doc(alias = "magic_list")]
+        pub fn list_path<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+            let c_string = crate::ffi::path(filename.as_ref()).map_err(|err| Error {
+                function: "magic_list",
+                source: err,
+            })?;
+            match crate::ffi::list(&self.lock(), Some(c_string.as_c_str())) {
                 Err(err) => Err(Error {
                     function: "magic_list",
                     source: err,
@@ -1133,6 +2509,7 @@ pub mod cookie {
     #[error("could not open magic cookie: {}",
         match .kind {
             OpenErrorKind::UnsupportedFlags => format!("unsupported flags {}", .flags),
+            OpenErrorKind::UnsupportedVersion => format!("flags {} need a newer `libmagic`", .flags),
             OpenErrorKind::Errno => "other error".to_string(),
         }
     )]
@@ -1140,7 +2517,17 @@ pub mod cookie {
         flags: Flags,
         kind: OpenErrorKind,
         //#[backtrace]
-        source: crate::ffi::OpenError,
+        source: OpenErrorSource,
+    }
+
+    impl OpenError {
+        /// Returns the version error if the flags need a newer `libmagic`
+        pub fn unsupported_version(&self) -> Option<&crate::version::UnsupportedLibmagicVersion> {
+            match &self.source {
+                OpenErrorSource::UnsupportedVersion(err) => Some(err),
+                OpenErrorSource::Libmagic(_) => None,
+            }
+        }
     }
 
     /// Kind of [`OpenError`]
@@ -1148,10 +2535,20 @@ pub mod cookie {
     enum OpenErrorKind {
         /// Unsupported flags given
         UnsupportedFlags,
+        /// Flags given that need a newer `libmagic`
+        UnsupportedVersion,
         /// Other kind
         Errno,
     }
 
+    #[derive(thiserror::Error, Debug)]
+    enum OpenErrorSource {
+        #[error(transparent)]
+        Libmagic(crate::ffi::OpenError),
+        #[error(transparent)]
+        UnsupportedVersion(crate::version::UnsupportedLibmagicVersion),
+    }
+
     /// Error within [`Cookie::set_flags()`](Cookie::set_flags)
     ///
     /// Note that a similar [`cookie::OpenError`](OpenError) can also occur
@@ -1160,11 +2557,86 @@ pub mod cookie {
     pub struct SetFlagsError {
         flags: Flags,
         //#[backtrace]
-        source: crate::ffi::SetFlagsError,
+        source: SetFlagsErrorSource,
+    }
+
+    impl SetFlagsError {
+        /// Returns the version error if the flags need a newer `libmagic`
+        pub fn unsupported_version(&self) -> Option<&crate::version::UnsupportedLibmagicVersion> {
+            match &self.source {
+                SetFlagsErrorSource::UnsupportedVersion(err) => Some(err),
+                SetFlagsErrorSource::Libmagic(_) => None,
+            }
+        }
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    enum SetFlagsErrorSource {
+        #[error(transparent)]
+        Libmagic(crate::ffi::SetFlagsError),
+        #[error(transparent)]
+        UnsupportedVersion(crate::version::UnsupportedLibmagicVersion),
+    }
+
+    /// Checks that the `libmagic` in use supports all of the `flags`
+    ///
+    /// Older `libmagic` versions silently ignore or misinterpret unknown flags.
+    fn require_flags(flags: Flags) -> Result<(), crate::version::UnsupportedLibmagicVersion> {
+        if flags.intersects(Flags::EXTENSION) {
+            crate::version::require(crate::version::Capability::Extension.since())?;
+        }
+        Ok(())
+    }
+
+    /// Error within [`Cookie::set_parameter()`](Cookie::set_parameter) or [`Cookie::parameter()`](Cookie::parameter)
+    #[derive(thiserror::Error, Debug)]
+    #[error("could not access magic cookie parameter {:?}", .parameter)]
+    pub struct ParameterError {
+        parameter: Parameter,
+        //#[backtrace]
+        source: ParameterErrorSource,
+    }
+
+    impl ParameterError {
+        /// Returns the parameter that could not be accessed
+        pub fn parameter(&self) -> Parameter {
+            self.parameter
+        }
+
+        /// Returns the kind of this error
+        pub fn kind(&self) -> ParameterErrorKind {
+            match self.source {
+                ParameterErrorSource::Unsupported(_) => ParameterErrorKind::UnsupportedParameter,
+                ParameterErrorSource::Libmagic(_) => ParameterErrorKind::Libmagic,
+            }
+        }
+    }
+
+    /// Kind of [`cookie::ParameterError`](ParameterError)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ParameterErrorKind {
+        /// The parameter is newer than the `libmagic` in use, see [`Parameter::since()`](Parameter::since)
+        UnsupportedParameter,
+        /// `libmagic` rejected the parameter
+        Libmagic,
+    }
+
+    #[derive(thiserror::Error, Debug)]
+    enum ParameterErrorSource {
+        #[error("parameter is not supported by `libmagic` {}.{}", .0.major(), .0.minor())]
+        Unsupported(crate::version::Version),
+        #[error("`libmagic` rejected parameter")]
+        Libmagic(#[source] crate::ffi::ParamError),
     }
 } // mod cookie
 
 pub use crate::cookie::Cookie;
+pub use crate::detector::Detector;
+#[cfg(feature = "global")]
+pub use crate::global::{detect_buffer, detect_file};
+pub use crate::media_type::Mime;
+pub use crate::version::Version;
 
 #[cfg(test)]
 mod tests {
@@ -1179,7 +2651,7 @@ mod tests {
     #[test]
     fn file() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
-        let databases = &["data/tests/db-images-png"].try_into().unwrap();
+        let databases = ["data/tests/db-images-png"];
         let cookie = cookie.load(databases).unwrap();
 
         let path = "data/tests/rust-logo-128x128-blk.png";
@@ -1201,7 +2673,7 @@ mod tests {
     #[test]
     fn buffer() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
-        let databases = &["data/tests/db-python"].try_into().unwrap();
+        let databases = ["data/tests/db-python"];
         let cookie = cookie.load(databases).unwrap();
 
         let s = b"#!/usr/bin/env python\nprint('Hello, world!')";
@@ -1217,7 +2689,9 @@ mod tests {
     #[test]
     fn file_error() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
-        let cookie = cookie.load(&Default::default()).unwrap();
+        let cookie = cookie
+            .load(super::cookie::DatabasePaths::default())
+            .unwrap();
 
         let ret = cookie.file("non-existent_file.txt");
         assert!(ret.is_err());
@@ -1226,28 +2700,358 @@ mod tests {
     #[test]
     fn load_default() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
-        assert!(cookie.load(&Default::default()).is_ok());
+        assert!(cookie.load(super::cookie::DatabasePaths::default()).is_ok());
+    }
+
+    #[test]
+    fn require_version() {
+        super::require_version(super::Version::new(5, 4)).unwrap();
+
+        let required = super::Version::new(99, 0);
+        let err = super::require_version(required).unwrap_err();
+        assert_eq!(err.required(), required);
+        assert_eq!(err.version(), super::Version::libmagic());
+
+        // supported by any `libmagic` this crate is tested with
+        let cookie = Cookie::open(Flags::EXTENSION).unwrap();
+        cookie.set_flags(Flags::NODESC).unwrap();
+    }
+
+    #[test]
+    fn default_database_path() {
+        let databases = super::default_database_path();
+        assert!(!databases.is_empty());
+
+        let resolved = super::cookie::DatabasePaths::default_resolved().unwrap();
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(&resolved).unwrap();
+        assert!(cookie
+            .file("data/tests/rust-logo-128x128-blk.png")
+            .unwrap()
+            .starts_with("PNG image data"));
+    }
+
+    #[test]
+    fn load_system_default_ignoring_env() {
+        // tests must not modify the environment, since other tests run in parallel
+        if std::env::var_os(super::cookie::DATABASE_PATHS_ENV).is_some() {
+            assert!(super::cookie::DatabasePaths::system_default_ignoring_env().is_none());
+            return;
+        }
+
+        let databases = super::cookie::DatabasePaths::system_default_ignoring_env().unwrap();
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        assert!(cookie.load(databases).is_ok());
     }
 
     #[test]
     fn load_one() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
-        let databases = &["data/tests/db-images-png"].try_into().unwrap();
+        let databases = ["data/tests/db-images-png"];
         assert!(cookie.load(databases).is_ok());
     }
 
     #[test]
     fn load_multiple() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
-        let databases = &["data/tests/db-images-png", "data/tests/db-python"]
-            .try_into()
-            .unwrap();
+        let databases = ["data/tests/db-images-png", "data/tests/db-python"];
         assert!(cookie.load(databases).is_ok());
     }
 
+    #[test]
+    fn load_invalid_path() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let err = cookie.load("data/tests/\0db-python").unwrap_err();
+        assert_eq!(err.kind(), super::cookie::ErrorKind::InvalidPath);
+
+        let databases: super::cookie::DatabasePaths = "data/tests/db-python".try_into().unwrap();
+        assert!(err.cookie().load(&databases).is_ok());
+    }
+
+    #[test]
+    fn load_error_kinds() {
+        use super::cookie::ErrorKind;
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let err = cookie.load("data/tests/doesntexist").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DatabaseMissing);
+        assert_eq!(
+            err.database(),
+            Some(std::path::Path::new("data/tests/doesntexist"))
+        );
+
+        let syntax = std::env::temp_dir().join("rust-magic-test-load_error_kinds");
+        std::fs::write(&syntax, "0\tbogus\tfoo\tFoo\n").unwrap();
+        let err = err.cookie().load(&*syntax).unwrap_err();
+        std::fs::remove_file(&syntax).unwrap();
+        assert_eq!(err.kind(), ErrorKind::DatabaseSyntax { line: None });
+
+        // magic number and format version 1 of a compiled database
+        let compiled = std::env::temp_dir().join("rust-magic-test-load_error_kinds.mgc");
+        let mut header = vec![0x1c, 0x04, 0x1e, 0xf1, 0x01, 0x00, 0x00, 0x00];
+        header.resize(1024, 0);
+        std::fs::write(&compiled, header).unwrap();
+        let err = err.cookie().load(&*compiled).unwrap_err();
+        std::fs::remove_file(&compiled).unwrap();
+        match err.kind() {
+            ErrorKind::DatabaseVersion { version, .. } => assert_eq!(version, 1),
+            kind => panic!("unexpected kind {:?}", kind),
+        }
+        assert_eq!(err.database(), Some(&*compiled));
+    }
+
+    #[test]
+    fn hooks() {
+        let mut cookie = Cookie::open(Flags::ERROR).unwrap();
+        cookie.add_hook(|result| result.replace("Python script", "Snake script"));
+        cookie.add_hook(|result| format!("{} (reviewed)", result));
+        let mut cookie = cookie.load(["data/tests/db-python"]).unwrap();
+
+        let s = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        assert_eq!(
+            cookie.buffer(s).unwrap(),
+            "Snake script, ASCII text executable (reviewed)"
+        );
+
+        cookie.clear_hooks();
+        assert_eq!(
+            cookie.buffer(s).unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+
+    #[test]
+    fn hooks_unlocked() {
+        use std::sync::{Arc, Mutex, Weak};
+
+        let slot: Arc<Mutex<Weak<Cookie<crate::cookie::Load>>>> = Arc::default();
+        let hook_slot = Arc::clone(&slot);
+        let mut cookie = Cookie::open(Flags::ERROR).unwrap();
+        cookie.add_hook(move |result| match hook_slot.lock().unwrap().upgrade() {
+            // this would deadlock if the cookie was still locked
+            Some(cookie) => format!("{} ({:?})", result, cookie.flags().contains(Flags::ERROR)),
+            None => result,
+        });
+        let cookie = Arc::new(cookie.load(["data/tests/db-python"]).unwrap());
+        *slot.lock().unwrap() = Arc::downgrade(&cookie);
+
+        let s: &[u8] = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        let expected = "Python script, ASCII text executable (true)";
+        assert_eq!(cookie.buffer(s).unwrap(), expected);
+        assert_eq!(cookie.buffers(&[s])[0].as_ref().unwrap(), expected);
+    }
+
     // TODO:
     //static_assertions::assert_impl_all!(Cookie<S>: std::fmt::Debug);
 
+    #[cfg(unix)]
+    #[test]
+    fn file_handle() {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let mut file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png").unwrap();
+        assert_eq!(
+            cookie.file_handle(&file).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(file.seek(SeekFrom::Current(0)).unwrap(), 0);
+
+        let mut signature = [0; 8];
+        file.read_exact(&mut signature).unwrap();
+        assert_eq!(&signature, b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn flags() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        assert_eq!(cookie.flags(), Flags::ERROR);
+
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        cookie
+            .set_flags(Flags::MIME_TYPE | Flags::CONTINUE)
+            .unwrap();
+        assert_eq!(cookie.flags(), Flags::MIME_TYPE | Flags::CONTINUE);
+    }
+
+    #[test]
+    fn with_flags() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        {
+            let guard = cookie.with_flags(Flags::ERROR | Flags::MIME_TYPE).unwrap();
+            assert_eq!(guard.flags(), Flags::ERROR | Flags::MIME_TYPE);
+            assert_eq!(guard.file(path).unwrap(), "image/png");
+            let data = std::fs::read(path).unwrap();
+            assert_eq!(guard.buffer(&data).unwrap(), "image/png");
+        }
+        assert_eq!(cookie.flags(), Flags::ERROR);
+        assert_eq!(
+            cookie.file(path).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        use super::cookie::Parameter;
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        cookie.set_parameter(Parameter::IndirMax, 3).unwrap();
+        assert_eq!(cookie.parameter(Parameter::IndirMax).unwrap(), 3);
+
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        cookie.set_parameter(Parameter::BytesMax, 16).unwrap();
+        assert_eq!(cookie.parameter(Parameter::BytesMax).unwrap(), 16);
+        // the image header with the dimensions is not read anymore
+        assert_eq!(
+            cookie.file("data/tests/rust-logo-128x128-blk.png").unwrap(),
+            "PNG image data"
+        );
+    }
+
+    #[test]
+    fn parameters_version_gate() {
+        use super::cookie::{Parameter, ParameterErrorKind};
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        for &parameter in &[Parameter::EncodingMax, Parameter::ElfShsizeMax] {
+            match cookie.parameter(parameter) {
+                Ok(_) => assert!(super::Version::libmagic() >= parameter.since()),
+                Err(err) => {
+                    assert!(super::Version::libmagic() < parameter.since());
+                    assert_eq!(err.kind(), ParameterErrorKind::UnsupportedParameter);
+                    assert_eq!(err.parameter(), parameter);
+                }
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths() {
+        use std::os::unix::ffi::OsStrExt;
+
+        // 0xFF is invalid UTF-8, so a lossy conversion would replace it
+        let mut name = format!("magic-non-utf8-{}-", std::process::id()).into_bytes();
+        name.push(0xFF);
+        let dir = std::env::temp_dir().join(std::ffi::OsStr::from_bytes(&name));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let database = dir.join("db-images-png");
+        std::fs::copy("data/tests/db-images-png", &database).unwrap();
+        let image = dir.join("rust-logo-128x128-blk.png");
+        std::fs::copy("data/tests/rust-logo-128x128-blk.png", &image).unwrap();
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load([&database]).unwrap();
+        assert_eq!(
+            cookie.file(&image).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn buffers() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let results = cookie.buffers(&[&png, b"", &png[..16]]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(results[1].as_ref().unwrap(), &cookie.buffer(b"").unwrap());
+        assert_eq!(results[2].as_ref().unwrap(), "PNG image data");
+        assert!(cookie.buffers(&[]).is_empty());
+    }
+
+    #[test]
+    fn borrowed_results() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let mut cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+        let result = cookie.file_ref(path).unwrap();
+        assert!(matches!(result, std::borrow::Cow::Borrowed(_)));
+        assert_eq!(result, expected);
+
+        let buffer = std::fs::read(path).unwrap();
+        assert_eq!(cookie.buffer_ref(&buffer).unwrap(), expected);
+
+        cookie.add_hook(|result| result.replace("PNG", "Portable Network Graphics"));
+        assert!(cookie
+            .buffer_ref(&buffer)
+            .unwrap()
+            .starts_with("Portable Network Graphics image data"));
+    }
+
+    #[test]
+    fn file_cstr() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = std::ffi::CString::new("data/tests/rust-logo-128x128-blk.png").unwrap();
+        assert_eq!(
+            cookie.file_cstr(&path).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+
+        let path = std::ffi::CString::new("data/tests/doesntexist.png").unwrap();
+        assert_eq!(
+            cookie.file_cstr(&path).unwrap_err().kind(),
+            super::cookie::ErrorKind::Libmagic
+        );
+    }
+
+    #[test]
+    fn raw_results() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        assert_eq!(
+            cookie.file_raw(path).unwrap(),
+            cookie.file(path).unwrap().into_bytes()
+        );
+
+        // prints the invalid UTF-8 byte of the buffer unescaped with `Flags::RAW`
+        let database = std::env::temp_dir().join(format!("magic-raw-{}", std::process::id()));
+        std::fs::write(&database, "0\tstring\tRAW\tRaw data\n>3\tstring\tx\t%s\n").unwrap();
+        let cookie = Cookie::open(Flags::ERROR | Flags::RAW).unwrap();
+        let cookie = cookie.load([&database]).unwrap();
+        assert_eq!(
+            cookie.buffer_raw(b"RAW\xFFabc").unwrap(),
+            b"Raw data \xFFabc"
+        );
+        assert_eq!(
+            cookie.buffer(b"RAW\xFFabc").unwrap(),
+            "Raw data \u{FFFD}abc"
+        );
+        std::fs::remove_file(&database).unwrap();
+    }
+
+    #[test]
+    fn file_invalid_path() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let err = cookie.file("data/tests/\0.png").unwrap_err();
+        assert_eq!(err.kind(), super::cookie::ErrorKind::InvalidPath);
+    }
+
+    static_assertions::assert_impl_all!(Cookie<super::cookie::Open>: Send, Sync);
+    static_assertions::assert_impl_all!(Cookie<super::cookie::Load>: Send, Sync);
+
     #[test]
     fn load_buffers_file() {
         let cookie = Cookie::open(Flags::ERROR).unwrap();
@@ -1263,6 +3067,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn load_buffers_invalid_header() {
+        use super::cookie::ErrorKind;
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let magic_database = std::fs::read("data/tests/db-images-png-precompiled.mgc").unwrap();
+        let magic_source = std::fs::read("data/tests/db-python").unwrap();
+        let buffers = vec![magic_database.as_slice(), magic_source.as_slice()];
+
+        let err = cookie.load_buffers(&buffers[1..]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DatabaseFormat);
+        assert_eq!(err.buffer(), Some(0));
+
+        // whether the precompiled database itself is supported depends on the `libmagic` version
+        let err = err.cookie().load_buffers(&buffers).unwrap_err();
+        match err.buffer() {
+            Some(0) => assert!(matches!(err.kind(), ErrorKind::DatabaseVersion { .. })),
+            buffer => assert_eq!(buffer, Some(1)),
+        }
+    }
+
+    #[test]
+    fn check_path() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        assert!(cookie.check_path("data/tests/db-images-png").is_ok());
+        assert!(cookie.check_path("data/tests/db-python").is_ok());
+    }
+
     #[test]
     fn libmagic_version() {
         let version = super::libmagic_version();
@@ -0,0 +1,282 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of email attachments
+//!
+//! The `Content-Type` and filename of an attachment are chosen by the sender and can not be trusted.
+//! [`scan_mail()`] analyzes the actual content of each attachment of a message parsed with the
+//! [`mailparse` crate](https://crates.io/crates/mailparse) and flags mismatches with the declared type and filename,
+//! e.g. for mail gateway filtering.
+//!
+//! The checks are the same as in [`spoof`](crate::spoof).
+//!
+//! Requires the `mail` feature.
+
+use crate::cookie::{Error, Flags, Load, SetFlagsError};
+use crate::policy::{Evaluation, Policy};
+use crate::spoof;
+use crate::Cookie;
+
+/// Analysis result of a single email attachment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    filename: Option<String>,
+    declared_type: String,
+    detected_type: String,
+    extensions: Vec<String>,
+}
+
+impl Attachment {
+    /// Returns the filename declared in the `Content-Disposition` or `Content-Type` header, if any
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Returns the media type declared in the `Content-Type` header, e.g. `application/pdf`
+    pub fn declared_type(&self) -> &str {
+        &self.declared_type
+    }
+
+    /// Returns the media type of the content as detected by `libmagic`, e.g. `image/png`
+    pub fn detected_type(&self) -> &str {
+        &self.detected_type
+    }
+
+    /// Returns the file extensions `libmagic` knows for the detected content, e.g. `["png"]`
+    ///
+    /// This is empty if the database does not know any extensions.
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// Returns whether the declared media type differs from the detected one
+    ///
+    /// A declared `application/octet-stream` does not claim any particular content and never mismatches.
+    pub fn content_type_mismatch(&self) -> bool {
+        spoof::content_type_mismatch(
+            &self.declared_type,
+            std::slice::from_ref(&self.detected_type),
+        )
+    }
+
+    /// Returns whether the extension of the declared filename is not one of the detected [`extensions()`](Attachment::extensions)
+    ///
+    /// Attachments without filename, filenames without extension and content without known extensions never mismatch.
+    pub fn filename_mismatch(&self) -> bool {
+        self.filename
+            .as_deref()
+            .and_then(spoof::extension)
+            .map_or(false, |extension| {
+                spoof::extension_mismatch(extension, &self.extensions)
+            })
+    }
+
+    /// Returns whether either the declared media type or filename mismatches the content
+    pub fn is_suspicious(&self) -> bool {
+        self.content_type_mismatch() || self.filename_mismatch()
+    }
+
+    /// Evaluates the detected media type with `policy`
+    ///
+    /// There is no textual description, so rules for descriptions do not match.
+    pub fn evaluate<'p>(&self, policy: &'p Policy) -> Evaluation<'p> {
+        policy.evaluate(&self.detected_type, None)
+    }
+}
+
+/// Error within [`scan_mail()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum MailError {
+    /// Decoding the body of an attachment failed
+    #[error("could not decode attachment")]
+    Decode(#[source] mailparse::MailParseError),
+    /// Setting the flags for the analysis failed
+    #[error("could not set flags to analyze attachment")]
+    SetFlags(#[source] SetFlagsError),
+    /// Analyzing an attachment failed
+    #[error("could not analyze attachment")]
+    Analyze(#[source] Error),
+}
+
+/// Analyzes the content of each attachment of `mail`
+///
+/// Attachments are leaf parts of the (possibly nested multipart) message which either have a
+/// `Content-Disposition: attachment` or a filename.
+/// Their bodies are decoded as per `Content-Transfer-Encoding` and analyzed with [`Cookie::buffer()`](Cookie::buffer).
+///
+/// The analysis uses [`Flags::MIME_TYPE`] and [`Flags::EXTENSION`] with [`Flags::ERROR`] instead of the flags of the cookie,
+/// see [`with_flags()`](Cookie::with_flags).
+///
+/// Requires the `mail` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+///
+/// let message = std::fs::read("message.eml")?;
+/// let mail = mailparse::parse_mail(&message)?;
+/// for attachment in magic::mail::scan_mail(&mail, &cookie)? {
+///     if attachment.is_suspicious() {
+///         println!(
+///             "{:?} declared as {} but is {}",
+///             attachment.filename(),
+///             attachment.declared_type(),
+///             attachment.detected_type()
+///         );
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the body of an attachment could not be decoded, a [`MailError::Decode`] will be returned.
+///
+/// If the flags could not be set, a [`MailError::SetFlags`] will be returned.
+///
+/// If there was an `libmagic` internal error, a [`MailError::Analyze`] will be returned.
+pub fn scan_mail(
+    mail: &mailparse::ParsedMail<'_>,
+    cookie: &Cookie<Load>,
+) -> Result<Vec<Attachment>, MailError> {
+    let mut attachments = Vec::new();
+    scan_part(mail, cookie, &mut attachments)?;
+    Ok(attachments)
+}
+
+fn scan_part(
+    part: &mailparse::ParsedMail<'_>,
+    cookie: &Cookie<Load>,
+    attachments: &mut Vec<Attachment>,
+) -> Result<(), MailError> {
+    if !part.subparts.is_empty() {
+        for subpart in &part.subparts {
+            scan_part(subpart, cookie, attachments)?;
+        }
+        return Ok(());
+    }
+
+    let disposition = part.get_content_disposition();
+    let filename = disposition
+        .params
+        .get("filename")
+        .or_else(|| part.ctype.params.get("name"))
+        .cloned();
+    if disposition.disposition != mailparse::DispositionType::Attachment && filename.is_none() {
+        return Ok(());
+    }
+
+    let body = part.get_body_raw().map_err(MailError::Decode)?;
+
+    let detected_type = cookie
+        .with_flags(Flags::ERROR | Flags::MIME_TYPE)
+        .map_err(MailError::SetFlags)?
+        .buffer(&body)
+        .map_err(MailError::Analyze)?;
+
+    let extensions = cookie
+        .with_flags(Flags::ERROR | Flags::EXTENSION)
+        .map_err(MailError::SetFlags)?
+        .buffer(&body)
+        .map_err(MailError::Analyze)?;
+    let extensions = spoof::parse_extensions(&extensions);
+
+    attachments.push(Attachment {
+        filename,
+        declared_type: part.ctype.mimetype.clone(),
+        detected_type,
+        extensions,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{scan_mail, Attachment};
+
+    #[test]
+    fn scan_mail_mismatch() {
+        use crate::cookie::Flags;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let message = b"From: sender@example.com\r\n\
+Content-Type: multipart/mixed; boundary=\"boundary\"\r\n\
+\r\n\
+--boundary\r\n\
+Content-Type: text/plain\r\n\
+\r\n\
+Please see the attached invoice.\r\n\
+--boundary\r\n\
+Content-Type: application/pdf\r\n\
+Content-Disposition: attachment; filename=\"invoice.pdf\"\r\n\
+\r\n\
+#!/usr/bin/env python\r\n\
+print('Hello, world!')\r\n\
+--boundary\r\n\
+Content-Type: text/x-python; name=\"hello.py\"\r\n\
+\r\n\
+#!/usr/bin/env python\r\n\
+print('Hello, world!')\r\n\
+--boundary--\r\n";
+        let mail = mailparse::parse_mail(message).unwrap();
+
+        let attachments = scan_mail(&mail, &cookie).unwrap();
+        assert_eq!(attachments.len(), 2);
+
+        assert_eq!(attachments[0].filename(), Some("invoice.pdf"));
+        assert_eq!(attachments[0].declared_type(), "application/pdf");
+        assert_eq!(attachments[0].detected_type(), "text/x-python");
+        assert!(attachments[0].content_type_mismatch());
+        assert!(attachments[0].is_suspicious());
+
+        assert_eq!(attachments[1].filename(), Some("hello.py"));
+        assert!(!attachments[1].is_suspicious());
+
+        assert_eq!(cookie.flags(), Flags::ERROR);
+    }
+
+    #[test]
+    fn filename_mismatch() {
+        let attachment = Attachment {
+            filename: Some("invoice.PDF".to_string()),
+            declared_type: "application/octet-stream".to_string(),
+            detected_type: "image/jpeg".to_string(),
+            extensions: vec!["jpeg".to_string(), "jpg".to_string()],
+        };
+        assert!(!attachment.content_type_mismatch());
+        assert!(attachment.filename_mismatch());
+
+        let attachment = Attachment {
+            filename: Some("photo.JPG".to_string()),
+            ..attachment
+        };
+        assert!(!attachment.filename_mismatch());
+
+        let attachment = Attachment {
+            extensions: Vec::new(),
+            ..attachment
+        };
+        assert!(!attachment.filename_mismatch());
+    }
+
+    #[test]
+    fn evaluate() {
+        use crate::policy::{Decision, Pattern, Policy};
+
+        let attachment = Attachment {
+            filename: Some("photo.jpg".to_string()),
+            declared_type: "image/jpeg".to_string(),
+            detected_type: "image/jpeg".to_string(),
+            extensions: vec!["jpeg".to_string(), "jpg".to_string()],
+        };
+        let policy = Policy::new(Decision::Deny).allow(Pattern::mime_type("image/*"));
+        assert_eq!(attachment.evaluate(&policy).decision(), Decision::Allow);
+    }
+}
@@ -0,0 +1,519 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Validation of MIME results
+//!
+//! `libmagic` MIME results are mostly, but not always, valid media types as per
+//! [RFC 6838](https://www.rfc-editor.org/rfc/rfc6838) and [RFC 9110](https://www.rfc-editor.org/rfc/rfc9110#name-media-type).
+//! For example, results of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) contain several lines
+//! and built-in checks return non-standard types such as `inode/directory`.
+//!
+//! [`validate()`] is an opt-in check before using a result e.g. as an HTTP `Content-Type` header.
+//! [`Mime`] additionally splits a valid result into type, subtype and parameters,
+//! see also [`Cookie::mime()`](crate::Cookie::mime). With the `mime` feature, it converts to the type of the `mime` crate.
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::media_type::{validate, Conformance};
+//!
+//! assert_eq!(validate("image/png; charset=binary").unwrap(), Conformance::Standard);
+//! assert_eq!(validate("text/x-python").unwrap(), Conformance::Unregistered);
+//! assert_eq!(validate("inode/directory").unwrap(), Conformance::Nonstandard);
+//! assert!(validate("image/png\n- application/octet-stream").is_err());
+//! ```
+
+use std::path::Path;
+
+use crate::cookie::{Error, Flags, Load, SetFlagsError};
+use crate::Cookie;
+
+/// Top-level media types registered with IANA
+///
+/// See <https://www.iana.org/assignments/top-level-media-types/top-level-media-types.xhtml>
+const TOP_LEVEL_TYPES: &[&str] = &[
+    "application",
+    "audio",
+    "example",
+    "font",
+    "haptics",
+    "image",
+    "message",
+    "model",
+    "multipart",
+    "text",
+    "video",
+];
+
+/// Classification of a syntactically valid media type
+///
+/// Returned from [`validate()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Conformance {
+    /// Registered top-level type and a subtype without `x-` prefix, e.g. `image/png`
+    ///
+    /// Note that the subtype is not checked against the IANA registry.
+    Standard,
+    /// Registered top-level type but an unregistered `x-` subtype, e.g. `text/x-python`
+    ///
+    /// Those are common in `libmagic` results and widely understood, but deprecated by RFC 6838.
+    Unregistered,
+    /// Unregistered top-level type, e.g. `inode/directory` of `libmagic`'s built-in checks
+    Nonstandard,
+}
+
+/// Invalid media type syntax
+///
+/// Returned from [`validate()`]
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid media type: {}", .reason)]
+pub struct InvalidMediaTypeError {
+    reason: &'static str,
+}
+
+/// Checks that `media_type` is a syntactically valid media type with optional parameters and classifies it
+///
+/// The syntax is `type "/" subtype *( OWS ";" OWS parameter )`
+/// where `parameter` is `token "=" ( token / quoted-string )`.
+///
+/// # Errors
+///
+/// If `media_type` is syntactically invalid, an [`InvalidMediaTypeError`] will be returned.
+pub fn validate(media_type: &str) -> Result<Conformance, InvalidMediaTypeError> {
+    let (type_, subtype, parameters) = split(media_type)?;
+
+    if let Some(parameters) = parameters {
+        parse_parameters(parameters)?;
+    }
+
+    Ok(classify(type_, subtype))
+}
+
+fn classify(type_: &str, subtype: &str) -> Conformance {
+    let type_ = type_.to_ascii_lowercase();
+    let subtype = subtype.to_ascii_lowercase();
+    match (
+        TOP_LEVEL_TYPES.contains(&type_.as_str()),
+        subtype.starts_with("x-"),
+    ) {
+        (false, _) => Conformance::Nonstandard,
+        (true, true) => Conformance::Unregistered,
+        (true, false) => Conformance::Standard,
+    }
+}
+
+/// Media type that was split into its parts
+///
+/// Type, subtype and parameter names are lowercase, parameter values are unquoted.
+/// `libmagic` also returns non-standard types such as `inode/directory`, `inode/symlink` or `inode/x-empty`
+/// for special files, and `application/x-*` or `text/x-*` subtypes that are not registered, see [`Conformance`].
+///
+/// # Examples
+///
+/// ```
+/// use magic::media_type::{Conformance, Mime};
+///
+/// let mime: Mime = "text/plain; charset=us-ascii".parse()?;
+/// assert_eq!(mime.type_(), "text");
+/// assert_eq!(mime.subtype(), "plain");
+/// assert_eq!(mime.essence(), "text/plain");
+/// assert_eq!(mime.parameter("charset"), Some("us-ascii"));
+/// assert_eq!(mime.conformance(), Conformance::Standard);
+/// assert_eq!(mime.to_string(), "text/plain; charset=us-ascii");
+/// # Ok::<(), magic::media_type::InvalidMediaTypeError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mime {
+    type_: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+
+impl Mime {
+    /// Parses a `media_type` with optional parameters, see [`validate()`] for the syntax
+    ///
+    /// # Errors
+    ///
+    /// If `media_type` is syntactically invalid, an [`InvalidMediaTypeError`] will be returned.
+    pub fn parse(media_type: &str) -> Result<Self, InvalidMediaTypeError> {
+        let (type_, subtype, parameters) = split(media_type)?;
+        let parameters = match parameters {
+            Some(parameters) => parse_parameters(parameters)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters,
+        })
+    }
+
+    /// Returns the top-level type, e.g. `image`
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    /// Returns the subtype, e.g. `png`
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// Returns the type and subtype without parameters, e.g. `image/png`
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    /// Returns all parameters as pairs of lowercase name and value, in their original order
+    pub fn parameters(&self) -> &[(String, String)] {
+        &self.parameters
+    }
+
+    /// Returns the value of the first parameter `name`, which is compared case-insensitively
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(parameter, _)| parameter.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Removes all parameters `name`, which is compared case-insensitively
+    pub fn remove_parameter(&mut self, name: &str) {
+        self.parameters
+            .retain(|(parameter, _)| !parameter.eq_ignore_ascii_case(name));
+    }
+
+    /// Returns the classification of the type and subtype
+    pub fn conformance(&self) -> Conformance {
+        classify(&self.type_, &self.subtype)
+    }
+}
+
+impl std::str::FromStr for Mime {
+    type Err = InvalidMediaTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for Mime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.parameters {
+            if is_token(value) {
+                write!(f, "; {}={}", name, value)?;
+            } else {
+                write!(f, "; {}=\"", name)?;
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        write!(f, "\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error within [`Cookie::mime()`](Cookie::mime) or [`Cookie::mime_buffer()`](Cookie::mime_buffer)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum MimeError {
+    /// The MIME flags could not be applied
+    #[error("could not apply MIME flags")]
+    Flags(#[source] SetFlagsError),
+    /// Analyzing the file or buffer failed
+    #[error("could not analyze data")]
+    Analyze(#[source] Error),
+    /// The result of `libmagic` is not a valid media type
+    #[error("invalid MIME result")]
+    Invalid(#[source] InvalidMediaTypeError),
+}
+
+impl Cookie<Load> {
+    /// Returns the parsed MIME type and encoding of the contents of the file `filename`
+    ///
+    /// The analysis uses [`Flags::MIME`] and [`Flags::ERROR`] instead of the flags of the cookie,
+    /// see [`with_flags()`](Cookie::with_flags).
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+    ///
+    /// let mime = cookie.mime("data/tests/rust-logo-128x128-blk.png")?;
+    /// assert_eq!(mime.essence(), "image/png");
+    /// assert_eq!(mime.parameter("charset"), Some("binary"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the flags can not be applied, a [`MimeError::Flags`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`MimeError::Analyze`] will be returned.
+    ///
+    /// If the result is not a valid media type, e.g. for [`Flags::CONTINUE`] results, a [`MimeError::Invalid`] will be returned.
+    #[doc(alias = "magic_file")]
+    pub fn mime<P: AsRef<Path>>(&self, filename: P) -> Result<Mime, MimeError> {
+        let result = self
+            .with_flags(Flags::ERROR | Flags::MIME)
+            .map_err(MimeError::Flags)?
+            .file(filename)
+            .map_err(MimeError::Analyze)?;
+        Mime::parse(&result).map_err(MimeError::Invalid)
+    }
+
+    /// Returns the parsed MIME type and encoding of the contents of the `buffer`
+    ///
+    /// See [`mime()`](Cookie::mime).
+    ///
+    /// # Errors
+    ///
+    /// See [`mime()`](Cookie::mime).
+    #[doc(alias = "magic_buffer")]
+    pub fn mime_buffer(&self, buffer: &[u8]) -> Result<Mime, MimeError> {
+        let result = self
+            .with_flags(Flags::ERROR | Flags::MIME)
+            .map_err(MimeError::Flags)?
+            .buffer(buffer)
+            .map_err(MimeError::Analyze)?;
+        Mime::parse(&result).map_err(MimeError::Invalid)
+    }
+}
+
+/// Splits `media_type` into type, subtype and the unparsed parameters after the first `;`
+///
+/// Whitespace between the essence and the `;` is allowed, see [`validate()`] for the syntax.
+fn split(media_type: &str) -> Result<(&str, &str, Option<&str>), InvalidMediaTypeError> {
+    let (essence, parameters) = match media_type.find(';') {
+        Some(index) => (
+            media_type[..index].trim_end_matches(is_ows),
+            Some(&media_type[index + 1..]),
+        ),
+        None => (media_type, None),
+    };
+
+    let (type_, subtype) = split_essence(essence)?;
+    Ok((type_, subtype, parameters))
+}
+
+/// Splits `type "/" subtype` and validates both are tokens
+fn split_essence(essence: &str) -> Result<(&str, &str), InvalidMediaTypeError> {
+    let mut parts = essence.splitn(2, '/');
+    let type_ = parts.next().unwrap_or_default();
+    let subtype = parts.next().ok_or(InvalidMediaTypeError {
+        reason: "missing \"/\" between type and subtype",
+    })?;
+
+    if !is_token(type_) {
+        return Err(InvalidMediaTypeError {
+            reason: "type is not a token",
+        });
+    }
+    if !is_token(subtype) {
+        return Err(InvalidMediaTypeError {
+            reason: "subtype is not a token",
+        });
+    }
+
+    Ok((type_, subtype))
+}
+
+/// Splits and validates `parameters` into pairs of lowercase name and unquoted value
+fn parse_parameters(parameters: &str) -> Result<Vec<(String, String)>, InvalidMediaTypeError> {
+    // quoted-string values may contain ";", so this can not simply split
+    let mut parsed = Vec::new();
+    let mut rest = parameters;
+    loop {
+        rest = rest.trim_start_matches(is_ows);
+        if rest.is_empty() {
+            return Ok(parsed);
+        }
+
+        let name_end = rest.find('=').ok_or(InvalidMediaTypeError {
+            reason: "parameter without \"=\"",
+        })?;
+        if !is_token(&rest[..name_end]) {
+            return Err(InvalidMediaTypeError {
+                reason: "parameter name is not a token",
+            });
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = &rest[name_end + 1..];
+
+        let (value, value_end) = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let len = quoted_string_len(quoted)?;
+                (unquote(&quoted[..len]), len + 2)
+            }
+            None => {
+                let end = rest.find(|c| c == ';' || is_ows(c)).unwrap_or(rest.len());
+                if !is_token(&rest[..end]) {
+                    return Err(InvalidMediaTypeError {
+                        reason: "parameter value is neither a token nor a quoted string",
+                    });
+                }
+                (rest[..end].to_string(), end)
+            }
+        };
+        parsed.push((name, value));
+        rest = rest[value_end..].trim_start_matches(is_ows);
+
+        match rest.strip_prefix(';') {
+            Some(next) => rest = next,
+            None if rest.is_empty() => return Ok(parsed),
+            None => {
+                return Err(InvalidMediaTypeError {
+                    reason: "parameters not separated by \";\"",
+                })
+            }
+        }
+    }
+}
+
+/// Returns the length of the quoted string content up to, but not including, its closing quote
+fn quoted_string_len(quoted: &str) -> Result<usize, InvalidMediaTypeError> {
+    let mut escaped = false;
+    for (index, c) in quoted.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok(index),
+            '\t' | ' '..='~' => {}
+            _ if !c.is_ascii() => {}
+            _ => break,
+        }
+    }
+    Err(InvalidMediaTypeError {
+        reason: "unterminated or invalid quoted string",
+    })
+}
+
+/// Removes the escaping backslashes of a valid quoted string content
+fn unquote(content: &str) -> String {
+    let mut unquoted = String::with_capacity(content.len());
+    let mut escaped = false;
+    for c in content.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unquoted.push(c);
+            escaped = false;
+        }
+    }
+    unquoted
+}
+
+fn is_ows(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn is_tchar(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+fn is_token(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_tchar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, Conformance, Mime};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn valid() {
+        assert_eq!(validate("image/png").unwrap(), Conformance::Standard);
+        assert_eq!(
+            validate("text/plain; charset=us-ascii").unwrap(),
+            Conformance::Standard
+        );
+        assert_eq!(
+            validate("text/plain;charset=\"a;b\";format=flowed").unwrap(),
+            Conformance::Standard
+        );
+        assert_eq!(
+            validate("application/x-empty; charset=binary").unwrap(),
+            Conformance::Unregistered
+        );
+        assert_eq!(
+            validate("inode/x-empty; charset=binary").unwrap(),
+            Conformance::Nonstandard
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(validate("").is_err());
+        assert!(validate("image").is_err());
+        assert!(validate("image/").is_err());
+        assert!(validate("image/png extra").is_err());
+        assert!(validate("image/png; charset").is_err());
+        assert!(validate("image/png; charset=\"binary").is_err());
+        assert!(validate("image/png; a=b c=d").is_err());
+        assert!(validate("image/png\n- text/plain").is_err());
+    }
+
+    #[test]
+    fn mime() {
+        let mime = Mime::parse("Text/Plain; Charset=\"us-\\\"ascii\"; format=flowed").unwrap();
+        assert_eq!(mime.type_(), "text");
+        assert_eq!(mime.subtype(), "plain");
+        assert_eq!(mime.essence(), "text/plain");
+        assert_eq!(mime.parameter("charset"), Some("us-\"ascii"));
+        assert_eq!(mime.parameter("FORMAT"), Some("flowed"));
+        assert_eq!(mime.parameters().len(), 2);
+        assert_eq!(
+            mime.to_string(),
+            "text/plain; charset=\"us-\\\"ascii\"; format=flowed"
+        );
+        assert_eq!(mime.to_string().parse::<Mime>().unwrap(), mime);
+
+        let mut mime = mime;
+        mime.remove_parameter("CHARSET");
+        assert_eq!(mime.to_string(), "text/plain; format=flowed");
+
+        let mime: Mime = "inode/directory".parse().unwrap();
+        assert_eq!(mime.conformance(), Conformance::Nonstandard);
+        assert!(mime.parameters().is_empty());
+
+        assert!(Mime::parse("image/png\n- text/plain").is_err());
+    }
+
+    #[test]
+    fn whitespace_before_parameters() {
+        assert_eq!(
+            validate("image/png ; charset=binary").unwrap(),
+            Conformance::Standard
+        );
+        let mime = Mime::parse("image/png \t; charset=binary").unwrap();
+        assert_eq!(mime.essence(), "image/png");
+        assert_eq!(mime.parameter("charset"), Some("binary"));
+
+        assert!(validate(" image/png; charset=binary").is_err());
+        assert!(validate("image/png ").is_err());
+    }
+
+    #[test]
+    fn cookie_mime() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let mime = cookie.mime(path).unwrap();
+        assert_eq!(mime.to_string(), "image/png; charset=binary");
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(cookie.mime_buffer(&data).unwrap(), mime);
+        assert_eq!(cookie.flags(), Flags::ERROR);
+    }
+}
@@ -0,0 +1,130 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Interoperability with the [`mime` crate](https://crates.io/crates/mime)
+//!
+//! Web frameworks commonly use [`mime::Mime`](::mime::Mime) for content types.
+//! [`Cookie::media_type()`](Cookie::media_type) returns the MIME result of `libmagic` as such directly,
+//! and [`Mime`] converts with [`TryFrom`].
+//!
+//! Non-standard results of `libmagic` such as `inode/directory` are syntactically valid and kept as they are,
+//! check [`Mime::conformance()`] before e.g. sending them to clients.
+//! Note that the results have a `charset` parameter, so compare with constants such as [`mime::IMAGE_PNG`](::mime::IMAGE_PNG)
+//! by [`essence_str()`](::mime::Mime::essence_str).
+//!
+//! Requires the `mime` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+//!
+//! let mime: mime::Mime = cookie.media_type("data/tests/rust-logo-128x128-blk.png")?;
+//! assert_eq!(mime.essence_str(), "image/png");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::convert::TryFrom;
+use std::path::Path;
+
+use crate::cookie::Load;
+use crate::media_type::{Mime, MimeError};
+use crate::Cookie;
+
+/// Error within [`Cookie::media_type()`](Cookie::media_type) or [`Cookie::media_type_buffer()`](Cookie::media_type_buffer)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum MediaTypeError {
+    /// The MIME result could not be obtained or parsed, see [`Cookie::mime()`](Cookie::mime)
+    #[error("could not get MIME result")]
+    Mime(#[source] MimeError),
+    /// The parsed result was rejected by the `mime` crate
+    #[error("could not convert MIME result")]
+    Convert(#[source] ::mime::FromStrError),
+}
+
+impl TryFrom<&Mime> for ::mime::Mime {
+    type Error = ::mime::FromStrError;
+
+    fn try_from(mime: &Mime) -> Result<Self, Self::Error> {
+        // the canonical form quotes parameter values where needed
+        mime.to_string().parse()
+    }
+}
+
+impl TryFrom<Mime> for ::mime::Mime {
+    type Error = ::mime::FromStrError;
+
+    fn try_from(mime: Mime) -> Result<Self, Self::Error> {
+        Self::try_from(&mime)
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns the MIME type and encoding of the contents of the file `filename` as a [`mime::Mime`](::mime::Mime)
+    ///
+    /// See [`mime()`](Cookie::mime).
+    ///
+    /// # Errors
+    ///
+    /// If [`mime()`](Cookie::mime) fails, a [`MediaTypeError::Mime`] will be returned.
+    ///
+    /// If the `mime` crate rejects the result, a [`MediaTypeError::Convert`] will be returned.
+    #[doc(alias = "magic_file")]
+    pub fn media_type<P: AsRef<Path>>(&self, filename: P) -> Result<::mime::Mime, MediaTypeError> {
+        let mime = self.mime(filename).map_err(MediaTypeError::Mime)?;
+        ::mime::Mime::try_from(mime).map_err(MediaTypeError::Convert)
+    }
+
+    /// Returns the MIME type and encoding of the contents of the `buffer` as a [`mime::Mime`](::mime::Mime)
+    ///
+    /// See [`media_type()`](Cookie::media_type).
+    ///
+    /// # Errors
+    ///
+    /// See [`media_type()`](Cookie::media_type).
+    #[doc(alias = "magic_buffer")]
+    pub fn media_type_buffer(&self, buffer: &[u8]) -> Result<::mime::Mime, MediaTypeError> {
+        let mime = self.mime_buffer(buffer).map_err(MediaTypeError::Mime)?;
+        ::mime::Mime::try_from(mime).map_err(MediaTypeError::Convert)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::cookie::Flags;
+    use crate::media_type::Mime;
+    use crate::Cookie;
+
+    #[test]
+    fn try_from() {
+        let mime = Mime::parse("Text/Plain; Charset=\"us-ascii\"").unwrap();
+        let mime = ::mime::Mime::try_from(mime).unwrap();
+        assert_eq!(mime.essence_str(), "text/plain");
+        assert_eq!(mime.to_string(), "text/plain; charset=us-ascii");
+
+        let mime = Mime::parse("inode/directory; charset=binary").unwrap();
+        let mime = ::mime::Mime::try_from(&mime).unwrap();
+        assert_eq!(mime.essence_str(), "inode/directory");
+    }
+
+    #[test]
+    fn media_type() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let mime = cookie.media_type(path).unwrap();
+        assert_eq!(mime.essence_str(), "image/png");
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(cookie.media_type_buffer(&data).unwrap(), mime);
+        assert_eq!(
+            cookie.media_type("data/tests").unwrap().essence_str(),
+            "inode/directory"
+        );
+    }
+}
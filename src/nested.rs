@@ -0,0 +1,316 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Recursive analysis of nested containers
+//!
+//! Archives often contain further compressed data or archives, e.g. a ZIP with a "tar.gz" inside.
+//! [`Cookie::detect_recursive()`](Cookie::detect_recursive) analyzes data and descends into the supported containers
+//! up to a maximum depth, returning a tree of [`Node`]s with what is really inside.
+//!
+//! Supported containers depend on the enabled features, see [`decompress`](crate::decompress) and [`archive`](crate::archive).
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use std::io::Read;
+
+/// Maximum number of bytes of a single container member that are analyzed and descended into
+pub const CONTENT_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Maximum number of bytes extracted from all containers of a single [`Cookie::detect_recursive()`](Cookie::detect_recursive)
+///
+/// This bounds the memory usage for e.g. decompression bombs.
+pub const TOTAL_LIMIT: usize = 64 * 1024 * 1024;
+
+/// Reason why the children of a [`Node`] are missing or incomplete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Stop {
+    /// The container is nested deeper than the maximum depth
+    Depth,
+    /// The content exceeds [`CONTENT_LIMIT`] or the [`TOTAL_LIMIT`] was reached
+    Size,
+    /// The container is identical to one of its ancestors, e.g. a ZIP quine
+    Cycle,
+    /// The container is invalid or could not be read
+    Invalid,
+}
+
+/// Analysis result of data within a tree of nested containers
+#[derive(Debug)]
+pub struct Node {
+    name: Option<String>,
+    result: Result<String, Error>,
+    children: Vec<Node>,
+    stop: Option<Stop>,
+}
+
+impl Node {
+    /// Returns the member's path within its parent archive
+    ///
+    /// This is `None` for the analyzed data itself and for decompressed content.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the textual result of the analysis of this data
+    pub fn result(&self) -> &Result<String, Error> {
+        &self.result
+    }
+
+    /// Returns the content of this container, empty if this is no supported container
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Returns why the [`children()`](Node::children) are missing or incomplete, if they are
+    pub fn stop(&self) -> Option<Stop> {
+        self.stop
+    }
+}
+
+/// Supported container format
+#[derive(Debug, Clone, Copy)]
+enum Container {
+    #[cfg(any(feature = "gzip", feature = "xz", feature = "zstd"))]
+    Compressed(crate::decompress::Compression),
+    #[cfg(feature = "zip")]
+    Zip,
+    #[cfg(feature = "tar")]
+    Tar,
+}
+
+impl Container {
+    fn detect(data: &[u8]) -> Option<Self> {
+        #[cfg(any(feature = "gzip", feature = "xz", feature = "zstd"))]
+        if let Some(compression) = crate::decompress::Compression::detect(data) {
+            return Some(Self::Compressed(compression));
+        }
+        #[cfg(feature = "zip")]
+        if data.starts_with(b"PK\x03\x04") {
+            return Some(Self::Zip);
+        }
+        #[cfg(feature = "tar")]
+        if data.get(257..262) == Some(b"ustar") {
+            return Some(Self::Tar);
+        }
+        None
+    }
+}
+
+/// Extracted content of a container member
+struct Member {
+    name: Option<String>,
+    content: Vec<u8>,
+    truncated: bool,
+}
+
+/// State of a single recursive analysis
+struct Walk<'c> {
+    cookie: &'c Cookie<Load>,
+    max_depth: usize,
+    budget: usize,
+    /// Hashes of the containers from the root to the current node
+    ancestors: Vec<u64>,
+}
+
+impl Walk<'_> {
+    fn node(&mut self, name: Option<String>, data: &[u8], truncated: bool, depth: usize) -> Node {
+        let mut node = Node {
+            name,
+            result: self.cookie.buffer(data),
+            children: Vec::new(),
+            stop: None,
+        };
+
+        let container = match Container::detect(data) {
+            None => return node,
+            Some(container) => container,
+        };
+        if truncated {
+            node.stop = Some(Stop::Size);
+            return node;
+        }
+        if depth >= self.max_depth {
+            node.stop = Some(Stop::Depth);
+            return node;
+        }
+        let hash = {
+            use std::hash::{Hash, Hasher};
+
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            data.hash(&mut hasher);
+            hasher.finish()
+        };
+        if self.ancestors.contains(&hash) {
+            node.stop = Some(Stop::Cycle);
+            return node;
+        }
+
+        let mut members = Vec::new();
+        node.stop = self.extract(container, data, &mut members).err();
+
+        self.ancestors.push(hash);
+        for member in members {
+            let child = self.node(member.name, &member.content, member.truncated, depth + 1);
+            node.children.push(child);
+        }
+        self.ancestors.pop();
+
+        node
+    }
+
+    /// Reads the next member from `reader` within the limits
+    fn read<R: Read>(&mut self, name: Option<String>, reader: R) -> Result<Member, Stop> {
+        if self.budget == 0 {
+            return Err(Stop::Size);
+        }
+        let limit = CONTENT_LIMIT.min(self.budget);
+
+        let mut content = Vec::new();
+        reader
+            .take(limit as u64 + 1)
+            .read_to_end(&mut content)
+            .map_err(|_| Stop::Invalid)?;
+        let truncated = content.len() > limit;
+        content.truncate(limit);
+        self.budget -= content.len();
+
+        Ok(Member {
+            name,
+            content,
+            truncated,
+        })
+    }
+
+    /// Extracts the members of `container` until an error
+    fn extract(
+        &mut self,
+        container: Container,
+        data: &[u8],
+        members: &mut Vec<Member>,
+    ) -> Result<(), Stop> {
+        match container {
+            #[cfg(any(feature = "gzip", feature = "xz", feature = "zstd"))]
+            Container::Compressed(compression) => {
+                let decoder = compression.decoder(data).map_err(|_| Stop::Invalid)?;
+                members.push(self.read(None, decoder)?);
+            }
+            #[cfg(feature = "zip")]
+            Container::Zip => {
+                let mut archive =
+                    zip::ZipArchive::new(std::io::Cursor::new(data)).map_err(|_| Stop::Invalid)?;
+                for index in 0..archive.len() {
+                    let member = archive.by_index(index).map_err(|_| Stop::Invalid)?;
+                    if member.is_dir() {
+                        continue;
+                    }
+                    let name = member.name().to_string();
+                    members.push(self.read(Some(name), member)?);
+                }
+            }
+            #[cfg(feature = "tar")]
+            Container::Tar => {
+                let mut archive = tar::Archive::new(data);
+                for member in archive.entries().map_err(|_| Stop::Invalid)? {
+                    let member = member.map_err(|_| Stop::Invalid)?;
+                    if !member.header().entry_type().is_file() {
+                        continue;
+                    }
+                    let name = member
+                        .path()
+                        .map_err(|_| Stop::Invalid)?
+                        .to_string_lossy()
+                        .into_owned();
+                    members.push(self.read(Some(name), member)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Cookie<Load> {
+    /// Returns a tree of textual descriptions of `buffer` and of the content of its nested containers
+    ///
+    /// Each node is analyzed with [`buffer()`](Cookie::buffer). Compressed data, ZIP and TAR archives are descended into
+    /// if their feature is enabled, up to `max_depth` levels of nesting. With a `max_depth` of `0` only `buffer` itself is analyzed.
+    ///
+    /// Descending stops at identical nested containers and is bounded by [`CONTENT_LIMIT`] and [`TOTAL_LIMIT`],
+    /// see [`Node::stop()`].
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use magic::nested::Node;
+    ///
+    /// fn print(node: &Node, depth: usize) {
+    ///     println!("{:indent$}{:?}: {:?}", "", node.name(), node.result(), indent = depth * 2);
+    ///     for child in node.children() {
+    ///         print(child, depth + 1);
+    ///     }
+    /// }
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// let upload = std::fs::read("upload.zip")?;
+    /// print(&cookie.detect_recursive(&upload, 4), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn detect_recursive(&self, buffer: &[u8], max_depth: usize) -> Node {
+        let mut walk = Walk {
+            cookie: self,
+            max_depth,
+            budget: TOTAL_LIMIT,
+            ancestors: Vec::new(),
+        };
+        walk.node(None, buffer, false, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tar")]
+    #[test]
+    fn detect_recursive() {
+        use super::Stop;
+        use crate::cookie::Flags;
+
+        fn tar(name: &str, content: &[u8]) -> Vec<u8> {
+            let mut archive = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, name, content).unwrap();
+            archive.into_inner().unwrap()
+        }
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let inner = tar("hello.py", b"#!/usr/bin/env python\nprint('Hello, world!')");
+        let outer = tar("inner.tar", &inner);
+
+        let root = cookie.detect_recursive(&outer, 2);
+        assert_eq!(root.name(), None);
+        assert_eq!(root.stop(), None);
+        let child = &root.children()[0];
+        assert_eq!(child.name(), Some("inner.tar"));
+        let grandchild = &child.children()[0];
+        assert_eq!(grandchild.name(), Some("hello.py"));
+        assert_eq!(
+            grandchild.result().as_ref().unwrap(),
+            "Python script, ASCII text executable"
+        );
+        assert!(grandchild.children().is_empty());
+
+        let root = cookie.detect_recursive(&outer, 1);
+        assert_eq!(root.children()[0].stop(), Some(Stop::Depth));
+        assert!(root.children()[0].children().is_empty());
+    }
+}
@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Normalization of analysis results for stable comparisons
+//!
+//! The wording of `libmagic` results changes between `libmagic` and database versions,
+//! which breaks exact assertions e.g. in snapshot or golden tests.
+//! [`normalize()`] reduces a result to a more stable form, at the cost of losing information.
+//!
+//! Note that this can not make results identical across all versions,
+//! since databases might detect a file completely differently.
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::normalize::{normalize, Normalization};
+//!
+//! let result = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+//! assert_eq!(normalize(result, Normalization::Summary), "PNG image data");
+//!
+//! let result = "image/png; charset=binary";
+//! assert_eq!(normalize(result, Normalization::MimeType), "image/png");
+//! ```
+
+/// How much to [`normalize()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Normalization {
+    /// Canonicalize whitespace
+    ///
+    /// Leading and trailing whitespace is removed, other runs of whitespace are replaced by a single space.
+    Whitespace,
+    /// Canonicalize whitespace and strip details
+    ///
+    /// Only the first match of a [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) result is kept
+    /// and only up to the first ", " (comma and space) which usually separates the details,
+    /// e.g. image dimensions or versions.
+    Summary,
+    /// Reduce a MIME result to the lowercase MIME type without parameters
+    ///
+    /// This is meant for results of [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE)
+    /// or [`Flags::MIME`](crate::cookie::Flags::MIME), e.g. "image/png; charset=binary" becomes "image/png".
+    MimeType,
+}
+
+/// Separator between matches of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) results with [`Flags::RAW`](crate::cookie::Flags::RAW)
+pub(crate) const CONTINUE_SEPARATOR: &str = "\n- ";
+
+/// Separator between matches of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) results without [`Flags::RAW`](crate::cookie::Flags::RAW),
+/// which escapes the newline
+const CONTINUE_SEPARATOR_ESCAPED: &str = "\\012- ";
+
+/// Returns the individual matches of a [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) `result`
+pub(crate) fn split_matches(result: &str) -> impl Iterator<Item = &str> {
+    result
+        .split(CONTINUE_SEPARATOR_ESCAPED)
+        .flat_map(|escaped| escaped.split(CONTINUE_SEPARATOR))
+}
+
+/// Returns the `result` of an analysis in a more stable form
+///
+/// See [`Normalization`] for what is normalized.
+pub fn normalize(result: &str, normalization: Normalization) -> String {
+    match normalization {
+        Normalization::Whitespace => canonicalize_whitespace(result),
+        Normalization::Summary => {
+            let first_match = split_matches(result).next().unwrap_or(result);
+            let summary = first_match.split(", ").next().unwrap_or(first_match);
+            canonicalize_whitespace(summary)
+        }
+        Normalization::MimeType => {
+            let mime_type = result.split(';').next().unwrap_or(result);
+            canonicalize_whitespace(mime_type).to_ascii_lowercase()
+        }
+    }
+}
+
+fn canonicalize_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, Normalization};
+
+    #[test]
+    fn whitespace() {
+        assert_eq!(
+            normalize(
+                "  Python script,\tASCII text\nexecutable ",
+                Normalization::Whitespace
+            ),
+            "Python script, ASCII text executable"
+        );
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(
+            normalize(
+                "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced",
+                Normalization::Summary
+            ),
+            "PNG image data"
+        );
+        assert_eq!(
+            normalize(
+                "Zip archive data, at least v2.0 to extract\n- data",
+                Normalization::Summary
+            ),
+            "Zip archive data"
+        );
+        assert_eq!(
+            normalize(
+                "Zip archive data, at least v2.0 to extract\\012- data",
+                Normalization::Summary
+            ),
+            "Zip archive data"
+        );
+        assert_eq!(normalize("data", Normalization::Summary), "data");
+    }
+
+    #[test]
+    fn mime_type() {
+        assert_eq!(
+            normalize("image/png; charset=binary", Normalization::MimeType),
+            "image/png"
+        );
+        assert_eq!(
+            normalize("Text/X-Python", Normalization::MimeType),
+            "text/x-python"
+        );
+    }
+}
@@ -0,0 +1,117 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of objects in object storage such as S3, GCS or Azure Blob Storage
+//!
+//! Downloading complete objects just to analyze them is wasteful, since `libmagic` only looks at the start of the data for most formats.
+//! [`sniff_object()`] instead issues a ranged GET for a prefix of the object via the
+//! [`object_store` crate](https://crates.io/crates/object_store) and analyzes that.
+//!
+//! Requires the `object_store` feature.
+
+use crate::cookie::{Error, Load};
+use crate::sniff::Sniff;
+use crate::Cookie;
+
+/// Default number of bytes that are fetched and analyzed
+///
+/// This is the same as the second step of [`SniffBuilder`](crate::sniff::SniffBuilder).
+pub const DEFAULT_PREFIX_LEN: usize = 64 * 1024;
+
+/// Error within [`sniff_object()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum ObjectError {
+    /// Fetching the prefix of the object failed
+    #[error("could not fetch object")]
+    Fetch(#[source] object_store::Error),
+    /// Analyzing the fetched prefix failed
+    #[error("could not analyze object")]
+    Analyze(#[source] Error),
+}
+
+/// Fetches up to `len` leading bytes of the object at `location` in `store` and analyzes them with [`Cookie::buffer()`](Cookie::buffer)
+///
+/// Objects shorter than `len` are fetched completely. See [`DEFAULT_PREFIX_LEN`] for a reasonable `len`.
+///
+/// Only the fetch is asynchronous, the analysis of the prefix blocks.
+///
+/// Requires the `object_store` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example(store: &dyn object_store::ObjectStore) -> Result<(), Box<dyn std::error::Error>> {
+/// use magic::object::{sniff_object, DEFAULT_PREFIX_LEN};
+///
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+///
+/// let location = object_store::path::Path::from("lake/part-0001");
+/// let sniff = sniff_object(store, &location, &cookie, DEFAULT_PREFIX_LEN).await?;
+/// println!("{}: {}", location, sniff.result());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If fetching the object failed, an [`ObjectError::Fetch`] will be returned.
+///
+/// If there was an `libmagic` internal error, an [`ObjectError::Analyze`] will be returned.
+pub async fn sniff_object(
+    store: &dyn object_store::ObjectStore,
+    location: &object_store::path::Path,
+    cookie: &Cookie<Load>,
+    len: usize,
+) -> Result<Sniff, ObjectError> {
+    let options = object_store::GetOptions {
+        range: Some(object_store::GetRange::Bounded(0..len)),
+        ..Default::default()
+    };
+    let prefix = store
+        .get_opts(location, options)
+        .await
+        .map_err(ObjectError::Fetch)?
+        .bytes()
+        .await
+        .map_err(ObjectError::Fetch)?;
+
+    // stores might return more than requested if the server ignored the range
+    let prefix = &prefix[..prefix.len().min(len)];
+    let result = cookie.buffer(prefix).map_err(ObjectError::Analyze)?;
+
+    Ok(Sniff {
+        result,
+        bytes_read: prefix.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sniff_object;
+    use object_store::ObjectStore;
+
+    #[test]
+    fn sniff_object_prefix() {
+        use crate::cookie::Flags;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let store = object_store::memory::InMemory::new();
+        let location = object_store::path::Path::from("images/logo.png");
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+
+        futures::executor::block_on(async {
+            store.put(&location, png.into()).await.unwrap();
+
+            let sniff = sniff_object(&store, &location, &cookie, 64).await.unwrap();
+            assert_eq!(
+                sniff.result(),
+                "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+            );
+            assert_eq!(sniff.bytes_read(), 64);
+        });
+    }
+}
@@ -0,0 +1,581 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Allow and deny rules over analysis results
+//!
+//! A [`Policy`] is an ordered list of [`Rule`]s, each of which matches a [`Pattern`] of media types,
+//! [`Category`]s or descriptions and leads to a [`Decision`].
+//! [`Policy::evaluate()`] returns the decision of the first matching rule, e.g. for uploads or mail attachments.
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::policy::{Category, Decision, Pattern, Policy};
+//!
+//! let policy = Policy::new(Decision::Review)
+//!     .deny(Pattern::Category(Category::Executable))
+//!     .allow(Pattern::mime_type("image/*"))
+//!     .allow(Pattern::mime_type("application/pdf"));
+//!
+//! assert_eq!(policy.evaluate("image/png", None).decision(), Decision::Allow);
+//! assert_eq!(policy.evaluate("application/x-dosexec", None).decision(), Decision::Deny);
+//! assert_eq!(policy.evaluate("text/plain", None).decision(), Decision::Review);
+//! ```
+//!
+//! For the common case of uploads that must be of a few known types, a [`ContentPolicy`] enforces an allowlist
+//! and a maximum size, analyzing the content itself and returning a typed [`Violation`].
+
+use std::path::Path;
+
+use crate::cookie::Load;
+use crate::kind::identified;
+use crate::normalize::{normalize, split_matches, Normalization};
+use crate::query::QueryError;
+use crate::Cookie;
+
+/// Detected media types of archives and compressed data
+const ARCHIVE_TYPES: &[&str] = &[
+    "application/gzip",
+    "application/vnd.rar",
+    "application/x-7z-compressed",
+    "application/x-bzip2",
+    "application/x-rar",
+    "application/x-tar",
+    "application/x-xz",
+    "application/zip",
+    "application/zstd",
+];
+
+/// Outcome of a [`Rule`] or a [`Policy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+    /// The content is acceptable
+    Allow,
+    /// The content must be rejected
+    Deny,
+    /// The content needs a manual review
+    Review,
+}
+
+/// Group of media types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Category {
+    /// Native executables and shared libraries
+    Executable,
+    /// Archives and compressed data
+    Archive,
+}
+
+impl Category {
+    fn contains(self, mime_type: &str) -> bool {
+        match self {
+            Self::Executable => crate::spoof::EXECUTABLE_TYPES.contains(&mime_type),
+            Self::Archive => ARCHIVE_TYPES.contains(&mime_type),
+        }
+    }
+}
+
+/// What a [`Rule`] matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Pattern {
+    /// Media type, exactly as in `image/png` or with wildcard subtype as in `image/*`
+    ///
+    /// Use [`Pattern::mime_type()`] to normalize the media type.
+    MimeType(String),
+    /// Any media type of the [`Category`]
+    Category(Category),
+    /// Text contained in the description, compared case-insensitively
+    ///
+    /// Use [`Pattern::description()`] to normalize the text.
+    Description(String),
+}
+
+impl Pattern {
+    /// Creates a [`Pattern::MimeType`] for `mime_type`, normalized to lowercase without parameters
+    pub fn mime_type(mime_type: &str) -> Self {
+        Self::MimeType(normalize(mime_type, Normalization::MimeType))
+    }
+
+    /// Creates a [`Pattern::Description`] for `text`, normalized to lowercase
+    pub fn description(text: &str) -> Self {
+        Self::Description(text.to_lowercase())
+    }
+
+    fn matches(&self, mime_types: &[String], description: Option<&str>) -> bool {
+        match self {
+            Self::MimeType(pattern) => {
+                mime_types
+                    .iter()
+                    .any(|mime_type| match pattern.strip_suffix("/*") {
+                        Some("*") => true,
+                        Some(top_level) => mime_type
+                            .split_once('/')
+                            .map_or(false, |(mime_top_level, _)| mime_top_level == top_level),
+                        None => mime_type == pattern,
+                    })
+            }
+            Self::Category(category) => mime_types
+                .iter()
+                .any(|mime_type| category.contains(mime_type)),
+            Self::Description(text) => description.map_or(false, |description| {
+                split_matches(description)
+                    .any(|description| description.to_lowercase().contains(text.as_str()))
+            }),
+        }
+    }
+}
+
+/// Single entry of a [`Policy`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pattern: Pattern,
+    decision: Decision,
+}
+
+impl Rule {
+    /// Creates a rule that leads to `decision` if `pattern` matches
+    pub fn new(pattern: Pattern, decision: Decision) -> Self {
+        Self { pattern, decision }
+    }
+
+    /// Returns what this rule matches
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    /// Returns the decision if this rule matches
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+}
+
+/// Ordered rules with a default decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    rules: Vec<Rule>,
+    default: Decision,
+}
+
+impl Policy {
+    /// Creates a policy without rules that leads to `default` for all content
+    pub fn new(default: Decision) -> Self {
+        Self {
+            rules: Vec::new(),
+            default,
+        }
+    }
+
+    /// Appends `rule`, which applies if no earlier rule matches
+    pub fn rule(mut self, rule: Rule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Appends a rule that allows content matching `pattern`
+    pub fn allow(self, pattern: Pattern) -> Self {
+        self.rule(Rule::new(pattern, Decision::Allow))
+    }
+
+    /// Appends a rule that denies content matching `pattern`
+    pub fn deny(self, pattern: Pattern) -> Self {
+        self.rule(Rule::new(pattern, Decision::Deny))
+    }
+
+    /// Appends a rule that requires a review of content matching `pattern`
+    pub fn review(self, pattern: Pattern) -> Self {
+        self.rule(Rule::new(pattern, Decision::Review))
+    }
+
+    /// Returns the rules in order of evaluation
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Returns the decision if no rule matches
+    pub fn default_decision(&self) -> Decision {
+        self.default
+    }
+
+    /// Evaluates the results of an analysis
+    ///
+    /// `mime_type` is the result of [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) and
+    /// `description` the optional result without flags for textual descriptions.
+    /// Both may contain several matches of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE),
+    /// a rule then matches if it matches any of them.
+    /// Rules for descriptions never match without `description`.
+    pub fn evaluate(&self, mime_type: &str, description: Option<&str>) -> Evaluation<'_> {
+        let mime_types: Vec<String> = split_matches(mime_type)
+            .map(|mime_type| normalize(mime_type, Normalization::MimeType))
+            .collect();
+
+        match self
+            .rules
+            .iter()
+            .find(|rule| rule.pattern.matches(&mime_types, description))
+        {
+            Some(rule) => Evaluation {
+                decision: rule.decision,
+                rule: Some(rule),
+            },
+            None => Evaluation {
+                decision: self.default,
+                rule: None,
+            },
+        }
+    }
+}
+
+/// Result of [`Policy::evaluate()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Evaluation<'p> {
+    decision: Decision,
+    rule: Option<&'p Rule>,
+}
+
+impl<'p> Evaluation<'p> {
+    /// Returns the decision for the content
+    pub fn decision(&self) -> Decision {
+        self.decision
+    }
+
+    /// Returns the first matching rule, `None` if the default decision applies
+    pub fn rule(&self) -> Option<&'p Rule> {
+        self.rule
+    }
+}
+
+/// Reason of a [`ContentPolicy`] to reject content
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// The content is larger than [`ContentPolicy::max_size()`]
+    #[error("content of {size} bytes exceeds the maximum size of {max_size} bytes")]
+    TooLarge {
+        /// Size of the content in bytes
+        size: u64,
+        /// Maximum size in bytes
+        max_size: u64,
+    },
+    /// The content was not identified and [`ContentPolicy::allow_unknown()`] is not set
+    #[error("content type could not be identified")]
+    Unknown,
+    /// The detected MIME type is not in the allowlist
+    #[error("content type {} is not allowed", .0)]
+    Disallowed(String),
+}
+
+/// Error of [`ContentPolicy::check_buffer()`] and [`ContentPolicy::check_file()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum CheckError {
+    /// The content violates the policy
+    #[error("content violates policy")]
+    Violation(#[source] Violation),
+    /// The size of the file could not be determined
+    #[error("could not read file metadata")]
+    Metadata(#[source] std::io::Error),
+    /// Analyzing the content failed
+    #[error("could not analyze content")]
+    Analyze(#[source] QueryError),
+}
+
+impl CheckError {
+    /// Returns the violation if the content was rejected by the policy, `None` if the check itself failed
+    pub fn violation(&self) -> Option<&Violation> {
+        match self {
+            Self::Violation(violation) => Some(violation),
+            _ => None,
+        }
+    }
+}
+
+/// Allowlist of MIME types with a maximum size, e.g. for upload endpoints
+///
+/// Unlike a [`Policy`], which only evaluates results, a `ContentPolicy` analyzes the content with a cookie
+/// and either accepts it or returns a [`Violation`].
+/// Content is only accepted if it is allowed by any of the patterns, so a policy without patterns rejects all identified content.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use magic::policy::{ContentPolicy, Violation};
+///
+/// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+/// let policy = ContentPolicy::new()
+///     .allow("image/png")
+///     .allow("image/jpeg")
+///     .max_size(1024 * 1024);
+///
+/// let mime_type = policy.check_file(&cookie, "data/tests/rust-logo-128x128-blk.png")?;
+/// assert_eq!(mime_type, "image/png");
+///
+/// let err = policy.check_buffer(&cookie, b"\x00\xff\x10\x80").unwrap_err();
+/// assert_eq!(err.violation(), Some(&Violation::Unknown));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentPolicy {
+    patterns: Vec<Pattern>,
+    max_size: Option<u64>,
+    allow_unknown: bool,
+}
+
+impl ContentPolicy {
+    /// Creates a policy that rejects all content
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the MIME type `mime_type`, e.g. `image/png`, or all subtypes of a type, e.g. `image/*`
+    ///
+    /// The MIME type is normalized, see [`Pattern::mime_type()`].
+    pub fn allow(self, mime_type: &str) -> Self {
+        self.allow_pattern(Pattern::mime_type(mime_type))
+    }
+
+    /// Allows the media types of the `category`
+    pub fn allow_category(self, category: Category) -> Self {
+        self.allow_pattern(Pattern::Category(category))
+    }
+
+    fn allow_pattern(mut self, pattern: Pattern) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+
+    /// Rejects content larger than `max_size` bytes
+    ///
+    /// Defaults to no limit. The size is checked before the analysis.
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Accepts content that `libmagic` does not identify, regardless of the allowlist
+    ///
+    /// Unidentified content has the generic MIME type `application/octet-stream`, see [`identified()`](crate::kind::identified).
+    /// Defaults to `false`, which rejects it with [`Violation::Unknown`].
+    pub fn allow_unknown(mut self, allow_unknown: bool) -> Self {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+
+    /// Returns whether the MIME type `mime_type` is allowed by any of the patterns
+    ///
+    /// Unlike [`check()`](ContentPolicy::check), this ignores the size and whether the content was identified.
+    pub fn allows(&self, mime_type: &str) -> bool {
+        self.allows_normalized(&normalize(mime_type, Normalization::MimeType))
+    }
+
+    /// Checks a [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) result for content of `size` bytes
+    ///
+    /// Returns the normalized MIME type if the content is accepted.
+    ///
+    /// # Errors
+    ///
+    /// If the content is not accepted, the [`Violation`] will be returned.
+    pub fn check(&self, mime_type: &str, size: u64) -> Result<String, Violation> {
+        self.check_size(size)?;
+        self.check_mime_type(mime_type)
+    }
+
+    /// Analyzes the `buffer` with the `cookie` and checks it against the policy
+    ///
+    /// Returns the normalized MIME type if the content is accepted.
+    /// The flags of the cookie do not matter, see [`Cookie::query()`](Cookie::query).
+    ///
+    /// # Errors
+    ///
+    /// If the content is not accepted, a [`CheckError::Violation`] will be returned.
+    ///
+    /// If the analysis failed, a [`CheckError::Analyze`] will be returned.
+    pub fn check_buffer(&self, cookie: &Cookie<Load>, buffer: &[u8]) -> Result<String, CheckError> {
+        self.check_size(buffer.len() as u64)
+            .map_err(CheckError::Violation)?;
+        let mime_type = cookie
+            .query()
+            .mime()
+            .of_buffer(buffer)
+            .map_err(CheckError::Analyze)?;
+        self.check_mime_type(&mime_type)
+            .map_err(CheckError::Violation)
+    }
+
+    /// Analyzes the contents of the file `filename` with the `cookie` and checks them against the policy
+    ///
+    /// See [`check_buffer()`](ContentPolicy::check_buffer).
+    ///
+    /// # Errors
+    ///
+    /// If the size of the file can not be determined, a [`CheckError::Metadata`] will be returned.
+    ///
+    /// Otherwise see [`check_buffer()`](ContentPolicy::check_buffer).
+    pub fn check_file<P: AsRef<Path>>(
+        &self,
+        cookie: &Cookie<Load>,
+        filename: P,
+    ) -> Result<String, CheckError> {
+        let filename = filename.as_ref();
+        if self.max_size.is_some() {
+            let size = std::fs::metadata(filename)
+                .map_err(CheckError::Metadata)?
+                .len();
+            self.check_size(size).map_err(CheckError::Violation)?;
+        }
+        let mime_type = cookie
+            .query()
+            .mime()
+            .of_file(filename)
+            .map_err(CheckError::Analyze)?;
+        self.check_mime_type(&mime_type)
+            .map_err(CheckError::Violation)
+    }
+
+    fn check_size(&self, size: u64) -> Result<(), Violation> {
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(Violation::TooLarge { size, max_size }),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_mime_type(&self, mime_type: &str) -> Result<String, Violation> {
+        let mime_type = normalize(mime_type, Normalization::MimeType);
+        if identified(&mime_type).is_none() {
+            return if self.allow_unknown {
+                Ok(mime_type)
+            } else {
+                Err(Violation::Unknown)
+            };
+        }
+
+        if self.allows_normalized(&mime_type) {
+            Ok(mime_type)
+        } else {
+            Err(Violation::Disallowed(mime_type))
+        }
+    }
+
+    fn allows_normalized(&self, mime_type: &str) -> bool {
+        let mime_types = [mime_type.to_string()];
+        self.patterns
+            .iter()
+            .any(|pattern| pattern.matches(&mime_types, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Category, CheckError, ContentPolicy, Decision, Pattern, Policy, Violation};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn evaluate() {
+        let policy = Policy::new(Decision::Deny)
+            .deny(Pattern::Category(Category::Executable))
+            .review(Pattern::description("encrypted"))
+            .allow(Pattern::Category(Category::Archive))
+            .allow(Pattern::mime_type("Image/*"))
+            .allow(Pattern::mime_type("text/plain"));
+
+        let evaluation = policy.evaluate("image/png; charset=binary", None);
+        assert_eq!(evaluation.decision(), Decision::Allow);
+        assert_eq!(evaluation.rule(), Some(&policy.rules()[3]));
+
+        assert_eq!(
+            policy
+                .evaluate("application/zip", Some("Zip archive data, encrypted"))
+                .decision(),
+            Decision::Review
+        );
+        assert_eq!(
+            policy.evaluate("application/zip", None).decision(),
+            Decision::Allow
+        );
+
+        // any match of a polyglot is enough
+        let evaluation = policy.evaluate("image/gif\n- application/x-dosexec", None);
+        assert_eq!(evaluation.decision(), Decision::Deny);
+        assert_eq!(evaluation.rule(), Some(&policy.rules()[0]));
+
+        let evaluation = policy.evaluate("text/x-python", None);
+        assert_eq!(evaluation.decision(), Decision::Deny);
+        assert_eq!(evaluation.rule(), None);
+    }
+
+    #[test]
+    fn content_policy() {
+        let policy = ContentPolicy::new()
+            .allow("Image/*")
+            .allow_category(Category::Archive)
+            .max_size(16);
+
+        assert_eq!(
+            policy.check("image/png; charset=binary", 16),
+            Ok("image/png".to_string())
+        );
+        assert_eq!(
+            policy.check("application/zip", 0),
+            Ok("application/zip".to_string())
+        );
+        assert_eq!(
+            policy.check("image/png", 17),
+            Err(Violation::TooLarge {
+                size: 17,
+                max_size: 16
+            })
+        );
+        assert_eq!(
+            policy.check("text/plain; charset=us-ascii", 4),
+            Err(Violation::Disallowed("text/plain".to_string()))
+        );
+        assert!(policy.allows("IMAGE/GIF"));
+        assert!(!policy.allows("images/gif"));
+        assert_eq!(
+            policy.check("application/octet-stream", 4),
+            Err(Violation::Unknown)
+        );
+        assert_eq!(
+            policy
+                .allow_unknown(true)
+                .check("application/octet-stream", 4),
+            Ok("application/octet-stream".to_string())
+        );
+    }
+
+    #[test]
+    fn content_policy_cookie() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let policy = ContentPolicy::new().allow("image/png");
+
+        assert_eq!(policy.check_file(&cookie, path).unwrap(), "image/png");
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(policy.check_buffer(&cookie, &data).unwrap(), "image/png");
+        assert_eq!(
+            policy
+                .check_buffer(&cookie, b"\x00\xff\x10\x80")
+                .unwrap_err()
+                .violation(),
+            Some(&Violation::Unknown)
+        );
+
+        let policy = policy.max_size(64);
+        assert!(matches!(
+            policy.check_file(&cookie, path),
+            Err(CheckError::Violation(Violation::TooLarge {
+                max_size: 64,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            policy.check_file(&cookie, "data/tests/doesntexist.png"),
+            Err(CheckError::Metadata(_))
+        ));
+    }
+}
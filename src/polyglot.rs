@@ -0,0 +1,155 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Detection of polyglot files that are valid in several formats at once
+//!
+//! `libmagic` reports the strongest match at the start of the data, but some container formats are also
+//! accepted by their readers after arbitrary leading data. For example a GIF image with an appended ZIP archive
+//! is displayed as an image by browsers and extracted as an archive by unzip tools.
+//!
+//! [`identify()`] collects all matches of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) and additionally
+//! re-analyzes the data at the offsets of embedded container signatures, to return all plausible identities.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(magic::cookie::Flags::CONTINUE)?;
+//! let cookie = cookie.load(["data/tests/db-images-png"])?;
+//!
+//! let png = std::fs::read("data/tests/rust-logo-128x128-blk.png")?;
+//! let identities = magic::polyglot::identify(&cookie, &png)?;
+//! assert!(!identities.is_polyglot());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::{Error, Load};
+use crate::normalize::split_matches;
+use crate::Cookie;
+
+/// Maximum number of embedded signatures that are re-analyzed
+///
+/// This bounds the effort for data with many (possibly bogus) signatures.
+pub const RESCAN_LIMIT: usize = 16;
+
+/// Signatures of container formats whose readers accept leading data
+const SECONDARY_SIGNATURES: &[&[u8]] = &[
+    // ZIP local file header, also JAR, Office Open XML etc.
+    b"PK\x03\x04",
+    b"%PDF-",
+    b"Rar!\x1a\x07",
+    b"7z\xbc\xaf\x27\x1c",
+];
+
+/// Results without a specific identity
+pub(crate) const GENERIC_RESULTS: &[&str] = &["data", "application/octet-stream"];
+
+/// Single plausible identity of the data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    offset: usize,
+    result: String,
+}
+
+impl Identity {
+    /// Returns the offset of the data that was analyzed for this identity, `0` for the start
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the textual result of the analysis
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+}
+
+/// Result of [`identify()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identities {
+    identities: Vec<Identity>,
+}
+
+impl Identities {
+    /// Returns the distinct identities, strongest match at the start first, then by offset
+    pub fn identities(&self) -> &[Identity] {
+        &self.identities
+    }
+
+    /// Returns whether the data has more than one identity
+    pub fn is_polyglot(&self) -> bool {
+        self.identities.len() > 1
+    }
+}
+
+/// Returns all plausible identities of `buffer`
+///
+/// The `cookie` should be opened with [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) to report all matches at each offset.
+/// Generic results such as "data" are not identities. Identities with the same textual result are only reported once.
+///
+/// # Errors
+///
+/// If there was an `libmagic` internal error, an [`Error`] will be returned.
+pub fn identify(cookie: &Cookie<Load>, buffer: &[u8]) -> Result<Identities, Error> {
+    let mut identities = Vec::new();
+    add_identities(&mut identities, 0, &cookie.buffer(buffer)?);
+
+    for offset in secondary_offsets(buffer).take(RESCAN_LIMIT) {
+        add_identities(&mut identities, offset, &cookie.buffer(&buffer[offset..])?);
+    }
+
+    Ok(Identities { identities })
+}
+
+fn add_identities(identities: &mut Vec<Identity>, offset: usize, result: &str) {
+    for result in split_matches(result) {
+        if GENERIC_RESULTS.contains(&result)
+            || identities.iter().any(|identity| identity.result == result)
+        {
+            continue;
+        }
+        identities.push(Identity {
+            offset,
+            result: result.to_string(),
+        });
+    }
+}
+
+/// Returns the offsets after the start of `buffer` where any of the [`SECONDARY_SIGNATURES`] start
+fn secondary_offsets(buffer: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    (1..buffer.len()).filter(move |offset| {
+        SECONDARY_SIGNATURES
+            .iter()
+            .any(|signature| buffer[*offset..].starts_with(signature))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::identify;
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn png_with_appended_zip() {
+        let cookie = Cookie::open(Flags::ERROR | Flags::CONTINUE).unwrap();
+        let databases = ["data/tests/db-images-png", "data/tests/db-archive-zip"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let mut data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let png_len = data.len();
+        data.extend_from_slice(b"PK\x03\x04\x14\x00\x00\x00");
+
+        let identities = identify(&cookie, &data).unwrap();
+        assert!(identities.is_polyglot());
+        let identities = identities.identities();
+        assert_eq!(identities.len(), 2);
+        assert_eq!(identities[0].offset(), 0);
+        assert_eq!(
+            identities[0].result(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(identities[1].offset(), png_len);
+        assert_eq!(identities[1].result(), "Zip archive data");
+    }
+}
@@ -0,0 +1,142 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of a bounded prefix of files
+//!
+//! [`Cookie::file()`](Cookie::file) lets `libmagic` open and read files itself,
+//! which some environments must avoid, e.g. FUSE mounts, network filesystems or sandboxes.
+//! [`Cookie::file_prefix()`](Cookie::file_prefix) instead reads at most a bounded prefix of the file
+//! and analyzes it like [`Cookie::buffer()`](Cookie::buffer).
+
+use std::io::Read;
+use std::path::Path;
+
+use crate::cookie::{Error, Load, Parameter, ParameterError};
+use crate::Cookie;
+
+/// Error within [`Cookie::file_prefix()`](Cookie::file_prefix) or [`Cookie::file_prefix_len()`](Cookie::file_prefix_len)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum PrefixError {
+    /// The file could not be opened or read
+    #[error("could not read file prefix")]
+    Read(#[source] std::io::Error),
+    /// The file is no regular file, e.g. a directory, FIFO or device
+    #[error("could not read file prefix of non-regular file")]
+    NotRegular,
+    /// The default prefix length could not be determined
+    #[error("could not get prefix length")]
+    Parameter(#[source] ParameterError),
+    /// Analyzing the prefix failed
+    #[error("could not analyze file prefix")]
+    Analyze(#[source] Error),
+}
+
+impl Cookie<Load> {
+    /// Returns a textual description of the first bytes of the file `filename`, which is read by this crate instead of `libmagic`
+    ///
+    /// At most as many bytes as `libmagic` would read itself are read, see [`Parameter::BytesMax`].
+    /// See [`file_prefix_len()`](Cookie::file_prefix_len) for an explicit length.
+    ///
+    /// Unlike [`file()`](Cookie::file), special files like directories, FIFOs or devices are not described but rejected
+    /// before they are opened, since reading them could block or never end. Symbolic links are followed.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // open a new cookie with default flags and database
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// let file_description = cookie.file_prefix("data/tests/rust-logo-128x128-blk.png")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the parameter can not be accessed, a [`PrefixError::Parameter`] will be returned.
+    ///
+    /// See [`file_prefix_len()`](Cookie::file_prefix_len) for other errors.
+    pub fn file_prefix<P: AsRef<Path>>(&self, filename: P) -> Result<String, PrefixError> {
+        let max_len = self
+            .parameter(Parameter::BytesMax)
+            .map_err(PrefixError::Parameter)?;
+        self.file_prefix_len(filename, max_len)
+    }
+
+    /// Returns a textual description of at most `max_len` first bytes of the file `filename`
+    ///
+    /// See [`file_prefix()`](Cookie::file_prefix).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // open a new cookie with default flags and database
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// // only read the first 4 KiB
+    /// let file_description = cookie.file_prefix_len("data/tests/rust-logo-128x128-blk.png", 4 * 1024)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the file can not be opened or read, a [`PrefixError::Read`] will be returned.
+    ///
+    /// If the file is no regular file, a [`PrefixError::NotRegular`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`PrefixError::Analyze`] will be returned.
+    pub fn file_prefix_len<P: AsRef<Path>>(
+        &self,
+        filename: P,
+        max_len: usize,
+    ) -> Result<String, PrefixError> {
+        let filename = filename.as_ref();
+        let metadata = std::fs::metadata(filename).map_err(PrefixError::Read)?;
+        if !metadata.is_file() {
+            return Err(PrefixError::NotRegular);
+        }
+
+        let file = std::fs::File::open(filename).map_err(PrefixError::Read)?;
+        let mut prefix = Vec::new();
+        file.take(max_len as u64)
+            .read_to_end(&mut prefix)
+            .map_err(PrefixError::Read)?;
+        self.buffer(&prefix).map_err(PrefixError::Analyze)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefixError;
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn file_prefix() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        assert_eq!(
+            cookie.file_prefix(path).unwrap(),
+            cookie.file(path).unwrap()
+        );
+        // the image header with the dimensions is not read
+        assert_eq!(cookie.file_prefix_len(path, 16).unwrap(), "PNG image data");
+
+        assert!(matches!(
+            cookie.file_prefix("data/tests/doesntexist.png"),
+            Err(PrefixError::Read(_))
+        ));
+        assert!(matches!(
+            cookie.file_prefix("data/tests"),
+            Err(PrefixError::NotRegular)
+        ));
+    }
+}
@@ -0,0 +1,440 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Queries with typed results
+//!
+//! [`Cookie::query()`](Cookie::query) starts a [`Query`] whose [`Output`] determines both the flags of the analysis
+//! and the type of its result, e.g. [`Query::mime()`] returns the normalized MIME type and
+//! [`Query::extensions()`] a list of extensions, instead of strings that need to be parsed.
+//!
+//! The flags are applied with [`Cookie::with_flags()`](Cookie::with_flags) for the query only,
+//! so queries are safe on a cookie that is shared with other code.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+//! let path = "data/tests/rust-logo-128x128-blk.png";
+//!
+//! let mime: String = cookie.query().mime().follow_symlinks().of_file(path)?;
+//! assert_eq!(mime, "image/png");
+//!
+//! let extensions: Vec<String> = cookie.query().extensions().of_file(path)?;
+//! assert_eq!(extensions, ["png"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::marker::PhantomData;
+use std::path::Path;
+
+use crate::cookie::{Error, Flags, Load, SetFlagsError};
+use crate::normalize::{normalize, split_matches, Normalization};
+use crate::Cookie;
+
+/// Error within [`Query::of_file()`] or [`Query::of_buffer()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum QueryError {
+    /// The flags of the query could not be applied
+    #[error("could not apply query flags")]
+    Flags(#[source] SetFlagsError),
+    /// Analyzing the file or buffer failed
+    #[error("could not analyze data")]
+    Analyze(#[source] Error),
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Description {}
+    impl Sealed for super::MimeType {}
+    impl Sealed for super::MimeEncoding {}
+    impl Sealed for super::Extensions {}
+    impl Sealed for super::Apple {}
+}
+
+/// Kind of result of a [`Query`]
+pub trait Output: private::Sealed {
+    /// Type of the parsed result
+    type Value;
+
+    /// Flags that select this kind of result
+    const FLAGS: Flags;
+
+    /// Parses the textual `result` of `libmagic`
+    fn parse(result: String) -> Self::Value;
+}
+
+/// Textual description, e.g. "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Description;
+
+impl Output for Description {
+    type Value = String;
+    const FLAGS: Flags = Flags::empty();
+
+    fn parse(result: String) -> Self::Value {
+        result
+    }
+}
+
+/// MIME type without parameters, e.g. "image/png", see [`Normalization::MimeType`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MimeType;
+
+impl Output for MimeType {
+    type Value = String;
+    const FLAGS: Flags = Flags::MIME_TYPE;
+
+    fn parse(result: String) -> Self::Value {
+        normalize(&result, Normalization::MimeType)
+    }
+}
+
+/// MIME encoding, e.g. "us-ascii" or "binary", see [`Flags::MIME_ENCODING`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MimeEncoding;
+
+impl Output for MimeEncoding {
+    type Value = String;
+    const FLAGS: Flags = Flags::MIME_ENCODING;
+
+    fn parse(result: String) -> Self::Value {
+        result
+    }
+}
+
+/// File extensions, e.g. `["jpeg", "jpg"]`, empty if unknown, see [`Flags::EXTENSION`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Extensions;
+
+impl Output for Extensions {
+    type Value = Vec<String>;
+    const FLAGS: Flags = Flags::EXTENSION;
+
+    fn parse(result: String) -> Self::Value {
+        crate::spoof::parse_extensions(&result)
+    }
+}
+
+/// Apple creator and type codes, see [`Flags::APPLE`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Apple;
+
+impl Output for Apple {
+    type Value = AppleCodes;
+    const FLAGS: Flags = Flags::APPLE;
+
+    fn parse(result: String) -> Self::Value {
+        // both codes have four characters, e.g. "8BIMPNGf"
+        match (result.get(..4), result.get(4..)) {
+            (Some(creator), Some(file_type)) if file_type.len() == 4 => AppleCodes {
+                creator: creator.to_string(),
+                file_type: file_type.to_string(),
+            },
+            _ => AppleCodes {
+                creator: result,
+                file_type: String::new(),
+            },
+        }
+    }
+}
+
+/// Result of [`Query::apple()`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
+pub struct AppleCodes {
+    creator: String,
+    file_type: String,
+}
+
+impl AppleCodes {
+    /// Returns the creator code, e.g. "8BIM", or "UNKN" if unknown
+    pub fn creator(&self) -> &str {
+        &self.creator
+    }
+
+    /// Returns the type code, e.g. "PNGf", or "UNKN" if unknown
+    pub fn file_type(&self) -> &str {
+        &self.file_type
+    }
+}
+
+/// Query of [`Cookie::query()`](Cookie::query) with a result of kind `O`
+#[derive(Debug)]
+pub struct Query<'c, O> {
+    cookie: &'c Cookie<Load>,
+    flags: Flags,
+    output: PhantomData<O>,
+}
+
+impl<'c, O: Output> Query<'c, O> {
+    fn output<T: Output>(self) -> Query<'c, T> {
+        Query {
+            cookie: self.cookie,
+            flags: self.flags,
+            output: PhantomData,
+        }
+    }
+
+    /// Returns the textual description, which is the default
+    pub fn description(self) -> Query<'c, Description> {
+        self.output()
+    }
+
+    /// Returns the MIME type
+    pub fn mime(self) -> Query<'c, MimeType> {
+        self.output()
+    }
+
+    /// Returns the MIME encoding
+    pub fn mime_encoding(self) -> Query<'c, MimeEncoding> {
+        self.output()
+    }
+
+    /// Returns the file extensions
+    pub fn extensions(self) -> Query<'c, Extensions> {
+        self.output()
+    }
+
+    /// Returns the Apple creator and type codes
+    pub fn apple(self) -> Query<'c, Apple> {
+        self.output()
+    }
+
+    /// Follows symbolic links, see [`Flags::SYMLINK`]
+    pub fn follow_symlinks(mut self) -> Self {
+        self.flags |= Flags::SYMLINK;
+        self
+    }
+
+    /// Looks inside compressed files, see [`Flags::COMPRESS`]
+    pub fn decompress(mut self) -> Self {
+        self.flags |= Flags::COMPRESS;
+        self
+    }
+
+    /// Reads block or character special files, see [`Flags::DEVICES`]
+    pub fn devices(mut self) -> Self {
+        self.flags |= Flags::DEVICES;
+        self
+    }
+
+    /// Returns the result for the contents of the file `filename`
+    ///
+    /// # Errors
+    ///
+    /// If the flags can not be applied, a [`QueryError::Flags`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`QueryError::Analyze`] will be returned.
+    #[doc(alias = "magic_file")]
+    pub fn of_file<P: AsRef<Path>>(self, filename: P) -> Result<O::Value, QueryError> {
+        let guard = self
+            .cookie
+            .with_flags(self.flags | O::FLAGS)
+            .map_err(QueryError::Flags)?;
+        let result = guard.file(filename).map_err(QueryError::Analyze)?;
+        Ok(O::parse(result))
+    }
+
+    /// Returns the result for the contents of the `buffer`
+    ///
+    /// # Errors
+    ///
+    /// See [`of_file()`](Query::of_file).
+    #[doc(alias = "magic_buffer")]
+    pub fn of_buffer(self, buffer: &[u8]) -> Result<O::Value, QueryError> {
+        let guard = self
+            .cookie
+            .with_flags(self.flags | O::FLAGS)
+            .map_err(QueryError::Flags)?;
+        let result = guard.buffer(buffer).map_err(QueryError::Analyze)?;
+        Ok(O::parse(result))
+    }
+}
+
+/// Splits a [`Flags::CONTINUE`] `result` into its non-empty matches
+fn parse_matches(result: &str) -> Vec<String> {
+    split_matches(result)
+        .filter(|result| !result.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Cookie<Load> {
+    /// Starts a query with a typed result, see mod [`query`](crate::query)
+    ///
+    /// The query returns the textual description unless another kind of result is chosen,
+    /// and only uses the flags chosen for it and [`Flags::ERROR`], not those of the cookie.
+    pub fn query(&self) -> Query<'_, Description> {
+        Query {
+            cookie: self,
+            flags: Flags::ERROR,
+            output: PhantomData,
+        }
+    }
+
+    /// Returns all matches for the contents of the file `filename`, strongest match first
+    ///
+    /// The file is analyzed with the flags of the cookie plus [`Flags::CONTINUE`],
+    /// whose result is split at the separator `\n- ` (or `\012- ` without [`Flags::RAW`]) between the matches.
+    /// Note that `libmagic` might also report weak matches such as "data" after the strong ones.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+    ///
+    /// let matches = cookie.file_all("data/tests/rust-logo-128x128-blk.png")?;
+    /// assert_eq!(matches[0], "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_file()`].
+    #[doc(alias = "magic_file")]
+    pub fn file_all<P: AsRef<Path>>(&self, filename: P) -> Result<Vec<String>, QueryError> {
+        let guard = self
+            .with_added_flags(Flags::CONTINUE)
+            .map_err(QueryError::Flags)?;
+        let result = guard.file(filename).map_err(QueryError::Analyze)?;
+        Ok(parse_matches(&result))
+    }
+
+    /// Returns all matches for the contents of the `buffer`, strongest match first
+    ///
+    /// See [`file_all()`](Cookie::file_all).
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_buffer()`].
+    #[doc(alias = "magic_buffer")]
+    pub fn buffer_all(&self, buffer: &[u8]) -> Result<Vec<String>, QueryError> {
+        let guard = self
+            .with_added_flags(Flags::CONTINUE)
+            .map_err(QueryError::Flags)?;
+        let result = guard.buffer(buffer).map_err(QueryError::Analyze)?;
+        Ok(parse_matches(&result))
+    }
+
+    /// Returns the file extensions of the contents of the file `filename`, e.g. `["jpeg", "jpg"]`
+    ///
+    /// The slash-separated result of [`Flags::EXTENSION`] is split, and the unknown result `???` becomes empty.
+    /// See [`Query::extensions()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+    ///
+    /// let extensions = cookie.extensions("data/tests/rust-logo-128x128-blk.png")?;
+    /// assert_eq!(extensions, ["png"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_file()`].
+    pub fn extensions<P: AsRef<Path>>(&self, filename: P) -> Result<Vec<String>, QueryError> {
+        self.query().extensions().of_file(filename)
+    }
+
+    /// Returns the file extensions of the contents of the `buffer`
+    ///
+    /// See [`extensions()`](Cookie::extensions).
+    ///
+    /// # Errors
+    ///
+    /// See [`Query::of_buffer()`].
+    pub fn extensions_buffer(&self, buffer: &[u8]) -> Result<Vec<String>, QueryError> {
+        self.query().extensions().of_buffer(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Apple, Output};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn query() {
+        let cookie = Cookie::open(Flags::empty()).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let data = std::fs::read(path).unwrap();
+
+        assert_eq!(
+            cookie.query().of_file(path).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(cookie.query().mime().of_buffer(&data).unwrap(), "image/png");
+        assert_eq!(cookie.query().extensions().of_file(path).unwrap(), ["png"]);
+        assert!(cookie.query().mime().of_file("data/tests/\0.png").is_err());
+
+        // the flags of the cookie are restored
+        assert_eq!(cookie.flags(), Flags::empty());
+    }
+
+    #[test]
+    fn parse_matches() {
+        assert_eq!(
+            super::parse_matches("GIF image data\n- Zip archive data\\012- data"),
+            ["GIF image data", "Zip archive data", "data"]
+        );
+        assert_eq!(super::parse_matches("data"), ["data"]);
+        assert!(super::parse_matches("").is_empty());
+    }
+
+    #[test]
+    fn all_matches() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let matches = cookie.file_all(path).unwrap();
+        assert_eq!(
+            matches[0],
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        let data = std::fs::read(path).unwrap();
+        assert_eq!(cookie.buffer_all(&data).unwrap(), matches);
+        assert_eq!(cookie.flags(), Flags::ERROR);
+    }
+
+    #[test]
+    fn extensions() {
+        let cookie = Cookie::open(Flags::empty()).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        assert_eq!(
+            cookie
+                .extensions("data/tests/rust-logo-128x128-blk.png")
+                .unwrap(),
+            ["png"]
+        );
+        assert!(cookie
+            .extensions_buffer(b"no magic here")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn apple_codes() {
+        let codes = Apple::parse("8BIMPNGf".to_string());
+        assert_eq!(codes.creator(), "8BIM");
+        assert_eq!(codes.file_type(), "PNGf");
+
+        let codes = Apple::parse("UNKN".to_string());
+        assert_eq!(codes.creator(), "UNKN");
+        assert_eq!(codes.file_type(), "");
+    }
+}
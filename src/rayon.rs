@@ -0,0 +1,159 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Parallel analysis of many files using the [`rayon` crate](https://crates.io/crates/rayon)
+//!
+//! [`MagicParallelExt::magic_detect()`] analyzes the paths of a parallel iterator
+//! with one cookie per worker thread, see mod [`thread_local`](crate::thread_local).
+//!
+//! Requires the `rayon` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::rayon::MagicParallelExt;
+//! use magic::thread_local::Config;
+//! use rayon::prelude::*;
+//!
+//! let paths = ["data/tests/rust-logo-128x128-blk.png", "Cargo.toml"];
+//! let results: Vec<_> = paths
+//!     .iter()
+//!     .par_bridge()
+//!     .magic_detect(&Config::default())
+//!     .collect();
+//! for (path, result) in results {
+//!     match result {
+//!         Ok(description) => println!("{}: {}", path, description),
+//!         Err(err) => eprintln!("{}: {}", path, err),
+//!     }
+//! }
+//! ```
+
+use std::path::Path;
+
+use ::rayon::iter::plumbing::UnindexedConsumer;
+use ::rayon::iter::ParallelIterator;
+
+use crate::cookie::Error;
+use crate::thread_local::{Config, ThreadLocalError};
+
+/// Error within [`MagicParallelExt::magic_detect()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum ParallelError {
+    /// The cookie of the worker thread could not be opened or loaded
+    #[error("could not create cookie")]
+    Cookie(#[source] ThreadLocalError),
+    /// Analyzing the file failed
+    #[error("could not analyze file")]
+    Analyze(#[source] Error),
+}
+
+/// Parallel iterator of [`MagicParallelExt::magic_detect()`]
+///
+/// Yields each path along with its result of [`Cookie::file()`](crate::Cookie::file).
+#[derive(Debug)]
+pub struct MagicDetect<I> {
+    paths: I,
+    config: Config,
+}
+
+impl<I, P> ParallelIterator for MagicDetect<I>
+where
+    I: ParallelIterator<Item = P>,
+    P: AsRef<Path> + Send,
+{
+    type Item = (P, Result<String, ParallelError>);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        let config = self.config;
+        self.paths
+            .map(move |path| {
+                let result = match config.with_cookie(|cookie| cookie.file(path.as_ref())) {
+                    Ok(result) => result.map_err(ParallelError::Analyze),
+                    Err(err) => Err(ParallelError::Cookie(err)),
+                };
+                (path, result)
+            })
+            .drive_unindexed(consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        self.paths.opt_len()
+    }
+}
+
+/// Extension of parallel iterators of paths to analyze the files
+pub trait MagicParallelExt: ParallelIterator + Sized {
+    /// Analyzes the file of each path with the thread-local cookie of `config`
+    ///
+    /// Each path is yielded along with its result, in no particular order.
+    /// An error for one file does not stop the iteration.
+    ///
+    /// See [`Config::with_cookie()`](Config::with_cookie).
+    #[doc(alias = "magic_file")]
+    fn magic_detect(self, config: &Config) -> MagicDetect<Self>;
+}
+
+impl<I, P> MagicParallelExt for I
+where
+    I: ParallelIterator<Item = P>,
+    P: AsRef<Path> + Send,
+{
+    fn magic_detect(self, config: &Config) -> MagicDetect<Self> {
+        MagicDetect {
+            paths: self,
+            config: config.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MagicParallelExt, ParallelError};
+    use crate::cookie::Flags;
+    use crate::thread_local::Config;
+    use ::rayon::prelude::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn magic_detect() {
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let config = Config::new(Flags::ERROR, databases);
+
+        let paths = vec![
+            "data/tests/rust-logo-128x128-blk.png",
+            "data/tests/doesntexist.png",
+        ];
+        let mut results: Vec<_> = paths
+            .clone()
+            .into_iter()
+            .par_bridge()
+            .magic_detect(&config)
+            .collect();
+        results.sort_by_key(|(path, _)| *path);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, paths[1]);
+        assert!(matches!(results[0].1, Err(ParallelError::Analyze(_))));
+        assert_eq!(results[1].0, paths[0]);
+        assert_eq!(
+            results[1].1.as_ref().unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+
+        let config = Config::new(
+            Flags::ERROR,
+            "data/tests/doesntexist.mgc".try_into().unwrap(),
+        );
+        let results: Vec<_> = paths
+            .into_iter()
+            .par_bridge()
+            .magic_detect(&config)
+            .collect();
+        assert!(matches!(results[0].1, Err(ParallelError::Cookie(_))));
+    }
+}
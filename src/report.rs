@@ -0,0 +1,251 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis results with their provenance
+//!
+//! The same content can be described differently depending on the flags, the databases and the `libmagic` version.
+//! A [`Report`] bundles a result with all of those and the time of the analysis, e.g. for audit trails
+//! in forensic or compliance pipelines. With the `serde` feature, reports can be serialized, e.g. to JSON.
+//!
+//! A [`Reporter`] loads the databases itself, so the reported databases are the ones that were actually loaded.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use std::convert::TryInto;
+//!
+//! use magic::report::Reporter;
+//!
+//! let cookie = magic::Cookie::open(magic::cookie::Flags::ERROR)?;
+//! let reporter = Reporter::load(cookie, ["data/tests/db-images-png"].try_into()?)?;
+//!
+//! let report = reporter.file("data/tests/rust-logo-128x128-blk.png")?;
+//! assert_eq!(report.result(), "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced");
+//! assert!(report.flags().contains(magic::cookie::Flags::ERROR));
+//! assert_eq!(report.databases(), [std::path::Path::new("data/tests/db-images-png")]);
+//! println!("analyzed with libmagic {} at {:?}", report.libmagic_version(), report.started());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::cookie::{DatabasePaths, Error, Flags, FlagsGuard, Load, LoadError, State};
+use crate::version::Version;
+use crate::Cookie;
+
+/// What a [`Report`] is about
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
+#[non_exhaustive]
+pub enum Subject {
+    /// The contents of the file at the path
+    File(PathBuf),
+    /// A buffer of the length in bytes
+    Buffer(usize),
+}
+
+/// Result of an analysis with its provenance, see mod [`report`](crate::report)
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
+pub struct Report {
+    subject: Subject,
+    result: String,
+    flags: Flags,
+    databases: Vec<PathBuf>,
+    libmagic_version: Version,
+    crate_version: String,
+    started: SystemTime,
+    finished: SystemTime,
+}
+
+impl Report {
+    /// Returns what was analyzed
+    pub fn subject(&self) -> &Subject {
+        &self.subject
+    }
+
+    /// Returns the textual result of `libmagic`
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+
+    /// Returns the flags of the cookie for the analysis
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// Returns the paths of the loaded databases
+    pub fn databases(&self) -> &[PathBuf] {
+        &self.databases
+    }
+
+    /// Returns the version of the `libmagic` C library, see [`Version::libmagic()`]
+    pub fn libmagic_version(&self) -> Version {
+        self.libmagic_version
+    }
+
+    /// Returns the version of this Rust crate
+    pub fn crate_version(&self) -> &str {
+        &self.crate_version
+    }
+
+    /// Returns when the analysis started
+    pub fn started(&self) -> SystemTime {
+        self.started
+    }
+
+    /// Returns when the analysis finished
+    pub fn finished(&self) -> SystemTime {
+        self.finished
+    }
+}
+
+/// Loaded cookie that knows its databases and creates [`Report`]s
+#[derive(Debug)]
+pub struct Reporter {
+    cookie: Cookie<Load>,
+    databases: Vec<PathBuf>,
+}
+
+impl Reporter {
+    /// Loads the `databases` into the `cookie`, see [`Cookie::load()`](Cookie::load)
+    ///
+    /// The database paths are resolved before loading, see [`default_database_path()`](crate::default_database_path)
+    /// for [`DatabasePaths::default()`].
+    ///
+    /// # Errors
+    ///
+    /// See [`Cookie::load()`](Cookie::load).
+    pub fn load<S: State>(
+        cookie: Cookie<S>,
+        databases: DatabasePaths,
+    ) -> Result<Self, LoadError<S>> {
+        let resolved = databases.resolve();
+        let cookie = cookie.load(databases)?;
+        Ok(Self {
+            cookie,
+            databases: resolved,
+        })
+    }
+
+    /// Returns the loaded cookie, e.g. to change its flags
+    pub fn cookie(&self) -> &Cookie<Load> {
+        &self.cookie
+    }
+
+    /// Returns the paths of the loaded databases
+    pub fn databases(&self) -> &[PathBuf] {
+        &self.databases
+    }
+
+    /// Returns a report for the contents of the file `filename`
+    ///
+    /// See [`Cookie::file()`](Cookie::file).
+    ///
+    /// # Errors
+    ///
+    /// See [`Cookie::file()`](Cookie::file).
+    #[doc(alias = "magic_file")]
+    pub fn file<P: AsRef<Path>>(&self, filename: P) -> Result<Report, Error> {
+        let filename = filename.as_ref();
+        self.report(Subject::File(filename.to_path_buf()), |guard| {
+            guard.file(filename)
+        })
+    }
+
+    /// Returns a report for the contents of the `buffer`
+    ///
+    /// See [`Cookie::buffer()`](Cookie::buffer).
+    ///
+    /// # Errors
+    ///
+    /// See [`Cookie::buffer()`](Cookie::buffer).
+    #[doc(alias = "magic_buffer")]
+    pub fn buffer(&self, buffer: &[u8]) -> Result<Report, Error> {
+        self.report(Subject::Buffer(buffer.len()), |guard| guard.buffer(buffer))
+    }
+
+    fn report<F>(&self, subject: Subject, analysis: F) -> Result<Report, Error>
+    where
+        F: FnOnce(&FlagsGuard<'_>) -> Result<String, Error>,
+    {
+        // the cookie stays locked, so the reported flags are those of the analysis
+        let guard = self.cookie.with_current_flags();
+        let flags = guard.flags();
+        let started = SystemTime::now();
+        let result = analysis(&guard)?;
+        let finished = SystemTime::now();
+        drop(guard);
+
+        Ok(Report {
+            subject,
+            result,
+            flags,
+            databases: self.databases.clone(),
+            libmagic_version: Version::libmagic(),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            started,
+            finished,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::path::Path;
+
+    use super::{Reporter, Subject};
+    use crate::cookie::{DatabasePaths, Flags};
+    use crate::version::Version;
+    use crate::Cookie;
+
+    #[test]
+    fn report() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let reporter = Reporter::load(cookie, databases).unwrap();
+        let database = Path::new("data/tests/db-images-png");
+        assert_eq!(reporter.databases(), [database]);
+        reporter
+            .cookie()
+            .set_flags(Flags::ERROR | Flags::MIME_TYPE)
+            .unwrap();
+
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let report = reporter.file(path).unwrap();
+        assert_eq!(report.subject(), &Subject::File(path.into()));
+        assert_eq!(report.result(), "image/png");
+        assert_eq!(report.flags(), Flags::ERROR | Flags::MIME_TYPE);
+        assert_eq!(report.databases(), [database]);
+        assert_eq!(report.libmagic_version(), Version::libmagic());
+        assert_eq!(report.crate_version(), env!("CARGO_PKG_VERSION"));
+        assert!(report.started() <= report.finished());
+
+        let data = std::fs::read(path).unwrap();
+        let report = reporter.buffer(&data).unwrap();
+        assert_eq!(report.subject(), &Subject::Buffer(data.len()));
+        assert_eq!(report.result(), "image/png");
+
+        assert!(reporter.file("data/tests/doesntexist.png").is_err());
+    }
+
+    #[test]
+    fn default_databases() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let reporter = Reporter::load(cookie, DatabasePaths::default()).unwrap();
+        assert!(!reporter.databases().is_empty());
+        assert_eq!(reporter.databases(), crate::default_database_path());
+
+        let report = reporter
+            .file("data/tests/rust-logo-128x128-blk.png")
+            .unwrap();
+        assert!(report.result().starts_with("PNG image data"));
+        assert_eq!(report.databases(), reporter.databases());
+    }
+}
@@ -0,0 +1,103 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! JSON Schemas with the [`schemars` crate](https://crates.io/crates/schemars)
+//!
+//! The serializable result types implement [`JsonSchema`], e.g. to generate OpenAPI documents for services
+//! that return [`Report`](crate::report::Report)s or [`Analysis`](crate::analysis::Analysis) results.
+//! The schemas describe the representation of mod [`serde`](crate::serde), so use this together with the `serde` feature:
+//! [`Flags`], [`Mime`] and [`Version`] are strings.
+//!
+//! Requires the `schemars` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! let schema = schemars::schema_for!(magic::report::Report);
+//! println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+//! ```
+
+use ::schemars::gen::SchemaGenerator;
+use ::schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
+use ::schemars::JsonSchema;
+
+use crate::cookie::Flags;
+use crate::media_type::Mime;
+use crate::version::Version;
+
+/// Returns the schema of a string with a `description`
+fn string_schema(description: &str) -> Schema {
+    SchemaObject {
+        instance_type: Some(InstanceType::String.into()),
+        metadata: Some(Box::new(Metadata {
+            description: Some(description.to_string()),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
+
+impl JsonSchema for Flags {
+    fn schema_name() -> String {
+        "Flags".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        string_schema("Names of `libmagic` flags separated by ` | `, e.g. \"MIME_TYPE | ERROR\"")
+    }
+}
+
+impl JsonSchema for Mime {
+    fn schema_name() -> String {
+        "Mime".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        string_schema("Media type with optional parameters, e.g. \"text/plain; charset=us-ascii\"")
+    }
+}
+
+impl JsonSchema for Version {
+    fn schema_name() -> String {
+        "Version".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        string_schema("Version of `libmagic` as major and two-digit minor version, e.g. \"5.41\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::schemars::gen::SchemaGenerator;
+    use ::schemars::schema::{InstanceType, Schema};
+    use ::schemars::JsonSchema;
+
+    use crate::analysis::Analysis;
+    use crate::cookie::Flags;
+    use crate::media_type::Mime;
+    use crate::query::AppleCodes;
+    use crate::report::{Report, Subject};
+    use crate::version::Version;
+
+    fn is_string<T: JsonSchema>() -> bool {
+        match T::json_schema(&mut SchemaGenerator::default()) {
+            Schema::Object(object) => object.instance_type == Some(InstanceType::String.into()),
+            Schema::Bool(_) => false,
+        }
+    }
+
+    #[test]
+    fn strings() {
+        assert!(is_string::<Flags>());
+        assert!(is_string::<Mime>());
+        assert!(is_string::<Version>());
+        assert_eq!(Flags::schema_name(), "Flags");
+    }
+
+    static_assertions::assert_impl_all!(Analysis: JsonSchema);
+    static_assertions::assert_impl_all!(AppleCodes: JsonSchema);
+    static_assertions::assert_impl_all!(Report: JsonSchema);
+    static_assertions::assert_impl_all!(Subject: JsonSchema);
+}
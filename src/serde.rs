@@ -0,0 +1,211 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Serialization with the [`serde` crate](https://crates.io/crates/serde)
+//!
+//! Results can be persisted or shipped between services in any `serde` format:
+//! - [`Flags`] are (de)serialized by name, e.g. `"MIME_TYPE | ERROR"`, since their bits depend on the `libmagic` version
+//! - [`Mime`] is (de)serialized as its canonical string, e.g. `"text/plain; charset=us-ascii"`
+//! - [`Version`] is (de)serialized as its string, e.g. `"5.41"`
+//! - [`Analysis`](crate::analysis::Analysis), [`AppleCodes`](crate::query::AppleCodes) and [`Report`](crate::report::Report)
+//!   are (de)serialized as structs
+//!
+//! Errors keep their sources, which can not be serialized, so [`ErrorReport`] captures their messages instead.
+//!
+//! Requires the `serde` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use magic::cookie::Flags;
+//!
+//! let json = serde_json::to_string(&(Flags::MIME_TYPE | Flags::ERROR))?;
+//! assert_eq!(json, r#""MIME_TYPE | ERROR""#);
+//!
+//! let flags: Flags = serde_json::from_str(&json)?;
+//! assert_eq!(flags, Flags::MIME_TYPE | Flags::ERROR);
+//! # Ok(())
+//! # }
+//! ```
+
+use ::serde::de::Error as _;
+use ::serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::cookie::Flags;
+use crate::media_type::Mime;
+use crate::version::Version;
+
+impl Serialize for Flags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Flags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = String::deserialize(deserializer)?;
+        bitflags::parser::from_str(&names).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Mime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Mime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let media_type = String::deserialize(deserializer)?;
+        Mime::parse(&media_type).map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        version.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Serializable messages of an error and its sources
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use magic::serde::ErrorReport;
+///
+/// let cookie = magic::Cookie::open(magic::cookie::Flags::ERROR)?.load(["data/tests/db-images-png"])?;
+///
+/// let err = cookie.query().of_file("data/tests/doesntexist.png").unwrap_err();
+/// let report = ErrorReport::new(&err);
+/// assert_eq!(report.message(), "could not analyze data");
+/// assert!(!report.sources().is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(::schemars::JsonSchema))]
+pub struct ErrorReport {
+    message: String,
+    sources: Vec<String>,
+}
+
+impl ErrorReport {
+    /// Captures the message of `error` and the messages of its sources
+    pub fn new<E: std::error::Error + ?Sized>(error: &E) -> Self {
+        let mut sources = Vec::new();
+        let mut source = error.source();
+        while let Some(err) = source {
+            sources.push(err.to_string());
+            source = err.source();
+        }
+
+        Self {
+            message: error.to_string(),
+            sources,
+        }
+    }
+
+    /// Returns the message of the error
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the messages of the sources of the error, outermost first
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+}
+
+impl std::fmt::Display for ErrorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+        for source in &self.sources {
+            write!(f, ": {}", source)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorReport;
+    use crate::analysis::Analysis;
+    use crate::cookie::Flags;
+    use crate::media_type::Mime;
+    use crate::policy::CheckError;
+    use crate::query::AppleCodes;
+    use crate::report::Report;
+    use crate::version::Version;
+
+    #[test]
+    fn flags() {
+        let flags = Flags::MIME_TYPE | Flags::CONTINUE | Flags::ERROR;
+        let json = serde_json::to_string(&flags).unwrap();
+        assert_eq!(json, r#""MIME_TYPE | CONTINUE | ERROR""#);
+        assert_eq!(serde_json::from_str::<Flags>(&json).unwrap(), flags);
+
+        assert_eq!(serde_json::to_string(&Flags::empty()).unwrap(), r#""""#);
+        assert_eq!(
+            serde_json::from_str::<Flags>(r#""""#).unwrap(),
+            Flags::empty()
+        );
+        assert!(serde_json::from_str::<Flags>(r#""MIME_TYPO""#).is_err());
+    }
+
+    #[test]
+    fn mime() {
+        let mime = Mime::parse("Text/Plain; charset=us-ascii").unwrap();
+        let json = serde_json::to_string(&mime).unwrap();
+        assert_eq!(json, r#""text/plain; charset=us-ascii""#);
+        assert_eq!(serde_json::from_str::<Mime>(&json).unwrap(), mime);
+
+        assert!(serde_json::from_str::<Mime>(r#""text""#).is_err());
+    }
+
+    #[test]
+    fn version() {
+        let json = serde_json::to_string(&Version::new(5, 4)).unwrap();
+        assert_eq!(json, r#""5.04""#);
+        assert_eq!(
+            serde_json::from_str::<Version>(&json).unwrap(),
+            Version::new(5, 4)
+        );
+    }
+
+    #[test]
+    fn error_report() {
+        let err = std::io::Error::new(std::io::ErrorKind::Other, "disk on fire");
+        let err = CheckError::Metadata(err);
+        let report = ErrorReport::new(&err);
+        assert_eq!(report.message(), "could not read file metadata");
+        assert_eq!(report.sources(), ["disk on fire"]);
+        assert_eq!(
+            report.to_string(),
+            "could not read file metadata: disk on fire"
+        );
+    }
+
+    static_assertions::assert_impl_all!(
+        Analysis: ::serde::Serialize,
+        ::serde::de::DeserializeOwned
+    );
+    static_assertions::assert_impl_all!(
+        AppleCodes: ::serde::Serialize,
+        ::serde::de::DeserializeOwned
+    );
+    static_assertions::assert_impl_all!(Report: ::serde::Serialize, ::serde::de::DeserializeOwned);
+    static_assertions::assert_impl_all!(
+        ErrorReport: ::serde::Serialize,
+        ::serde::de::DeserializeOwned
+    );
+}
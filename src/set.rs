@@ -0,0 +1,238 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Explicit precedence between groups of databases
+//!
+//! Databases loaded into a single cookie are merged, and `libmagic` picks the strongest match across all of them.
+//! A [`CookieSet`] instead keeps one cookie per named group of databases, e.g. a specialized signature pack and the
+//! default database. Queries try the groups in the order they were inserted and return the first specific result,
+//! or only try a subset of the groups.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use magic::cookie::Flags;
+//! use magic::set::CookieSet;
+//!
+//! let mut set = CookieSet::new();
+//! set.insert("python", Flags::ERROR, ["data/tests/db-python"])?;
+//! set.insert("images", Flags::ERROR, ["data/tests/db-images-png"])?;
+//!
+//! let found = set.file("data/tests/rust-logo-128x128-blk.png")?.unwrap();
+//! assert_eq!(found.group(), "images");
+//!
+//! assert!(set.file_in(&["python"], "data/tests/rust-logo-128x128-blk.png")?.is_none());
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::{
+    DatabasePaths, Error, Flags, InvalidDatabasePathError, Load, LoadError, Open, OpenError,
+};
+use crate::polyglot::GENERIC_RESULTS;
+use crate::Cookie;
+use std::convert::TryInto;
+use std::path::Path;
+
+/// Error within [`CookieSet::insert()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum CookieSetError {
+    /// Opening the cookie for the group failed
+    #[error("could not open cookie for database group")]
+    Open(#[source] OpenError),
+    /// Loading the databases of the group failed
+    #[error("could not load databases of group")]
+    Load(#[source] LoadError<Open>),
+}
+
+/// First specific result of a [`CookieSet`] query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Found<'s> {
+    group: &'s str,
+    result: String,
+}
+
+impl<'s> Found<'s> {
+    /// Returns the name of the group whose databases produced the result
+    pub fn group(&self) -> &'s str {
+        self.group
+    }
+
+    /// Returns the textual result
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+}
+
+/// Named groups of databases, queried in order of precedence
+#[derive(Debug, Default)]
+pub struct CookieSet {
+    groups: Vec<(String, Cookie<Load>)>,
+}
+
+impl CookieSet {
+    /// Creates an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a cookie with `flags`, loads `databases` into it and adds it as group `name`
+    ///
+    /// The group takes precedence after all previously inserted groups.
+    /// An existing group with the same `name` is replaced, keeping its precedence.
+    ///
+    /// # Errors
+    ///
+    /// If the cookie can not be opened, a [`CookieSetError::Open`] will be returned.
+    ///
+    /// If the databases can not be loaded, a [`CookieSetError::Load`] will be returned.
+    pub fn insert<N, D>(
+        &mut self,
+        name: N,
+        flags: Flags,
+        databases: D,
+    ) -> Result<(), CookieSetError>
+    where
+        N: Into<String>,
+        D: TryInto<DatabasePaths>,
+        D::Error: Into<InvalidDatabasePathError>,
+    {
+        let cookie = Cookie::open(flags)
+            .map_err(CookieSetError::Open)?
+            .load(databases)
+            .map_err(CookieSetError::Load)?;
+
+        let name = name.into();
+        match self.groups.iter_mut().find(|(group, _)| *group == name) {
+            Some((_, existing)) => *existing = cookie,
+            None => self.groups.push((name, cookie)),
+        }
+        Ok(())
+    }
+
+    /// Removes the group `name` and returns its cookie, if it exists
+    pub fn remove(&mut self, name: &str) -> Option<Cookie<Load>> {
+        let index = self.groups.iter().position(|(group, _)| group == name)?;
+        Some(self.groups.remove(index).1)
+    }
+
+    /// Returns the cookie of group `name`, e.g. to change its flags
+    pub fn get(&self, name: &str) -> Option<&Cookie<Load>> {
+        self.groups
+            .iter()
+            .find(|(group, _)| group == name)
+            .map(|(_, cookie)| cookie)
+    }
+
+    /// Returns the names of all groups in order of precedence
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.groups.iter().map(|(group, _)| group.as_str())
+    }
+
+    /// Returns the first specific result for the file `filename` of all groups
+    ///
+    /// Generic results such as "data" are skipped, `None` is returned if no group has a specific result.
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error in any group, a [`cookie::Error`](Error) will be returned.
+    pub fn file<P: AsRef<Path>>(&self, filename: P) -> Result<Option<Found<'_>>, Error> {
+        self.first(None, |cookie| cookie.file(filename.as_ref()))
+    }
+
+    /// Returns the first specific result for the `buffer` of all groups
+    ///
+    /// See [`file()`](CookieSet::file) for details.
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error in any group, a [`cookie::Error`](Error) will be returned.
+    pub fn buffer(&self, buffer: &[u8]) -> Result<Option<Found<'_>>, Error> {
+        self.first(None, |cookie| cookie.buffer(buffer))
+    }
+
+    /// Returns the first specific result for the file `filename` of the groups `names`
+    ///
+    /// The groups are still tried in order of precedence, not in the order of `names`. Unknown names are ignored.
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error in any tried group, a [`cookie::Error`](Error) will be returned.
+    pub fn file_in<P: AsRef<Path>>(
+        &self,
+        names: &[&str],
+        filename: P,
+    ) -> Result<Option<Found<'_>>, Error> {
+        self.first(Some(names), |cookie| cookie.file(filename.as_ref()))
+    }
+
+    /// Returns the first specific result for the `buffer` of the groups `names`
+    ///
+    /// See [`file_in()`](CookieSet::file_in) for details.
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error in any tried group, a [`cookie::Error`](Error) will be returned.
+    pub fn buffer_in(&self, names: &[&str], buffer: &[u8]) -> Result<Option<Found<'_>>, Error> {
+        self.first(Some(names), |cookie| cookie.buffer(buffer))
+    }
+
+    fn first<F>(&self, names: Option<&[&str]>, query: F) -> Result<Option<Found<'_>>, Error>
+    where
+        F: Fn(&Cookie<Load>) -> Result<String, Error>,
+    {
+        for (group, cookie) in &self.groups {
+            if names.map_or(false, |names| !names.contains(&group.as_str())) {
+                continue;
+            }
+
+            let result = query(cookie)?;
+            if !GENERIC_RESULTS.contains(&result.as_str()) {
+                return Ok(Some(Found { group, result }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CookieSet;
+    use crate::cookie::Flags;
+
+    #[test]
+    fn precedence() {
+        let mut set = CookieSet::new();
+        set.insert("python", Flags::ERROR, ["data/tests/db-python"])
+            .unwrap();
+        set.insert("images", Flags::ERROR, ["data/tests/db-images-png"])
+            .unwrap();
+        set.insert(
+            "python",
+            Flags::ERROR | Flags::MIME_TYPE,
+            ["data/tests/db-python"],
+        )
+        .unwrap();
+        assert_eq!(set.names().collect::<Vec<_>>(), ["python", "images"]);
+
+        let script = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        let found = set.buffer(script).unwrap().unwrap();
+        assert_eq!(found.group(), "python");
+        assert_eq!(found.result(), "text/x-python");
+
+        // the PNG is only "application/octet-stream" for the python group
+        let png = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let found = set.buffer(&png).unwrap().unwrap();
+        assert_eq!(found.group(), "images");
+        assert_eq!(
+            found.result(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+
+        assert!(set.buffer_in(&["python"], &png).unwrap().is_none());
+        assert!(set.remove("images").is_some());
+        assert!(set.buffer(&png).unwrap().is_none());
+    }
+}
@@ -0,0 +1,102 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Explicit handling of very short buffers
+//!
+//! `libmagic` results for empty or few-byte buffers depend on the flags, e.g. "empty", "very short file (no magic)",
+//! `application/x-empty` or just "data".
+//! [`Cookie::buffer_min_len()`](Cookie::buffer_min_len) instead returns a typed [`BufferError::TooShort`]
+//! below a threshold, so callers can distinguish empty input from an unknown format.
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+
+/// Default threshold for [`Cookie::buffer_min_len()`](Cookie::buffer_min_len)
+///
+/// Most magic numbers are at least this long, e.g. `%PDF` or `\x7fELF`.
+pub const DEFAULT_MIN_LEN: usize = 4;
+
+/// Error within [`Cookie::buffer_min_len()`](Cookie::buffer_min_len)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum BufferError {
+    /// The buffer is shorter than the threshold and was not analyzed
+    #[error("buffer of {len} bytes is shorter than {min_len} bytes")]
+    TooShort {
+        /// Length of the buffer, `0` for empty input
+        len: usize,
+        /// Threshold the buffer was checked against
+        min_len: usize,
+    },
+    /// Analyzing the buffer failed
+    #[error("could not analyze buffer")]
+    Analyze(#[source] Error),
+}
+
+impl Cookie<Load> {
+    /// Returns a textual description of the contents of the `buffer` if it is at least `min_len` bytes long
+    ///
+    /// See [`DEFAULT_MIN_LEN`] for a reasonable `min_len`. Otherwise this is the same as [`buffer()`](Cookie::buffer).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use magic::short::{BufferError, DEFAULT_MIN_LEN};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// match cookie.buffer_min_len(b"", DEFAULT_MIN_LEN) {
+    ///     Err(BufferError::TooShort { len: 0, .. }) => println!("empty input"),
+    ///     Err(BufferError::TooShort { len, .. }) => println!("only {} bytes", len),
+    ///     result => println!("{:?}", result),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If `buffer` is shorter than `min_len`, a [`BufferError::TooShort`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`BufferError::Analyze`] will be returned.
+    pub fn buffer_min_len(&self, buffer: &[u8], min_len: usize) -> Result<String, BufferError> {
+        if buffer.len() < min_len {
+            return Err(BufferError::TooShort {
+                len: buffer.len(),
+                min_len,
+            });
+        }
+
+        self.buffer(buffer).map_err(BufferError::Analyze)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BufferError, DEFAULT_MIN_LEN};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn buffer_min_len() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        assert!(matches!(
+            cookie.buffer_min_len(b"", DEFAULT_MIN_LEN),
+            Err(BufferError::TooShort { len: 0, min_len: 4 })
+        ));
+        assert!(matches!(
+            cookie.buffer_min_len(b"#!", DEFAULT_MIN_LEN),
+            Err(BufferError::TooShort { len: 2, min_len: 4 })
+        ));
+        assert_eq!(
+            cookie
+                .buffer_min_len(b"#!/usr/bin/env python\nprint('Hello, world!')", 0)
+                .unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+}
@@ -0,0 +1,586 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of data from readers and writers instead of files or complete buffers
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Error within [`SniffBuilder::sniff()`](SniffBuilder::sniff)
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum SniffError {
+    /// Reading from the source failed
+    #[error("could not read data to sniff")]
+    Read(#[source] std::io::Error),
+    /// Analyzing the data read failed
+    #[error("could not analyze sniffed data")]
+    Analyze(#[source] Error),
+    /// Seeking back to the original position failed, see [`Cookie::detect_seekable()`](Cookie::detect_seekable)
+    #[error("could not seek back after sniffing")]
+    Seek(#[source] std::io::Error),
+}
+
+/// Result of [`SniffBuilder::sniff()`](SniffBuilder::sniff) and similar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sniff {
+    pub(crate) result: String,
+    pub(crate) bytes_read: usize,
+}
+
+impl Sniff {
+    /// Returns the textual result of the analysis
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+
+    /// Returns the result, consuming the sniff
+    pub fn into_result(self) -> String {
+        self.result
+    }
+
+    /// Returns how many bytes were read from the source
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+/// Analyzes successively larger prefixes of a source until the result stabilizes
+///
+/// Reading a complete file from a slow source such as object storage just to analyze it is wasteful,
+/// since `libmagic` only looks at the start of the data for most formats.
+/// Some formats however are only detected with data at deeper offsets.
+///
+/// The sniffer reads the source up to the first step size and analyzes that prefix with [`Cookie::buffer()`](Cookie::buffer).
+/// It then reads up to the next step size and analyzes the longer prefix, and so on.
+/// It stops as soon as two consecutive prefixes result in the same analysis,
+/// if the source ends or if the last step is reached.
+///
+/// The default steps are 4 KiB, 64 KiB and 1 MiB.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?;
+/// let cookie = cookie.load(["data/tests/db-images-png"])?;
+///
+/// let file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+/// let sniff = magic::sniff::SniffBuilder::new().sniff(&cookie, file)?;
+/// assert_eq!(
+///     sniff.result(),
+///     "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SniffBuilder {
+    steps: Vec<usize>,
+}
+
+impl SniffBuilder {
+    /// Creates a new sniffer with the default steps
+    pub fn new() -> Self {
+        Self {
+            steps: vec![4 * 1024, 64 * 1024, 1024 * 1024],
+        }
+    }
+
+    /// Sets the prefix sizes in bytes to analyze
+    ///
+    /// The `steps` are sorted and deduplicated, empty prefixes are ignored.
+    pub fn steps<I: IntoIterator<Item = usize>>(mut self, steps: I) -> Self {
+        self.steps = steps.into_iter().filter(|step| *step > 0).collect();
+        self.steps.sort_unstable();
+        self.steps.dedup();
+        self
+    }
+
+    /// Reads and analyzes prefixes of `source` with the loaded `cookie`
+    ///
+    /// # Errors
+    ///
+    /// If reading from `source` failed, a [`SniffError::Read`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`SniffError::Analyze`] will be returned.
+    pub fn sniff<R: Read>(
+        &self,
+        cookie: &Cookie<Load>,
+        mut source: R,
+    ) -> Result<Sniff, SniffError> {
+        let mut buffer = Vec::new();
+        let mut previous: Option<String> = None;
+
+        for step in &self.steps {
+            let missing = step - buffer.len();
+            let read = source
+                .by_ref()
+                .take(missing as u64)
+                .read_to_end(&mut buffer)
+                .map_err(SniffError::Read)?;
+
+            let result = cookie.buffer(&buffer).map_err(SniffError::Analyze)?;
+            let exhausted = read < missing;
+
+            if exhausted || previous.as_ref() == Some(&result) {
+                return Ok(Sniff {
+                    result,
+                    bytes_read: buffer.len(),
+                });
+            }
+            previous = Some(result);
+        }
+
+        match previous {
+            Some(result) => Ok(Sniff {
+                result,
+                bytes_read: buffer.len(),
+            }),
+            None => Ok(Sniff {
+                result: cookie.buffer(&buffer).map_err(SniffError::Analyze)?,
+                bytes_read: 0,
+            }),
+        }
+    }
+}
+
+impl Default for SniffBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reader adapter that analyzes the first bytes passing through it
+///
+/// All data is forwarded from the inner reader unchanged, while the first `limit` bytes are captured.
+/// Once that many bytes were read, or the inner reader ends, they are analyzed like [`Cookie::buffer()`](Cookie::buffer)
+/// and the result is available from [`detected()`](SniffingReader::detected).
+/// This classifies e.g. uploads or pipes while streaming them to disk, without reading them twice.
+///
+/// With [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) for the cookie, the result is a MIME type.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?;
+/// let cookie = cookie.load(["data/tests/db-images-png"])?;
+///
+/// let file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+/// let mut reader = magic::sniff::SniffingReader::new(&cookie, file, 4 * 1024);
+/// let mut copy = Vec::new();
+/// std::io::copy(&mut reader, &mut copy)?;
+///
+/// assert_eq!(
+///     reader.detected(),
+///     Some("PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced")
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SniffingReader<'c, R> {
+    cookie: &'c Cookie<Load>,
+    inner: R,
+    limit: usize,
+    prefix: Vec<u8>,
+    detection: Option<Result<String, Error>>,
+}
+
+impl<'c, R> SniffingReader<'c, R> {
+    /// Creates a new adapter that analyzes the first `limit` bytes read from `inner` with the loaded `cookie`
+    pub fn new(cookie: &'c Cookie<Load>, inner: R, limit: usize) -> Self {
+        Self {
+            cookie,
+            inner,
+            limit,
+            prefix: Vec::new(),
+            detection: None,
+        }
+    }
+
+    /// Returns the textual result of the analysis, if enough data was read and the analysis succeeded
+    pub fn detected(&self) -> Option<&str> {
+        match &self.detection {
+            Some(Ok(result)) => Some(result),
+            _ => None,
+        }
+    }
+
+    /// Returns the error of the analysis, if it failed
+    pub fn error(&self) -> Option<&Error> {
+        match &self.detection {
+            Some(Err(err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Analyzes the bytes read so far, unless that already happened, and returns the result
+    ///
+    /// This is for callers that stop reading before `limit` bytes or the end.
+    pub fn detect_now(&mut self) -> Option<&str> {
+        if self.detection.is_none() {
+            self.detection = Some(self.cookie.buffer(&self.prefix));
+            self.prefix = Vec::new();
+        }
+        self.detected()
+    }
+
+    /// Returns a reference to the inner reader
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the inner reader, consuming the adapter
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<'c, R: Read> Read for SniffingReader<'c, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if self.detection.is_none() {
+            let missing = self.limit - self.prefix.len();
+            self.prefix.extend_from_slice(&buf[..read.min(missing)]);
+            let exhausted = read == 0 && !buf.is_empty();
+            if exhausted || self.prefix.len() >= self.limit {
+                self.detect_now();
+            }
+        }
+        Ok(read)
+    }
+}
+
+/// Writer adapter that analyzes the first bytes passing through it
+///
+/// This is the counterpart of [`SniffingReader`] for code that only sees data on the write path, e.g. proxies or archivers.
+/// All data is forwarded to the inner writer unchanged, while the first `limit` bytes are captured.
+/// They are analyzed like [`Cookie::buffer()`](Cookie::buffer) once that many bytes were written,
+/// on [`flush()`](Write::flush) or on [`finish()`](SniffingWriter::finish), whichever comes first.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?;
+/// let cookie = cookie.load(["data/tests/db-images-png"])?;
+///
+/// let mut file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+/// let mut writer = magic::sniff::SniffingWriter::new(&cookie, Vec::new(), 4 * 1024);
+/// std::io::copy(&mut file, &mut writer)?;
+///
+/// assert_eq!(
+///     writer.finish(),
+///     Some("PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced")
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SniffingWriter<'c, W> {
+    cookie: &'c Cookie<Load>,
+    inner: W,
+    limit: usize,
+    prefix: Vec<u8>,
+    detection: Option<Result<String, Error>>,
+}
+
+impl<'c, W> SniffingWriter<'c, W> {
+    /// Creates a new adapter that analyzes the first `limit` bytes written to `inner` with the loaded `cookie`
+    pub fn new(cookie: &'c Cookie<Load>, inner: W, limit: usize) -> Self {
+        Self {
+            cookie,
+            inner,
+            limit,
+            prefix: Vec::new(),
+            detection: None,
+        }
+    }
+
+    /// Returns the textual result of the analysis, if it happened already and succeeded
+    pub fn detected(&self) -> Option<&str> {
+        match &self.detection {
+            Some(Ok(result)) => Some(result),
+            _ => None,
+        }
+    }
+
+    /// Returns the error of the analysis, if it failed
+    pub fn error(&self) -> Option<&Error> {
+        match &self.detection {
+            Some(Err(err)) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Analyzes the bytes written so far, unless that already happened, and returns the result
+    ///
+    /// Call this once all data was written, since short data never reaches `limit`.
+    pub fn finish(&mut self) -> Option<&str> {
+        if self.detection.is_none() {
+            self.detection = Some(self.cookie.buffer(&self.prefix));
+            self.prefix = Vec::new();
+        }
+        self.detected()
+    }
+
+    /// Returns a reference to the inner writer
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the inner writer, consuming the adapter
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<'c, W: Write> Write for SniffingWriter<'c, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        if self.detection.is_none() {
+            let missing = self.limit - self.prefix.len();
+            self.prefix.extend_from_slice(&buf[..written.min(missing)]);
+            if self.prefix.len() >= self.limit {
+                self.finish();
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.finish();
+        self.inner.flush()
+    }
+}
+
+impl Cookie<Load> {
+    /// Reads at most `limit` bytes from `reader` and analyzes them like [`buffer()`](Cookie::buffer)
+    ///
+    /// This is for arbitrary sources like decompressors or network streams.
+    /// The bytes read are consumed from `reader`, see [`Sniff::bytes_read()`].
+    /// See [`SniffBuilder`] to read only as much as needed instead.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let cookie = magic::Cookie::open(Default::default())?;
+    /// let cookie = cookie.load(["data/tests/db-images-png"])?;
+    ///
+    /// let file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+    /// let sniff = cookie.detect_reader(file, 4 * 1024)?;
+    /// assert_eq!(
+    ///     sniff.result(),
+    ///     "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If reading from `reader` failed, a [`SniffError::Read`] will be returned.
+    ///
+    /// If there was an `libmagic` internal error, a [`SniffError::Analyze`] will be returned.
+    pub fn detect_reader<R: Read>(&self, reader: R, limit: usize) -> Result<Sniff, SniffError> {
+        let mut buffer = Vec::new();
+        reader
+            .take(limit as u64)
+            .read_to_end(&mut buffer)
+            .map_err(SniffError::Read)?;
+        Ok(Sniff {
+            result: self.buffer(&buffer).map_err(SniffError::Analyze)?,
+            bytes_read: buffer.len(),
+        })
+    }
+
+    /// Reads at most `limit` bytes from `reader`, analyzes them and seeks back to the original position
+    ///
+    /// Unlike [`detect_reader()`](Cookie::detect_reader), no data is consumed,
+    /// so the source can be processed completely by other code afterwards without opening it again.
+    /// The position is restored even if the analysis fails.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use std::io::Read;
+    ///
+    /// let cookie = magic::Cookie::open(Default::default())?;
+    /// let cookie = cookie.load(["data/tests/db-images-png"])?;
+    ///
+    /// let mut file = std::fs::File::open("data/tests/rust-logo-128x128-blk.png")?;
+    /// let sniff = cookie.detect_seekable(&mut file, 4 * 1024)?;
+    /// assert!(sniff.result().starts_with("PNG image data"));
+    ///
+    /// // read the complete file from the start
+    /// let mut content = Vec::new();
+    /// file.read_to_end(&mut content)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If seeking in `reader` failed, a [`SniffError::Seek`] will be returned.
+    ///
+    /// See [`detect_reader()`](Cookie::detect_reader) for other errors.
+    pub fn detect_seekable<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        limit: usize,
+    ) -> Result<Sniff, SniffError> {
+        let position = reader.stream_position().map_err(SniffError::Seek)?;
+        let sniff = self.detect_reader(&mut *reader, limit);
+        reader
+            .seek(SeekFrom::Start(position))
+            .map_err(SniffError::Seek)?;
+        sniff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SniffBuilder;
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    #[test]
+    fn sniff_stable() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        // the PNG header is detected within the first step, the second step confirms it
+        let mut data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        data.resize(16 * 1024, 0);
+        let sniff = SniffBuilder::new()
+            .steps(vec![32, 64, 128])
+            .sniff(&cookie, data.as_slice())
+            .unwrap();
+        assert_eq!(
+            sniff.result(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+        assert_eq!(sniff.bytes_read(), 64);
+    }
+
+    #[test]
+    fn detect_reader() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let mut reader = data.as_slice();
+        let sniff = cookie.detect_reader(&mut reader, 16).unwrap();
+        assert_eq!(sniff.result(), "PNG image data");
+        assert_eq!(sniff.bytes_read(), 16);
+        assert_eq!(reader.len(), data.len() - 16);
+
+        let sniff = cookie.detect_reader(data.as_slice(), usize::MAX).unwrap();
+        assert_eq!(sniff.bytes_read(), data.len());
+    }
+
+    #[test]
+    fn detect_seekable() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+        let mut reader = std::io::Cursor::new(&data);
+        reader.set_position(1);
+        let sniff = cookie.detect_seekable(&mut reader, 64).unwrap();
+        // the PNG signature is incomplete from the second byte on
+        assert_ne!(sniff.result(), "PNG image data");
+        assert_eq!(reader.position(), 1);
+
+        reader.set_position(0);
+        let sniff = cookie.detect_seekable(&mut reader, 16).unwrap();
+        assert_eq!(sniff.result(), "PNG image data");
+        assert_eq!(reader.position(), 0);
+    }
+
+    #[test]
+    fn sniffing_reader() {
+        use super::SniffingReader;
+        use std::io::Read;
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+        let data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+
+        let mut reader = SniffingReader::new(&cookie, data.as_slice(), 16);
+        let mut buf = [0; 10];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.detected(), None);
+        let mut copy = buf.to_vec();
+        reader.read_to_end(&mut copy).unwrap();
+        assert_eq!(copy, data);
+        assert_eq!(reader.detected(), Some("PNG image data"));
+
+        // the source ends before the limit
+        let mut reader = SniffingReader::new(&cookie, data.as_slice(), usize::MAX);
+        std::io::copy(&mut reader, &mut std::io::sink()).unwrap();
+        assert_eq!(
+            reader.detected(),
+            Some("PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced")
+        );
+
+        let mut reader = SniffingReader::new(&cookie, data.as_slice(), 1024);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.detect_now(), Some("PNG image data"));
+        assert!(reader.error().is_none());
+    }
+
+    #[test]
+    fn sniffing_writer() {
+        use super::SniffingWriter;
+        use std::io::Write;
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = cookie.load(databases).unwrap();
+        let data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+
+        let mut writer = SniffingWriter::new(&cookie, Vec::new(), 16);
+        writer.write_all(&data[..10]).unwrap();
+        assert_eq!(writer.detected(), None);
+        writer.write_all(&data[10..]).unwrap();
+        assert_eq!(writer.detected(), Some("PNG image data"));
+        assert_eq!(writer.into_inner(), data);
+
+        // the data ends before the limit
+        let mut writer = SniffingWriter::new(&cookie, std::io::sink(), usize::MAX);
+        writer.write_all(&data).unwrap();
+        assert_eq!(writer.detected(), None);
+        writer.flush().unwrap();
+        assert_eq!(
+            writer.detected(),
+            Some("PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced")
+        );
+        assert!(writer.error().is_none());
+    }
+
+    #[test]
+    fn sniff_exhausted() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let data = b"#!/usr/bin/env python\nprint('Hello, world!')";
+        let sniff = SniffBuilder::new().sniff(&cookie, &data[..]).unwrap();
+        assert_eq!(sniff.result(), "Python script, ASCII text executable");
+        assert_eq!(sniff.bytes_read(), data.len());
+    }
+}
@@ -0,0 +1,258 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Detection of spoofed content types, e.g. of uploads
+//!
+//! The declared content type and filename of an upload are chosen by the client and can not be trusted.
+//! [`check()`] compares them with what `libmagic` detected and returns a [`Verdict`] with the [`Reason`]s for suspicion.
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::spoof::{check, Detection, Reason};
+//!
+//! // results of `Flags::MIME_TYPE | Flags::CONTINUE` and `Flags::EXTENSION`
+//! let detection = Detection::from_results("application/x-dosexec", "exe/dll/com");
+//!
+//! let verdict = check(Some("image/png"), Some("cat.png"), &detection);
+//! assert!(verdict.is_suspicious());
+//! assert!(verdict.has(Reason::DisguisedExecutable));
+//! ```
+
+use crate::normalize::{normalize, split_matches, Normalization};
+
+/// Declared type that does not claim any particular content
+const GENERIC_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Unknown result of [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION)
+pub(crate) const UNKNOWN_EXTENSION: &str = "???";
+
+/// Detected media types of native executables
+pub(crate) const EXECUTABLE_TYPES: &[&str] = &[
+    "application/vnd.microsoft.portable-executable",
+    "application/x-dosexec",
+    "application/x-executable",
+    "application/x-mach-binary",
+    "application/x-msdownload",
+    "application/x-pie-executable",
+    "application/x-sharedlib",
+];
+
+/// Filename extensions that claim to be images
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "avif", "bmp", "gif", "heic", "ico", "jpeg", "jpg", "png", "svg", "tif", "tiff", "webp",
+];
+
+/// What `libmagic` detected for some content
+///
+/// This is built from the results of separate analyses with
+/// [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE) and [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    mime_types: Vec<String>,
+    extensions: Vec<String>,
+}
+
+impl Detection {
+    /// Creates a detection from the textual results of `libmagic`
+    ///
+    /// `mime_type` is the result of [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE),
+    /// optionally with [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE) to report all matches.
+    /// `extension` is the result of [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION), e.g. "jpeg/jpg/jpe/jfif".
+    pub fn from_results(mime_type: &str, extension: &str) -> Self {
+        let mut mime_types: Vec<String> = Vec::new();
+        for mime_type in split_matches(mime_type) {
+            let mime_type = normalize(mime_type, Normalization::MimeType);
+            if !mime_types.contains(&mime_type) {
+                mime_types.push(mime_type);
+            }
+        }
+
+        Self {
+            mime_types,
+            extensions: parse_extensions(extension),
+        }
+    }
+
+    /// Returns the media type of the strongest match
+    pub fn mime_type(&self) -> &str {
+        &self.mime_types[0]
+    }
+
+    /// Returns the distinct media types of all matches, strongest first
+    pub fn mime_types(&self) -> &[String] {
+        &self.mime_types
+    }
+
+    /// Returns the file extensions `libmagic` knows for the content
+    ///
+    /// This is empty if the database does not know any extensions.
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+}
+
+/// Reason for suspicion within a [`Verdict`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Reason {
+    /// The declared content type matches none of the detected media types
+    ///
+    /// A declared `application/octet-stream` does not claim any particular content and never mismatches.
+    ContentTypeMismatch,
+    /// The extension of the declared filename is not one of the detected extensions
+    ///
+    /// Filenames without extension and content without known extensions never mismatch.
+    ExtensionMismatch,
+    /// A native executable is declared as an image by content type or filename
+    DisguisedExecutable,
+    /// The content matches several distinct media types, e.g. a GIF that also is a ZIP archive
+    ///
+    /// This requires results of [`Flags::CONTINUE`](crate::cookie::Flags::CONTINUE).
+    Polyglot,
+}
+
+/// Outcome of [`check()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    reasons: Vec<Reason>,
+}
+
+impl Verdict {
+    /// Returns all reasons for suspicion, empty if there are none
+    pub fn reasons(&self) -> &[Reason] {
+        &self.reasons
+    }
+
+    /// Returns whether `reason` applies
+    pub fn has(&self, reason: Reason) -> bool {
+        self.reasons.contains(&reason)
+    }
+
+    /// Returns whether any reason for suspicion applies
+    pub fn is_suspicious(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+}
+
+/// Compares the `declared_type` and `filename` with the `detection` of the actual content
+///
+/// Both `declared_type` and `filename` are optional, missing ones are not suspicious by themselves.
+pub fn check(
+    declared_type: Option<&str>,
+    filename: Option<&str>,
+    detection: &Detection,
+) -> Verdict {
+    let mut reasons = Vec::new();
+
+    let declared_type = declared_type.map(|declared| normalize(declared, Normalization::MimeType));
+    if let Some(declared) = &declared_type {
+        if content_type_mismatch(declared, &detection.mime_types) {
+            reasons.push(Reason::ContentTypeMismatch);
+        }
+    }
+
+    let extension = filename.and_then(extension);
+    if let Some(extension) = extension {
+        if extension_mismatch(extension, &detection.extensions) {
+            reasons.push(Reason::ExtensionMismatch);
+        }
+    }
+
+    let declared_image = declared_type
+        .as_deref()
+        .map_or(false, |declared| declared.starts_with("image/"))
+        || extension.map_or(false, |extension| {
+            IMAGE_EXTENSIONS
+                .iter()
+                .any(|image| image.eq_ignore_ascii_case(extension))
+        });
+    let executable = detection
+        .mime_types
+        .iter()
+        .any(|detected| EXECUTABLE_TYPES.contains(&detected.as_str()));
+    if declared_image && executable {
+        reasons.push(Reason::DisguisedExecutable);
+    }
+
+    if detection.mime_types.len() > 1 {
+        reasons.push(Reason::Polyglot);
+    }
+
+    Verdict { reasons }
+}
+
+/// Returns the extension of `filename` after its last `.`, if any
+pub(crate) fn extension(filename: &str) -> Option<&str> {
+    filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.is_empty())
+}
+
+/// Splits a result of [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION), dropping unknown extensions
+pub(crate) fn parse_extensions(extension: &str) -> Vec<String> {
+    extension
+        .split('/')
+        .map(str::trim)
+        .filter(|extension| !extension.is_empty() && *extension != UNKNOWN_EXTENSION)
+        .map(str::to_string)
+        .collect()
+}
+
+pub(crate) fn content_type_mismatch(declared: &str, detected: &[String]) -> bool {
+    !declared.eq_ignore_ascii_case(GENERIC_CONTENT_TYPE)
+        && !detected
+            .iter()
+            .any(|detected| declared.eq_ignore_ascii_case(detected))
+}
+
+pub(crate) fn extension_mismatch(extension: &str, known: &[String]) -> bool {
+    !known.is_empty()
+        && !known
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, Detection, Reason};
+
+    #[test]
+    fn from_results() {
+        let detection = Detection::from_results("image/gif\n- application/zip\n- image/gif", "gif");
+        assert_eq!(detection.mime_type(), "image/gif");
+        assert_eq!(detection.mime_types(), ["image/gif", "application/zip"]);
+        assert_eq!(detection.extensions(), ["gif"]);
+
+        let detection = Detection::from_results("text/x-python", "???");
+        assert!(detection.extensions().is_empty());
+
+        let detection = Detection::from_results("image/jpeg", "jpeg/jpg/jpe/jfif\n");
+        assert_eq!(detection.extensions(), ["jpeg", "jpg", "jpe", "jfif"]);
+    }
+
+    #[test]
+    fn check_reasons() {
+        let png = Detection::from_results("image/png", "png");
+        assert!(!check(Some("image/png"), Some("logo.PNG"), &png).is_suspicious());
+        assert!(!check(Some("application/octet-stream"), None, &png).is_suspicious());
+        assert!(!check(None, Some("README"), &png).is_suspicious());
+        assert_eq!(
+            check(Some("application/pdf"), Some("invoice.pdf"), &png).reasons(),
+            [Reason::ContentTypeMismatch, Reason::ExtensionMismatch]
+        );
+
+        let exe = Detection::from_results("application/x-dosexec", "exe/dll/com");
+        assert_eq!(
+            check(None, Some("cat.jpg"), &exe).reasons(),
+            [Reason::ExtensionMismatch, Reason::DisguisedExecutable]
+        );
+
+        let polyglot = Detection::from_results("image/gif\n- application/zip", "gif");
+        assert_eq!(
+            check(Some("image/gif"), Some("cat.gif"), &polyglot).reasons(),
+            [Reason::Polyglot]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Aggregation of analysis results
+//!
+//! [`ScanSummary`] counts files and sums their sizes per analysis result, e.g. per MIME type with
+//! [`Flags::MIME_TYPE`](crate::cookie::Flags::MIME_TYPE).
+//! Summaries of parallel scans can be [`merge()`](ScanSummary::merge)d.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(magic::cookie::Flags::MIME_TYPE)?;
+//! let cookie = cookie.load(["data/tests/db-images-png"])?;
+//!
+//! let mut summary = magic::summary::ScanSummary::new();
+//! for path in ["data/tests/rust-logo-128x128-blk.png"] {
+//!     let size = std::fs::metadata(path)?.len();
+//!     summary.add(&cookie.file(path), size);
+//! }
+//!
+//! let png = summary.get("image/png").unwrap();
+//! assert_eq!(png.count(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::Error;
+use std::collections::BTreeMap;
+
+/// Number of files and their total size
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tally {
+    count: u64,
+    bytes: u64,
+}
+
+impl Tally {
+    /// Returns the number of files
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the total size of the files in bytes
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    fn add(&mut self, other: Tally) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Accumulator of analysis results
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScanSummary {
+    kinds: BTreeMap<String, Tally>,
+    errors: Tally,
+}
+
+impl ScanSummary {
+    /// Creates a new empty summary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the analysis `result` of a file with `size` bytes
+    ///
+    /// Successful results are tallied per textual result, errors are tallied together.
+    pub fn add(&mut self, result: &Result<String, Error>, size: u64) {
+        let tally = Tally {
+            count: 1,
+            bytes: size,
+        };
+        match result {
+            Ok(kind) => match self.kinds.get_mut(kind.as_str()) {
+                Some(existing) => existing.add(tally),
+                None => {
+                    self.kinds.insert(kind.clone(), tally);
+                }
+            },
+            Err(_) => self.errors.add(tally),
+        }
+    }
+
+    /// Adds all results of the `other` summary, e.g. of a parallel scan
+    pub fn merge(&mut self, other: ScanSummary) {
+        for (kind, tally) in other.kinds {
+            self.kinds.entry(kind).or_default().add(tally);
+        }
+        self.errors.add(other.errors);
+    }
+
+    /// Returns the tally of the textual result `kind`, if any file had that result
+    pub fn get(&self, kind: &str) -> Option<&Tally> {
+        self.kinds.get(kind)
+    }
+
+    /// Returns the tallies of all textual results, ordered by result
+    pub fn kinds(&self) -> impl Iterator<Item = (&str, &Tally)> {
+        self.kinds
+            .iter()
+            .map(|(kind, tally)| (kind.as_str(), tally))
+    }
+
+    /// Returns the tally of files whose analysis failed
+    pub fn errors(&self) -> &Tally {
+        &self.errors
+    }
+
+    /// Returns the tally of all files, including those whose analysis failed
+    pub fn total(&self) -> Tally {
+        let mut total = self.errors;
+        for tally in self.kinds.values() {
+            total.add(*tally);
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScanSummary;
+
+    #[test]
+    fn add_and_merge() {
+        let mut a = ScanSummary::new();
+        a.add(&Ok("image/png".to_string()), 100);
+        a.add(&Ok("text/x-python".to_string()), 10);
+
+        let mut b = ScanSummary::new();
+        b.add(&Ok("image/png".to_string()), 50);
+
+        a.merge(b);
+
+        let png = a.get("image/png").unwrap();
+        assert_eq!(png.count(), 2);
+        assert_eq!(png.bytes(), 150);
+        assert_eq!(
+            a.kinds().map(|(kind, _)| kind).collect::<Vec<_>>(),
+            ["image/png", "text/x-python"]
+        );
+        assert_eq!(a.errors().count(), 0);
+        assert_eq!(a.total().count(), 3);
+        assert_eq!(a.total().bytes(), 160);
+        assert_eq!(a.get("inode/x-empty"), None);
+    }
+}
@@ -0,0 +1,383 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Sharing a loaded cookie across threads
+//!
+//! `libmagic` cookies must not be used concurrently, but they are not bound to the thread that opened them.
+//! So a [`Cookie`] is [`Send`] and [`Sync`], and every single operation on it is serialized by an internal lock.
+//!
+//! Sequences of operations are not serialized though, e.g. another thread might change the flags
+//! between [`set_flags()`](Cookie::set_flags) and [`file()`](Cookie::file).
+//! [`SharedCookie`] is a cheaply cloneable handle to a cookie that also allows
+//! to [`lock()`](SharedCookie::lock) it for such a sequence.
+//! For queries with temporary flags, [`Cookie::with_flags()`](Cookie::with_flags) suffices.
+//!
+//! For parallel queries, use one cookie per thread instead.
+//! [`CookiePool`] holds several cookies with the same flags and databases,
+//! so e.g. the workers of a server can analyze concurrently without loading databases per request.
+
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::cookie::{DatabasePaths, Error, Flags, Load, LoadError, Open, OpenError};
+use crate::Cookie;
+
+/// Loaded cookie that is shared across threads
+///
+/// Clones share the same cookie. Every function locks the cookie for its duration.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use magic::cookie::Flags;
+/// use magic::sync::SharedCookie;
+///
+/// let cookie = magic::Cookie::open(Flags::ERROR)?.load(magic::cookie::DatabasePaths::default())?;
+/// let cookie = SharedCookie::new(cookie);
+///
+/// let thread = std::thread::spawn({
+///     let cookie = cookie.clone();
+///     move || cookie.file("data/tests/rust-logo-128x128-blk.png")
+/// });
+///
+/// {
+///     // no other thread can change the flags before the query
+///     let cookie = cookie.lock();
+///     cookie.set_flags(Flags::ERROR | Flags::MIME_TYPE)?;
+///     let mime = cookie.file("data/tests/rust-logo-128x128-blk.png")?;
+///     cookie.set_flags(Flags::ERROR)?;
+/// }
+///
+/// let file_description = thread.join().unwrap()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedCookie {
+    cookie: Arc<Mutex<Cookie<Load>>>,
+}
+
+impl SharedCookie {
+    /// Creates a new shared cookie from the loaded `cookie`
+    pub fn new(cookie: Cookie<Load>) -> Self {
+        Self {
+            cookie: Arc::new(Mutex::new(cookie)),
+        }
+    }
+
+    /// Locks the cookie exclusively, until the returned guard is dropped
+    ///
+    /// Functions of other clones block in the meantime.
+    pub fn lock(&self) -> MutexGuard<'_, Cookie<Load>> {
+        // a panic in between operations does not leave the cookie itself inconsistent
+        self.cookie.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns a textual description of the contents of the file `filename`
+    ///
+    /// See [`Cookie::file()`](Cookie::file).
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+    #[doc(alias = "magic_file")]
+    pub fn file<P: AsRef<Path>>(&self, filename: P) -> Result<String, Error> {
+        self.lock().file(filename)
+    }
+
+    /// Returns a textual description of the contents of the `buffer`
+    ///
+    /// See [`Cookie::buffer()`](Cookie::buffer).
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+    #[doc(alias = "magic_buffer")]
+    pub fn buffer(&self, buffer: &[u8]) -> Result<String, Error> {
+        self.lock().buffer(buffer)
+    }
+
+    /// Returns the cookie if this is the last clone, otherwise `self` again
+    pub fn try_into_inner(self) -> Result<Cookie<Load>, Self> {
+        match Arc::try_unwrap(self.cookie) {
+            Ok(cookie) => Ok(cookie.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(cookie) => Err(Self { cookie }),
+        }
+    }
+}
+
+impl From<Cookie<Load>> for SharedCookie {
+    fn from(cookie: Cookie<Load>) -> Self {
+        Self::new(cookie)
+    }
+}
+
+/// Error within [`CookiePool::new()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum PoolError {
+    /// Opening a cookie failed
+    #[error("could not open cookie")]
+    Open(#[source] OpenError),
+    /// Loading the databases failed
+    #[error("could not load databases")]
+    Load(#[source] LoadError<Open>),
+    /// No cookies were given for the pool
+    #[error("could not create pool without cookies")]
+    Empty,
+}
+
+/// Pool of loaded cookies for concurrent analyses
+///
+/// [`get()`](CookiePool::get) borrows a cookie exclusively, until the returned [`PooledCookie`] is dropped.
+/// A pool is [`Send`] and [`Sync`], e.g. it can be shared in an [`Arc`] across threads.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use magic::cookie::{DatabasePaths, Flags};
+/// use magic::sync::CookiePool;
+/// use std::sync::Arc;
+///
+/// let pool = Arc::new(CookiePool::new(4, Flags::ERROR, &DatabasePaths::default())?);
+///
+/// let threads: Vec<_> = (0..8)
+///     .map(|_| {
+///         let pool = Arc::clone(&pool);
+///         std::thread::spawn(move || pool.get().file("data/tests/rust-logo-128x128-blk.png"))
+///     })
+///     .collect();
+/// for thread in threads {
+///     let file_description = thread.join().unwrap()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CookiePool {
+    cookies: Mutex<Vec<Cookie<Load>>>,
+    returned: Condvar,
+    size: usize,
+    // restored when a borrowed cookie is returned
+    flags: Flags,
+}
+
+impl CookiePool {
+    /// Creates a new pool of `size` cookies, each opened with `flags` and loaded with `databases`
+    ///
+    /// A `size` of 0 is treated like 1.
+    ///
+    /// # Errors
+    ///
+    /// If a cookie can not be opened, a [`PoolError::Open`] will be returned.
+    ///
+    /// If the databases can not be loaded, a [`PoolError::Load`] will be returned.
+    pub fn new(size: usize, flags: Flags, databases: &DatabasePaths) -> Result<Self, PoolError> {
+        let cookies = (0..size.max(1))
+            .map(|_| {
+                Cookie::open(flags)
+                    .map_err(PoolError::Open)?
+                    .load(databases)
+                    .map_err(PoolError::Load)
+            })
+            .collect::<Result<_, _>>()?;
+        let mut pool = Self::from_cookies(cookies)?;
+        pool.flags = flags;
+        Ok(pool)
+    }
+
+    /// Creates a new pool of the loaded `cookies`
+    ///
+    /// The cookies should have the same flags and databases, since [`get()`](CookiePool::get) returns any of them.
+    /// The flags of the first cookie are restored whenever a borrowed cookie is returned.
+    ///
+    /// # Errors
+    ///
+    /// If `cookies` is empty, a [`PoolError::Empty`] will be returned.
+    pub fn from_cookies(cookies: Vec<Cookie<Load>>) -> Result<Self, PoolError> {
+        let flags = match cookies.first() {
+            Some(cookie) => cookie.flags(),
+            None => return Err(PoolError::Empty),
+        };
+        Ok(Self {
+            flags,
+            size: cookies.len(),
+            cookies: Mutex::new(cookies),
+            returned: Condvar::new(),
+        })
+    }
+
+    /// Returns the number of cookies in this pool, including borrowed ones
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn cookies(&self) -> MutexGuard<'_, Vec<Cookie<Load>>> {
+        // a panic while a cookie is borrowed does not leave the list inconsistent
+        self.cookies.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Borrows a cookie, blocking until one is available
+    pub fn get(&self) -> PooledCookie<'_> {
+        let mut cookies = self.cookies();
+        loop {
+            if let Some(cookie) = cookies.pop() {
+                return PooledCookie {
+                    pool: self,
+                    cookie: Some(cookie),
+                };
+            }
+            cookies = self
+                .returned
+                .wait(cookies)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Borrows a cookie if one is available, without blocking
+    pub fn try_get(&self) -> Option<PooledCookie<'_>> {
+        let cookie = self.cookies().pop()?;
+        Some(PooledCookie {
+            pool: self,
+            cookie: Some(cookie),
+        })
+    }
+}
+
+/// Cookie borrowed from a [`CookiePool`]
+///
+/// Dereferences to the [`Cookie`], and returns it to the pool when dropped.
+/// Flags that were changed with [`Cookie::set_flags()`] are reset to those of the pool then.
+#[derive(Debug)]
+pub struct PooledCookie<'p> {
+    pool: &'p CookiePool,
+    // only `None` while dropping
+    cookie: Option<Cookie<Load>>,
+}
+
+impl std::ops::Deref for PooledCookie<'_> {
+    type Target = Cookie<Load>;
+
+    fn deref(&self) -> &Self::Target {
+        self.cookie.as_ref().expect("cookie is only taken on drop")
+    }
+}
+
+impl Drop for PooledCookie<'_> {
+    fn drop(&mut self) {
+        if let Some(cookie) = self.cookie.take() {
+            // the pool's flags were applied to the cookie before, so resetting them does not fail
+            let _ = cookie.set_flags(self.pool.flags);
+            self.pool.cookies().push(cookie);
+            self.pool.returned.notify_one();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CookiePool, PoolError, SharedCookie};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+    use std::convert::TryInto;
+
+    #[test]
+    fn shared_cookie() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = SharedCookie::new(cookie.load(["data/tests/db-images-png"]).unwrap());
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let cookie = cookie.clone();
+                std::thread::spawn(move || cookie.file(path).unwrap())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+
+        {
+            let cookie = cookie.lock();
+            cookie.set_flags(Flags::ERROR | Flags::MIME_TYPE).unwrap();
+            assert_eq!(cookie.file(path).unwrap(), "image/png");
+            cookie.set_flags(Flags::ERROR).unwrap();
+        }
+
+        let clone = cookie.clone();
+        let cookie = cookie.try_into_inner().unwrap_err();
+        drop(clone);
+        let cookie = cookie.try_into_inner().unwrap();
+        assert_eq!(cookie.file(path).unwrap(), expected);
+    }
+
+    #[test]
+    fn cookie_pool() {
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let pool = std::sync::Arc::new(CookiePool::new(2, Flags::ERROR, &databases).unwrap());
+        assert_eq!(pool.size(), 2);
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+        {
+            let first = pool.get();
+            let second = pool.try_get().unwrap();
+            assert!(pool.try_get().is_none());
+            assert_eq!(first.file(path).unwrap(), expected);
+            assert_eq!(second.file(path).unwrap(), expected);
+        }
+        assert!(pool.try_get().is_some());
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let pool = std::sync::Arc::clone(&pool);
+                std::thread::spawn(move || pool.get().file(path).unwrap())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn cookie_pool_flags() {
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let pool = CookiePool::new(1, Flags::ERROR, &databases).unwrap();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        {
+            let cookie = pool.get();
+            cookie.set_flags(Flags::ERROR | Flags::MIME_TYPE).unwrap();
+            assert_eq!(cookie.file(path).unwrap(), "image/png");
+        }
+
+        let cookie = pool.get();
+        assert_eq!(cookie.flags(), Flags::ERROR);
+        assert_eq!(
+            cookie.file(path).unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+    }
+
+    #[test]
+    fn cookie_pool_from_cookies() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+        let pool = CookiePool::from_cookies(vec![cookie]).unwrap();
+        assert_eq!(pool.size(), 1);
+
+        assert!(matches!(
+            CookiePool::from_cookies(Vec::new()),
+            Err(PoolError::Empty)
+        ));
+    }
+
+    static_assertions::assert_impl_all!(SharedCookie: Send, Sync, Clone);
+    static_assertions::assert_impl_all!(CookiePool: Send, Sync);
+    // the raw cookie may move across threads, but must not be shared without a lock
+    static_assertions::assert_impl_all!(crate::ffi::Cookie: Send);
+    static_assertions::assert_not_impl_any!(crate::ffi::Cookie: Sync);
+}
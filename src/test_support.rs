@@ -0,0 +1,141 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Fixtures for deterministic tests of crates depending on `magic`
+//!
+//! The system's default database differs between `libmagic` installations,
+//! so exact assertions on analysis results are brittle.
+//! This module provides the tiny databases and sample files that this crate uses for its own tests.
+//!
+//! Requires the `test-support` feature:
+//! ```toml
+//! [dev-dependencies]
+//! magic = { version = "*", features = ["test-support"] }
+//! ```
+//!
+//! # Examples
+//!
+//! ```
+//! use magic::test_support;
+//!
+//! let cookie = test_support::cookie(magic::cookie::Flags::ERROR);
+//! assert_eq!(
+//!     cookie.buffer(test_support::PNG_IMAGE).unwrap(),
+//!     test_support::PNG_IMAGE_DESCRIPTION
+//! );
+//! ```
+//!
+//! # Caveats
+//!
+//! The database file paths are absolute paths into the `magic` crate sources, as built by `cargo`.
+//! On Windows those contain a ":" (colon) drive separator, which `libmagic` treats as a database path separator.
+
+use crate::cookie::{DatabasePaths, Flags, Load};
+use crate::Cookie;
+use std::path::Path;
+
+const PNG_DATABASE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/tests/db-images-png");
+const PYTHON_DATABASE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/data/tests/db-python");
+const PNG_IMAGE_FILE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/data/tests/rust-logo-128x128-blk.png"
+);
+
+/// Contents of a 128x128 PNG image of the Rust logo
+///
+/// Detected by [`png_database()`].
+pub const PNG_IMAGE: &[u8] = include_bytes!("../data/tests/rust-logo-128x128-blk.png");
+
+/// Textual description of [`PNG_IMAGE`]
+pub const PNG_IMAGE_DESCRIPTION: &str =
+    "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+/// MIME type of [`PNG_IMAGE`]
+pub const PNG_IMAGE_MIME_TYPE: &str = "image/png";
+
+/// Contents of a tiny Python script
+///
+/// Detected by [`python_database()`].
+pub const PYTHON_SCRIPT: &[u8] = b"#!/usr/bin/env python\nprint('Hello, world!')";
+
+/// Textual description of [`PYTHON_SCRIPT`]
+pub const PYTHON_SCRIPT_DESCRIPTION: &str = "Python script, ASCII text executable";
+
+/// MIME type of [`PYTHON_SCRIPT`]
+pub const PYTHON_SCRIPT_MIME_TYPE: &str = "text/x-python";
+
+/// Returns the path of a file with the contents of [`PNG_IMAGE`]
+pub fn png_image_path() -> &'static Path {
+    Path::new(PNG_IMAGE_FILE)
+}
+
+/// Returns the text database that detects PNG images
+///
+/// # Panics
+///
+/// Panics if the path of the `magic` crate sources is not a valid database path.
+pub fn png_database() -> DatabasePaths {
+    DatabasePaths::new([PNG_DATABASE]).expect("valid PNG database path")
+}
+
+/// Returns the text database that detects Python scripts
+///
+/// # Panics
+///
+/// Panics if the path of the `magic` crate sources is not a valid database path.
+pub fn python_database() -> DatabasePaths {
+    DatabasePaths::new([PYTHON_DATABASE]).expect("valid Python database path")
+}
+
+/// Returns both the [`png_database()`] and the [`python_database()`]
+///
+/// # Panics
+///
+/// Panics if the path of the `magic` crate sources is not a valid database path.
+pub fn databases() -> DatabasePaths {
+    DatabasePaths::new([PNG_DATABASE, PYTHON_DATABASE]).expect("valid database paths")
+}
+
+/// Returns a new cookie with the given `flags` and all [`databases()`] loaded
+///
+/// # Panics
+///
+/// Panics if the cookie could not be opened or the databases could not be loaded.
+pub fn cookie(flags: Flags) -> Cookie<Load> {
+    Cookie::open(flags)
+        .expect("opened cookie")
+        .load(&databases())
+        .expect("loaded test databases")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookie::Flags;
+
+    #[test]
+    fn cookie() {
+        let cookie = super::cookie(Flags::ERROR);
+        assert_eq!(
+            cookie.buffer(super::PNG_IMAGE).unwrap(),
+            super::PNG_IMAGE_DESCRIPTION
+        );
+        assert_eq!(
+            cookie.file(super::png_image_path()).unwrap(),
+            super::PNG_IMAGE_DESCRIPTION
+        );
+        assert_eq!(
+            cookie.buffer(super::PYTHON_SCRIPT).unwrap(),
+            super::PYTHON_SCRIPT_DESCRIPTION
+        );
+
+        cookie.set_flags(Flags::MIME_TYPE).unwrap();
+        assert_eq!(
+            cookie.buffer(super::PNG_IMAGE).unwrap(),
+            super::PNG_IMAGE_MIME_TYPE
+        );
+        assert_eq!(
+            cookie.buffer(super::PYTHON_SCRIPT).unwrap(),
+            super::PYTHON_SCRIPT_MIME_TYPE
+        );
+    }
+}
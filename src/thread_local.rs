@@ -0,0 +1,256 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! One loaded cookie per thread
+//!
+//! [`with_cookie()`] lazily opens and loads a cookie the first time it is called on a thread,
+//! and reuses that cookie for all later calls on the same thread.
+//! This gives e.g. work-stealing thread pools cheap access to a cookie without a [`CookiePool`](crate::sync::CookiePool).
+//!
+//! [`Config::with_cookie()`] does the same for cookies with other flags or databases,
+//! the cookies of each [`Config`] and its clones are cached separately.
+//!
+//! The cookies are dropped when their thread exits.
+//! Once the last clone of a [`Config`] is dropped, its cookies are dropped as well,
+//! each on the next call of [`Config::with_cookie()`] on its thread.
+
+use std::cell::RefCell;
+use std::sync::{Arc, Weak};
+
+use crate::cookie::{DatabasePaths, Flags, Load, LoadError, Open, OpenError};
+use crate::Cookie;
+
+/// Error within [`with_cookie()`] or [`Config::with_cookie()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum ThreadLocalError {
+    /// Opening the cookie failed
+    #[error("could not open cookie")]
+    Open(#[source] OpenError),
+    /// Loading the databases failed
+    #[error("could not load databases")]
+    Load(#[source] LoadError<Open>),
+}
+
+/// Config of a cached cookie, `None` for the default config
+type CachedConfig = Option<Weak<()>>;
+
+thread_local! {
+    static COOKIES: RefCell<Vec<(CachedConfig, Cookie<Load>)>> = RefCell::new(Vec::new());
+}
+
+/// Flags and databases of thread-local cookies
+///
+/// The default config uses [`Flags::default()`] and [`DatabasePaths::default()`], like [`with_cookie()`].
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use magic::cookie::{DatabasePaths, Flags};
+/// use magic::thread_local::Config;
+///
+/// let config = Config::new(Flags::MIME_TYPE, DatabasePaths::default());
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|_| {
+///         let config = config.clone();
+///         std::thread::spawn(move || {
+///             config.with_cookie(|cookie| cookie.file("data/tests/rust-logo-128x128-blk.png"))
+///         })
+///     })
+///     .collect();
+/// for thread in threads {
+///     let mime = thread.join().unwrap()??;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Config {
+    // identifies the cookies of a config and its clones, `None` for the default config
+    token: Option<Arc<()>>,
+    flags: Flags,
+    databases: DatabasePaths,
+}
+
+impl Config {
+    /// Creates a new config for cookies opened with `flags` and loaded with `databases`
+    pub fn new(flags: Flags, databases: DatabasePaths) -> Self {
+        Self {
+            token: Some(Arc::new(())),
+            flags,
+            databases,
+        }
+    }
+
+    /// Returns the flags that cookies are opened with
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    /// Returns the databases that cookies are loaded with
+    pub fn databases(&self) -> &DatabasePaths {
+        &self.databases
+    }
+
+    /// Returns whether the cached cookie of `config` belongs to this config
+    fn owns(&self, config: &CachedConfig) -> bool {
+        match (&self.token, config) {
+            (None, None) => true,
+            (Some(token), Some(config)) => std::ptr::eq(Arc::as_ptr(token), config.as_ptr()),
+            _ => false,
+        }
+    }
+
+    fn open(&self) -> Result<Cookie<Load>, ThreadLocalError> {
+        Cookie::open(self.flags)
+            .map_err(ThreadLocalError::Open)?
+            .load(&self.databases)
+            .map_err(ThreadLocalError::Load)
+    }
+
+    /// Calls `f` with the cookie of this config for the current thread, which is opened and loaded on first use
+    ///
+    /// Calls of `with_cookie()` within `f` on the same config open another cookie, which is cached afterwards as well.
+    ///
+    /// # Errors
+    ///
+    /// If the cookie can not be opened, a [`ThreadLocalError::Open`] will be returned.
+    ///
+    /// If the databases can not be loaded, a [`ThreadLocalError::Load`] will be returned.
+    pub fn with_cookie<F, T>(&self, f: F) -> Result<T, ThreadLocalError>
+    where
+        F: FnOnce(&Cookie<Load>) -> T,
+    {
+        // the cookie is taken out during `f`, so the cache is not borrowed if `f` uses it as well
+        let cached = COOKIES.with(|cookies| {
+            let mut cookies = cookies.borrow_mut();
+            // the cookies of configs without any clones left can not be used anymore
+            cookies.retain(|(config, _)| config.as_ref().map_or(true, |c| c.strong_count() > 0));
+            let index = cookies.iter().position(|(config, _)| self.owns(config))?;
+            Some(cookies.swap_remove(index).1)
+        });
+        let cookie = match cached {
+            Some(cookie) => cookie,
+            None => self.open()?,
+        };
+
+        let result = f(&cookie);
+
+        let config = self.token.as_ref().map(Arc::downgrade);
+        COOKIES.with(|cookies| cookies.borrow_mut().push((config, cookie)));
+        Ok(result)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            token: None,
+            flags: Flags::default(),
+            databases: DatabasePaths::default(),
+        }
+    }
+}
+
+impl Clone for Config {
+    /// Returns a config that shares the cached cookies with this one
+    fn clone(&self) -> Self {
+        Self {
+            token: self.token.clone(),
+            flags: self.flags,
+            databases: (&self.databases).into(),
+        }
+    }
+}
+
+/// Calls `f` with the cookie of the current thread, which is opened and loaded with the default config on first use
+///
+/// See [`Config::with_cookie()`].
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let file_description = magic::thread_local::with_cookie(|cookie| {
+///     cookie.file("data/tests/rust-logo-128x128-blk.png")
+/// })??;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// See [`Config::with_cookie()`].
+pub fn with_cookie<F, T>(f: F) -> Result<T, ThreadLocalError>
+where
+    F: FnOnce(&Cookie<Load>) -> T,
+{
+    Config::default().with_cookie(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, ThreadLocalError, COOKIES};
+    use crate::cookie::Flags;
+    use std::convert::TryInto;
+
+    #[test]
+    fn with_cookie() {
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let config = Config::new(Flags::ERROR, databases);
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+        std::thread::spawn(move || {
+            let clone = config.clone();
+            assert_eq!(
+                config.with_cookie(|c| c.file(path)).unwrap().unwrap(),
+                expected
+            );
+            assert_eq!(
+                clone.with_cookie(|c| c.file(path)).unwrap().unwrap(),
+                expected
+            );
+            COOKIES.with(|cookies| assert_eq!(cookies.borrow().len(), 1));
+
+            // nested use opens another cookie
+            let nested = config
+                .with_cookie(|_| config.with_cookie(|c| c.file(path)))
+                .unwrap();
+            assert_eq!(nested.unwrap().unwrap(), expected);
+            COOKIES.with(|cookies| assert_eq!(cookies.borrow().len(), 2));
+
+            let config = Config::new(
+                Flags::ERROR,
+                "data/tests/doesntexist.mgc".try_into().unwrap(),
+            );
+            assert!(matches!(
+                config.with_cookie(|_| ()),
+                Err(ThreadLocalError::Load(_))
+            ));
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn evict() {
+        let databases = ["data/tests/db-images-png"].try_into().unwrap();
+        let config = Config::new(Flags::ERROR, databases);
+        let other = Config::new(Flags::ERROR, config.databases().into());
+
+        std::thread::spawn(move || {
+            config.with_cookie(|_| ()).unwrap();
+            COOKIES.with(|cookies| assert_eq!(cookies.borrow().len(), 1));
+
+            // the cookie of the dropped config is evicted on the next use of the cache
+            drop(config);
+            other.with_cookie(|_| ()).unwrap();
+            COOKIES.with(|cookies| assert_eq!(cookies.borrow().len(), 1));
+        })
+        .join()
+        .unwrap();
+    }
+}
@@ -0,0 +1,251 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis in async applications using the [`tokio` crate](https://crates.io/crates/tokio)
+//!
+//! `libmagic` only has blocking functions, which must not run on the threads of an async executor.
+//! [`AsyncCookie`] runs the analysis of files and buffers on the blocking thread pool of `tokio` instead.
+//! [`detect_async_reader()`] reads a prefix of an [`AsyncRead`] source asynchronously
+//! and analyzes it on the blocking thread pool as well.
+//!
+//! For other executors, see mod `unblock` with the `blocking` feature.
+//!
+//! Requires the `tokio` feature.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use ::tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::cookie::{Error, Load};
+use crate::sniff::Sniff;
+use crate::Cookie;
+
+/// Error within [`detect_async_reader()`] or the functions of [`AsyncCookie`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum AsyncError {
+    /// Reading from the source failed
+    #[error("could not read data to analyze")]
+    Read(#[source] std::io::Error),
+    /// Analyzing the data failed
+    #[error("could not analyze data")]
+    Analyze(#[source] Error),
+    /// The blocking analysis was cancelled, e.g. because the runtime shuts down
+    #[error("blocking analysis was cancelled")]
+    Cancelled(#[source] ::tokio::task::JoinError),
+}
+
+/// Runs the blocking `analysis` on the blocking thread pool
+///
+/// Panics of the `analysis` are propagated to the caller.
+async fn spawn_analysis<F, T>(analysis: F) -> Result<T, AsyncError>
+where
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+    T: Send + 'static,
+{
+    match ::tokio::task::spawn_blocking(analysis).await {
+        Ok(result) => result.map_err(AsyncError::Analyze),
+        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Err(err) => Err(AsyncError::Cancelled(err)),
+    }
+}
+
+/// Reads at most `limit` bytes from `reader` and analyzes them like [`Cookie::buffer()`](Cookie::buffer) on a blocking thread
+///
+/// This is the async counterpart of [`Cookie::detect_reader()`](Cookie::detect_reader).
+/// The `cookie` is shared with the blocking thread, so it needs to be in an [`Arc`].
+///
+/// Requires the `tokio` feature and a `tokio` runtime.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example(stream: impl tokio::io::AsyncRead + Unpin) -> Result<(), Box<dyn std::error::Error>> {
+/// use std::sync::Arc;
+///
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+/// let cookie = Arc::new(cookie);
+///
+/// let sniff = magic::tokio::detect_async_reader(&cookie, stream, 4 * 1024).await?;
+/// println!("{}", sniff.result());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If reading from `reader` failed, an [`AsyncError::Read`] will be returned.
+///
+/// If there was an `libmagic` internal error, an [`AsyncError::Analyze`] will be returned.
+///
+/// If the runtime cancelled the blocking analysis, an [`AsyncError::Cancelled`] will be returned.
+pub async fn detect_async_reader<R: AsyncRead + Unpin>(
+    cookie: &Arc<Cookie<Load>>,
+    reader: R,
+    limit: usize,
+) -> Result<Sniff, AsyncError> {
+    let mut buffer = Vec::new();
+    reader
+        .take(limit as u64)
+        .read_to_end(&mut buffer)
+        .await
+        .map_err(AsyncError::Read)?;
+
+    let cookie = Arc::clone(cookie);
+    spawn_analysis(move || {
+        let result = cookie.buffer(&buffer)?;
+        Ok(Sniff {
+            result,
+            bytes_read: buffer.len(),
+        })
+    })
+    .await
+}
+
+/// Loaded cookie whose analysis does not block the async executor
+///
+/// The functions dispatch to the blocking thread pool of `tokio`, which also keeps large files
+/// or slow file systems from stalling other tasks. The cookie is shared in an [`Arc`], so an `AsyncCookie` is cheap to clone into tasks.
+/// Analyses with the same cookie are still serialized, use several cookies for parallel analyses.
+///
+/// Requires the `tokio` feature and a `tokio` runtime.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+/// let cookie = magic::tokio::AsyncCookie::new(cookie);
+///
+/// let file_description = cookie.file("data/tests/rust-logo-128x128-blk.png").await?;
+/// let buffer_description = cookie.buffer(b"%PDF-\xE2\x80\xA6").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncCookie {
+    cookie: Arc<Cookie<Load>>,
+}
+
+impl AsyncCookie {
+    /// Creates a new async wrapper that owns the loaded `cookie`
+    pub fn new(cookie: Cookie<Load>) -> Self {
+        Self {
+            cookie: Arc::new(cookie),
+        }
+    }
+
+    /// Returns the shared cookie, e.g. for blocking code
+    pub fn cookie(&self) -> &Arc<Cookie<Load>> {
+        &self.cookie
+    }
+
+    /// Returns a textual description of the contents of the file `filename`
+    ///
+    /// See [`Cookie::file()`](Cookie::file).
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error, an [`AsyncError::Analyze`] will be returned.
+    ///
+    /// If the runtime cancelled the blocking analysis, an [`AsyncError::Cancelled`] will be returned.
+    #[doc(alias = "magic_file")]
+    pub async fn file<P: AsRef<Path>>(&self, filename: P) -> Result<String, AsyncError> {
+        let cookie = Arc::clone(&self.cookie);
+        let filename = filename.as_ref().to_path_buf();
+        spawn_analysis(move || cookie.file(filename)).await
+    }
+
+    /// Returns a textual description of the contents of the `buffer`
+    ///
+    /// The `buffer` is moved to the blocking thread, e.g. a `Vec<u8>` or a `&'static [u8]`.
+    /// See [`Cookie::buffer()`](Cookie::buffer).
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error, an [`AsyncError::Analyze`] will be returned.
+    ///
+    /// If the runtime cancelled the blocking analysis, an [`AsyncError::Cancelled`] will be returned.
+    #[doc(alias = "magic_buffer")]
+    pub async fn buffer<B>(&self, buffer: B) -> Result<String, AsyncError>
+    where
+        B: AsRef<[u8]> + Send + 'static,
+    {
+        let cookie = Arc::clone(&self.cookie);
+        spawn_analysis(move || cookie.buffer(buffer.as_ref())).await
+    }
+
+    /// Reads at most `limit` bytes from `reader` and analyzes them, see [`detect_async_reader()`]
+    ///
+    /// # Errors
+    ///
+    /// See [`detect_async_reader()`].
+    pub async fn detect_reader<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+        limit: usize,
+    ) -> Result<Sniff, AsyncError> {
+        detect_async_reader(&self.cookie, reader, limit).await
+    }
+}
+
+impl From<Cookie<Load>> for AsyncCookie {
+    fn from(cookie: Cookie<Load>) -> Self {
+        Self::new(cookie)
+    }
+}
+
+impl From<Arc<Cookie<Load>>> for AsyncCookie {
+    fn from(cookie: Arc<Cookie<Load>>) -> Self {
+        Self { cookie }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_async_reader, AsyncCookie};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+    use std::sync::Arc;
+
+    #[test]
+    fn async_reader() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = Arc::new(cookie.load(databases).unwrap());
+        let data = std::fs::read("data/tests/rust-logo-128x128-blk.png").unwrap();
+
+        let runtime = ::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let sniff = detect_async_reader(&cookie, data.as_slice(), 16)
+                .await
+                .unwrap();
+            assert_eq!(sniff.result(), "PNG image data");
+            assert_eq!(sniff.bytes_read(), 16);
+        });
+    }
+
+    #[test]
+    fn async_cookie() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = AsyncCookie::new(cookie.load(databases).unwrap());
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+        let runtime = ::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            assert_eq!(cookie.file(path).await.unwrap(), expected);
+            let data = std::fs::read(path).unwrap();
+            assert_eq!(cookie.buffer(data).await.unwrap(), expected);
+            assert!(cookie.clone().file("data/tests/\0.png").await.is_err());
+        });
+    }
+
+    static_assertions::assert_impl_all!(AsyncCookie: Send, Sync, Clone);
+}
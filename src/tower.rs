@@ -0,0 +1,287 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Detection as a service of the [`tower` crate](https://crates.io/crates/tower)
+//!
+//! [`DetectService`] answers a [`DetectRequest`] with an [`Analysis`], so detection composes with
+//! the timeouts, rate limits, retries and load shedding of existing `tower` stacks.
+//! It analyzes synchronously within [`Service::call()`], which suits threads that may block.
+//! On async executors, use `AsyncDetectService` with the `tokio` feature instead, which analyzes on the blocking thread pool of `tokio`.
+//!
+//! Requires the `tower` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use magic::tower::{DetectRequest, DetectService};
+//! use tower::Service;
+//!
+//! let cookie = magic::Cookie::open(Default::default())?.load(["data/tests/db-images-png"])?;
+//! let mut service = DetectService::new(cookie);
+//!
+//! let analysis = futures::executor::block_on(
+//!     service.call(DetectRequest::file("data/tests/rust-logo-128x128-blk.png")),
+//! )?;
+//! assert_eq!(analysis.mime_type(), "image/png");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::{ready, Ready};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use ::tower::Service;
+
+use crate::analysis::Analysis;
+use crate::cookie::Load;
+use crate::query::QueryError;
+use crate::Cookie;
+
+/// Content to analyze with a [`DetectService`] or `AsyncDetectService`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DetectRequest {
+    /// Analyze the contents of the file, see [`Cookie::analyze()`](Cookie::analyze)
+    File(PathBuf),
+    /// Analyze the buffer, see [`Cookie::analyze_buffer()`](Cookie::analyze_buffer)
+    Buffer(Vec<u8>),
+}
+
+impl DetectRequest {
+    /// Creates a request to analyze the contents of the file `filename`
+    pub fn file<P: Into<PathBuf>>(filename: P) -> Self {
+        Self::File(filename.into())
+    }
+
+    /// Creates a request to analyze the `buffer`
+    pub fn buffer<B: Into<Vec<u8>>>(buffer: B) -> Self {
+        Self::Buffer(buffer.into())
+    }
+
+    fn analyze(&self, cookie: &Cookie<Load>) -> Result<Analysis, QueryError> {
+        match self {
+            Self::File(filename) => cookie.analyze(filename),
+            Self::Buffer(buffer) => cookie.analyze_buffer(buffer),
+        }
+    }
+}
+
+impl From<PathBuf> for DetectRequest {
+    fn from(filename: PathBuf) -> Self {
+        Self::File(filename)
+    }
+}
+
+impl From<Vec<u8>> for DetectRequest {
+    fn from(buffer: Vec<u8>) -> Self {
+        Self::Buffer(buffer)
+    }
+}
+
+/// Service that analyzes synchronously, see mod [`tower`](crate::tower)
+///
+/// The service is always ready and the returned future is already complete.
+/// Clones share the same cookie, so their analyses are serialized.
+#[derive(Debug, Clone)]
+pub struct DetectService {
+    cookie: Arc<Cookie<Load>>,
+}
+
+impl DetectService {
+    /// Creates a new service that owns the loaded `cookie`
+    ///
+    /// The flags of the cookie do not matter, see [`Cookie::analyze()`](Cookie::analyze).
+    pub fn new(cookie: Cookie<Load>) -> Self {
+        Self {
+            cookie: Arc::new(cookie),
+        }
+    }
+
+    /// Returns the shared cookie
+    pub fn cookie(&self) -> &Arc<Cookie<Load>> {
+        &self.cookie
+    }
+}
+
+impl From<Cookie<Load>> for DetectService {
+    fn from(cookie: Cookie<Load>) -> Self {
+        Self::new(cookie)
+    }
+}
+
+impl From<Arc<Cookie<Load>>> for DetectService {
+    fn from(cookie: Arc<Cookie<Load>>) -> Self {
+        Self { cookie }
+    }
+}
+
+impl Service<DetectRequest> for DetectService {
+    type Response = Analysis;
+    type Error = QueryError;
+    type Future = Ready<Result<Analysis, QueryError>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: DetectRequest) -> Self::Future {
+        ready(request.analyze(&self.cookie))
+    }
+}
+
+/// Error of [`AsyncDetectService`]
+///
+/// Requires the `tower` and `tokio` features.
+#[cfg(feature = "tokio")]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum AsyncDetectError {
+    /// Analyzing the content failed
+    #[error("could not analyze content")]
+    Analyze(#[source] QueryError),
+    /// The blocking analysis was cancelled, e.g. because the runtime shuts down
+    #[error("blocking analysis was cancelled")]
+    Cancelled(#[source] ::tokio::task::JoinError),
+}
+
+/// Service that analyzes on the blocking thread pool of `tokio`, see mod [`tower`](crate::tower)
+///
+/// The service is always ready, limit concurrency with a `tower` layer if needed.
+/// Clones share the same cookie, so their analyses are serialized.
+///
+/// Requires the `tower` and `tokio` features and a `tokio` runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone)]
+pub struct AsyncDetectService {
+    cookie: Arc<Cookie<Load>>,
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncDetectService {
+    /// Creates a new service that owns the loaded `cookie`
+    ///
+    /// The flags of the cookie do not matter, see [`Cookie::analyze()`](Cookie::analyze).
+    pub fn new(cookie: Cookie<Load>) -> Self {
+        Self {
+            cookie: Arc::new(cookie),
+        }
+    }
+
+    /// Returns the shared cookie
+    pub fn cookie(&self) -> &Arc<Cookie<Load>> {
+        &self.cookie
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<Cookie<Load>> for AsyncDetectService {
+    fn from(cookie: Cookie<Load>) -> Self {
+        Self::new(cookie)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<Arc<Cookie<Load>>> for AsyncDetectService {
+    fn from(cookie: Arc<Cookie<Load>>) -> Self {
+        Self { cookie }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<DetectService> for AsyncDetectService {
+    fn from(service: DetectService) -> Self {
+        Self {
+            cookie: service.cookie,
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Service<DetectRequest> for AsyncDetectService {
+    type Response = Analysis;
+    type Error = AsyncDetectError;
+    #[allow(clippy::type_complexity)]
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Analysis, AsyncDetectError>> + Send>,
+    >;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: DetectRequest) -> Self::Future {
+        let cookie = Arc::clone(&self.cookie);
+        let analysis = ::tokio::task::spawn_blocking(move || request.analyze(&cookie));
+        Box::pin(async move {
+            match analysis.await {
+                Ok(result) => result.map_err(AsyncDetectError::Analyze),
+                Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+                Err(err) => Err(AsyncDetectError::Cancelled(err)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tower::ServiceExt;
+
+    use super::{DetectRequest, DetectService};
+    use crate::cookie::Flags;
+    use crate::Cookie;
+
+    fn service() -> DetectService {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        DetectService::new(cookie.load(["data/tests/db-images-png"]).unwrap())
+    }
+
+    #[test]
+    fn detect() {
+        let service = service();
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let analysis =
+            futures::executor::block_on(service.clone().oneshot(DetectRequest::file(path)))
+                .unwrap();
+        assert_eq!(analysis.mime_type(), "image/png");
+
+        let data = std::fs::read(path).unwrap();
+        let request = DetectRequest::from(data);
+        assert_eq!(
+            futures::executor::block_on(service.clone().oneshot(request)).unwrap(),
+            analysis
+        );
+
+        let request = DetectRequest::file("data/tests/doesntexist.png");
+        assert!(futures::executor::block_on(service.oneshot(request)).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn detect_async() {
+        use super::AsyncDetectService;
+
+        let service = AsyncDetectService::from(service());
+        let path = "data/tests/rust-logo-128x128-blk.png";
+
+        let runtime = ::tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let analysis = service
+                .clone()
+                .oneshot(DetectRequest::file(path))
+                .await
+                .unwrap();
+            assert_eq!(analysis.mime_type(), "image/png");
+
+            let request = DetectRequest::file("data/tests/doesntexist.png");
+            assert!(service.oneshot(request).await.is_err());
+        });
+    }
+
+    static_assertions::assert_impl_all!(DetectService: Send, Sync, Clone);
+}
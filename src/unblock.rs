@@ -0,0 +1,239 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis in async applications on any executor using the [`blocking` crate](https://crates.io/crates/blocking)
+//!
+//! `libmagic` only has blocking functions, which must not run on the threads of an async executor.
+//! [`AsyncCookie`] runs the analysis of files and buffers on the thread pool of the `blocking` crate instead,
+//! which works with `async-std`, `smol` or any other executor.
+//!
+//! With the `futures-util` feature, [`Cookie::stream_files()`](Cookie::stream_files) analyzes a stream of paths
+//! with a bounded number of analyses in flight.
+//!
+//! For `tokio` applications, see mod `tokio` with the `tokio` feature instead.
+//!
+//! Requires the `blocking` feature.
+
+use std::path::Path;
+#[cfg(feature = "futures-util")]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[cfg(feature = "futures-util")]
+use ::futures_util::stream::{Stream, StreamExt};
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+
+/// Loaded cookie whose analysis does not block the async executor
+///
+/// The functions dispatch to the thread pool of the `blocking` crate and need no specific runtime.
+/// The cookie is shared in an [`Arc`], so an `AsyncCookie` is cheap to clone into tasks.
+/// Analyses with the same cookie are still serialized, use several cookies for parallel analyses.
+///
+/// Requires the `blocking` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+/// let cookie = magic::unblock::AsyncCookie::new(cookie);
+///
+/// let file_description = cookie.file("data/tests/rust-logo-128x128-blk.png").await?;
+/// let buffer_description = cookie.buffer(b"%PDF-\xE2\x80\xA6").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsyncCookie {
+    cookie: Arc<Cookie<Load>>,
+}
+
+impl AsyncCookie {
+    /// Creates a new async wrapper that owns the loaded `cookie`
+    pub fn new(cookie: Cookie<Load>) -> Self {
+        Self {
+            cookie: Arc::new(cookie),
+        }
+    }
+
+    /// Returns the shared cookie, e.g. for blocking code
+    pub fn cookie(&self) -> &Arc<Cookie<Load>> {
+        &self.cookie
+    }
+
+    /// Returns a textual description of the contents of the file `filename`
+    ///
+    /// See [`Cookie::file()`](Cookie::file).
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+    #[doc(alias = "magic_file")]
+    pub async fn file<P: AsRef<Path>>(&self, filename: P) -> Result<String, Error> {
+        let cookie = Arc::clone(&self.cookie);
+        let filename = filename.as_ref().to_path_buf();
+        ::blocking::unblock(move || cookie.file(filename)).await
+    }
+
+    /// Returns a textual description of the contents of the `buffer`
+    ///
+    /// The `buffer` is moved to the blocking thread, e.g. a `Vec<u8>` or a `&'static [u8]`.
+    /// See [`Cookie::buffer()`](Cookie::buffer).
+    ///
+    /// # Errors
+    ///
+    /// If there was an `libmagic` internal error, a [`cookie::Error`](Error) will be returned.
+    #[doc(alias = "magic_buffer")]
+    pub async fn buffer<B>(&self, buffer: B) -> Result<String, Error>
+    where
+        B: AsRef<[u8]> + Send + 'static,
+    {
+        let cookie = Arc::clone(&self.cookie);
+        ::blocking::unblock(move || cookie.buffer(buffer.as_ref())).await
+    }
+
+    /// Returns a stream of the textual descriptions of the contents of all files `paths`
+    ///
+    /// See [`Cookie::stream_files()`](Cookie::stream_files).
+    ///
+    /// Requires the `futures-util` feature.
+    #[cfg(feature = "futures-util")]
+    #[doc(alias = "magic_file")]
+    pub fn stream_files<S>(
+        &self,
+        paths: S,
+        concurrency: usize,
+    ) -> impl Stream<Item = (PathBuf, Result<String, Error>)>
+    where
+        S: Stream<Item = PathBuf>,
+    {
+        Arc::clone(&self.cookie).stream_files(paths, concurrency)
+    }
+}
+
+#[cfg(feature = "futures-util")]
+impl Cookie<Load> {
+    /// Returns a stream of the textual descriptions of the contents of all files `paths`
+    ///
+    /// Each path is yielded along with its result, which is the same as that of [`file()`](Cookie::file).
+    /// The analyses run on the thread pool of the `blocking` crate, with at most `concurrency` of them in flight,
+    /// and the results are yielded in the order the analyses complete.
+    /// New paths are only taken from `paths` when the stream is polled, which applies backpressure to the source.
+    ///
+    /// Analyses with the same cookie are still serialized, so a `concurrency` above 1 only queues up work.
+    /// A `concurrency` of 0 is treated like 1.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling,
+    /// and the `blocking` and `futures-util` features.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// use futures_util::stream::{self, StreamExt};
+    /// use std::path::PathBuf;
+    /// use std::sync::Arc;
+    ///
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    /// let cookie = Arc::new(cookie);
+    ///
+    /// let paths = stream::iter(vec![PathBuf::from("data/tests/rust-logo-128x128-blk.png")]);
+    /// let results: Vec<_> = cookie.stream_files(paths, 4).collect().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[doc(alias = "magic_file")]
+    pub fn stream_files<S>(
+        self: Arc<Self>,
+        paths: S,
+        concurrency: usize,
+    ) -> impl Stream<Item = (PathBuf, Result<String, Error>)>
+    where
+        S: Stream<Item = PathBuf>,
+    {
+        paths
+            .map(move |path| {
+                let cookie = Arc::clone(&self);
+                ::blocking::unblock(move || {
+                    let result = cookie.file(&path);
+                    (path, result)
+                })
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+}
+
+impl From<Cookie<Load>> for AsyncCookie {
+    fn from(cookie: Cookie<Load>) -> Self {
+        Self::new(cookie)
+    }
+}
+
+impl From<Arc<Cookie<Load>>> for AsyncCookie {
+    fn from(cookie: Arc<Cookie<Load>>) -> Self {
+        Self { cookie }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncCookie;
+    use crate::cookie::{ErrorKind, Flags};
+    use crate::Cookie;
+
+    #[test]
+    fn async_cookie() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-images-png"];
+        let cookie = AsyncCookie::new(cookie.load(databases).unwrap());
+        let path = "data/tests/rust-logo-128x128-blk.png";
+        let expected = "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced";
+
+        futures::executor::block_on(async {
+            assert_eq!(cookie.file(path).await.unwrap(), expected);
+            let data = std::fs::read(path).unwrap();
+            assert_eq!(cookie.buffer(data).await.unwrap(), expected);
+            assert_eq!(
+                cookie.file("data/tests/\0.png").await.unwrap_err().kind(),
+                ErrorKind::InvalidPath
+            );
+        });
+    }
+
+    #[cfg(feature = "futures-util")]
+    #[test]
+    fn stream_files() {
+        use futures_util::stream::{self, StreamExt};
+        use std::path::PathBuf;
+
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = AsyncCookie::new(cookie.load(["data/tests/db-images-png"]).unwrap());
+
+        let paths = vec![
+            PathBuf::from("data/tests/rust-logo-128x128-blk.png"),
+            PathBuf::from("data/tests/doesntexist.png"),
+        ];
+        let mut results: Vec<_> = futures::executor::block_on(
+            cookie
+                .stream_files(stream::iter(paths.clone()), 2)
+                .collect(),
+        );
+        results.sort_by(|a: &(PathBuf, _), b| a.0.cmp(&b.0));
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, paths[1]);
+        assert_eq!(
+            results[0].1.as_ref().unwrap_err().kind(),
+            ErrorKind::Libmagic
+        );
+        assert_eq!(results[1].0, paths[0]);
+        assert_eq!(
+            results[1].1.as_ref().unwrap(),
+            "PNG image data, 128 x 128, 8-bit/color RGBA, non-interlaced"
+        );
+    }
+
+    static_assertions::assert_impl_all!(AsyncCookie: Send, Sync, Clone);
+}
@@ -0,0 +1,161 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Batched analysis of file prefixes read via io_uring
+//!
+//! Scanning many files with [`Cookie::file()`](Cookie::file) lets `libmagic` open, read and close every file itself
+//! with several blocking system calls each.
+//! [`Cookie::file_prefixes()`](Cookie::file_prefixes) instead submits the reads of many files at once
+//! via [io_uring(7)](https://man7.org/linux/man-pages/man7/io_uring.7.html) using the [`tokio-uring` crate](https://crates.io/crates/tokio-uring),
+//! and analyzes the prefixes like [`Cookie::buffer()`](Cookie::buffer), so `libmagic` never touches the file system.
+//!
+//! Only available on Linux 5.11 or later. Requires the `uring` feature.
+
+use std::path::{Path, PathBuf};
+
+use crate::cookie::Load;
+use crate::prefix::PrefixError;
+use crate::Cookie;
+use tokio_uring::buf::BoundedBuf;
+
+/// Maximum number of file reads that are in flight at once
+const BATCH_LEN: usize = 64;
+
+/// Each path along with its result
+type PrefixResults<P> = Vec<(P, Result<String, PrefixError>)>;
+
+/// Maximum number of bytes that are reserved for a single read, so a large `max_len` does not allocate up front
+const READ_CHUNK_LEN: usize = 64 * 1024;
+
+/// Reads at most `max_len` first bytes of the regular file `path`
+async fn read_prefix(path: PathBuf, max_len: usize) -> Result<Vec<u8>, PrefixError> {
+    // like `file_prefix_len()`, since reading special files could block or never end
+    let metadata = std::fs::metadata(&path).map_err(PrefixError::Read)?;
+    if !metadata.is_file() {
+        return Err(PrefixError::NotRegular);
+    }
+
+    let file = tokio_uring::fs::File::open(path)
+        .await
+        .map_err(PrefixError::Read)?;
+    let mut prefix = Vec::new();
+    // reads may be short, e.g. for network file systems
+    while prefix.len() < max_len {
+        let filled = prefix.len();
+        let end = max_len.min(filled.saturating_add(READ_CHUNK_LEN));
+        prefix.reserve_exact(end - filled);
+        let (result, slice) = file.read_at(prefix.slice(filled..end), filled as u64).await;
+        prefix = slice.into_inner();
+        if result.map_err(PrefixError::Read)? == 0 {
+            break;
+        }
+    }
+    file.close().await.map_err(PrefixError::Read)?;
+    Ok(prefix)
+}
+
+impl Cookie<Load> {
+    /// Returns the textual descriptions of at most `max_len` first bytes of all files `paths`, which are read via io_uring
+    ///
+    /// Each path is returned along with its result, which is the same as that of [`file_prefix_len()`](Cookie::file_prefix_len).
+    /// Up to 64 files are read at once, while the prefixes that were read already are analyzed one after another.
+    /// An error for one file does not stop the scan.
+    ///
+    /// This starts its own io_uring runtime and blocks until all files are analyzed,
+    /// so it must not be called from within an async runtime.
+    ///
+    /// Requires to [`load()`](Cookie::load) databases before calling.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// // open a new cookie with default flags and database
+    /// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+    ///
+    /// let paths = ["data/tests/rust-logo-128x128-blk.png", "Cargo.toml"];
+    /// for (path, result) in cookie.file_prefixes(paths, 4 * 1024)? {
+    ///     match result {
+    ///         Ok(description) => println!("{}: {}", path, description),
+    ///         Err(err) => eprintln!("{}: {}", path, err),
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// If the io_uring runtime can not be created, e.g. because the kernel does not support it,
+    /// an [`std::io::Error`] will be returned.
+    pub fn file_prefixes<I, P>(
+        &self,
+        paths: I,
+        max_len: usize,
+    ) -> Result<PrefixResults<P>, std::io::Error>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let runtime = tokio_uring::Runtime::new(&tokio_uring::builder())?;
+        let mut paths = paths.into_iter().peekable();
+        let mut results = Vec::new();
+
+        runtime.block_on(async {
+            while paths.peek().is_some() {
+                let mut reads = Vec::with_capacity(BATCH_LEN);
+                for path in paths.by_ref().take(BATCH_LEN) {
+                    let read =
+                        tokio_uring::spawn(read_prefix(path.as_ref().to_path_buf(), max_len));
+                    reads.push((path, read));
+                }
+
+                for (path, read) in reads {
+                    let prefix = match read.await {
+                        Ok(prefix) => prefix,
+                        Err(err) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+                        Err(err) => Err(PrefixError::Read(std::io::Error::new(
+                            std::io::ErrorKind::Other,
+                            err,
+                        ))),
+                    };
+                    let result = prefix
+                        .and_then(|prefix| self.buffer(&prefix).map_err(PrefixError::Analyze));
+                    results.push((path, result));
+                }
+            }
+        });
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cookie::Flags;
+    use crate::prefix::PrefixError;
+    use crate::Cookie;
+
+    #[test]
+    fn file_prefixes() {
+        let cookie = Cookie::open(Flags::ERROR).unwrap();
+        let cookie = cookie.load(["data/tests/db-images-png"]).unwrap();
+
+        let paths = [
+            "data/tests/rust-logo-128x128-blk.png",
+            "data/tests/doesntexist.png",
+            "data/tests",
+        ];
+        // io_uring is unavailable on older kernels and often disabled in containers
+        let results = match cookie.file_prefixes(paths, 16) {
+            Ok(results) => results,
+            Err(_) => return,
+        };
+        assert_eq!(results.len(), 3);
+
+        assert_eq!(results[0].0, paths[0]);
+        assert_eq!(results[0].1.as_ref().unwrap(), "PNG image data");
+        assert!(matches!(results[1].1, Err(PrefixError::Read(_))));
+        assert!(matches!(results[2].1, Err(PrefixError::NotRegular)));
+    }
+}
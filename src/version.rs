@@ -0,0 +1,247 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Functionality for [`Version`]
+
+/// Version of the `libmagic` C library
+///
+/// `libmagic` reports its version as a single integer, e.g. "5.41" as `541`.
+/// This type splits it into its major and minor components and orders versions accordingly.
+///
+/// # Examples
+///
+/// ```
+/// use magic::version::{Capability, Version};
+///
+/// let version = Version::libmagic();
+/// if version.supports(Capability::LoadBuffers) {
+///     // safe to use `Cookie::load_buffers()`
+/// }
+///
+/// assert!(Version::new(5, 40) < Version::new(5, 41));
+/// assert!(magic::version() >= Version::new(5, 4));
+/// ```
+///
+/// A version can be converted from the integer that `libmagic` reports, and to and from text:
+///
+/// ```
+/// use magic::version::Version;
+/// use std::convert::TryFrom;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// assert_eq!(Version::try_from(541)?, Version::new(5, 41));
+/// assert_eq!(Version::new(5, 4).to_string(), "5.04");
+/// assert_eq!("5.41".parse::<Version>()?, Version::new(5, 41));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    major: u32,
+    minor: u32,
+}
+
+impl Version {
+    /// Creates a new version from its `major` and `minor` components
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Returns the version of the linked `libmagic` C library as reported by itself
+    ///
+    /// See [`magic::libmagic_version()`](crate::libmagic_version) for the raw version.
+    #[doc(alias = "magic_version")]
+    pub fn libmagic() -> Self {
+        Self::from_raw(crate::libmagic_version())
+    }
+
+    /// Returns the major version, e.g. `5` for "5.41"
+    pub const fn major(&self) -> u32 {
+        self.major
+    }
+
+    /// Returns the minor version, e.g. `41` for "5.41"
+    pub const fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Returns whether `libmagic` with this version provides the `capability`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use magic::version::{Capability, Version};
+    ///
+    /// assert!(!Version::new(5, 19).supports(Capability::LoadBuffers));
+    /// assert!(Version::new(5, 20).supports(Capability::LoadBuffers));
+    /// ```
+    pub fn supports(&self, capability: Capability) -> bool {
+        *self >= capability.since()
+    }
+
+    pub(crate) fn from_raw(version: libc::c_int) -> Self {
+        // negative versions are nonsense, treat them as the oldest possible
+        let version = std::convert::TryFrom::try_from(version).unwrap_or(0u32);
+        Self::new(version / 100, version % 100)
+    }
+}
+
+impl std::fmt::Display for Version {
+    /// Formats the version like `libmagic` does, e.g. "5.04"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+
+impl std::convert::TryFrom<libc::c_int> for Version {
+    type Error = ParseVersionError;
+
+    /// Converts the integer reported by `libmagic`, e.g. `541` for "5.41"
+    fn try_from(value: libc::c_int) -> Result<Self, Self::Error> {
+        match u32::try_from(value) {
+            Ok(version) => Ok(Self::new(version / 100, version % 100)),
+            Err(_) => Err(ParseVersionError {}),
+        }
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = ParseVersionError;
+
+    /// Parses text like "5.41", the inverse of [`Display`](std::fmt::Display)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s.splitn(2, '.');
+        let major = components.next().and_then(|major| major.parse().ok());
+        let minor = components.next().and_then(|minor| minor.parse().ok());
+        match (major, minor) {
+            (Some(major), Some(minor)) if minor < 100 => Ok(Self::new(major, minor)),
+            _ => Err(ParseVersionError {}),
+        }
+    }
+}
+
+/// Invalid [`Version`]
+///
+/// This is returned from [`Version::try_from()`](Version::try_from) and [`Version::from_str()`](std::str::FromStr::from_str)
+#[derive(thiserror::Error, Debug)]
+#[error("invalid `libmagic` version")]
+pub struct ParseVersionError {}
+
+/// The `libmagic` in use is older than a `required` version
+///
+/// This is returned from [`magic::require_version()`](crate::require_version) and by APIs that need a newer `libmagic`,
+/// e.g. [`Cookie::load_buffers()`](crate::Cookie::load_buffers) or [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION),
+/// instead of misbehaving silently.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("`libmagic` {} is older than required version {}", .version, .required)]
+pub struct UnsupportedLibmagicVersion {
+    version: Version,
+    required: Version,
+}
+
+impl UnsupportedLibmagicVersion {
+    /// Returns the version of the `libmagic` in use
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Returns the required version
+    pub fn required(&self) -> Version {
+        self.required
+    }
+}
+
+/// Checks that the `libmagic` in use is at least the `required` version
+pub(crate) fn require(required: Version) -> Result<(), UnsupportedLibmagicVersion> {
+    let version = Version::libmagic();
+    if version < required {
+        Err(UnsupportedLibmagicVersion { version, required })
+    } else {
+        Ok(())
+    }
+}
+
+/// Functionality of `libmagic` that is not available in all versions
+///
+/// See [`Version::supports()`](Version::supports).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Loading compiled databases from memory, see [`Cookie::load_buffers()`](crate::Cookie::load_buffers)
+    ///
+    /// Available since `libmagic` 5.20.
+    #[doc(alias = "magic_load_buffers")]
+    LoadBuffers,
+    /// Getting and setting parameters
+    ///
+    /// Available since `libmagic` 5.21.
+    #[doc(alias = "magic_getparam")]
+    #[doc(alias = "magic_setparam")]
+    GetParam,
+    /// Returning filename extensions, see [`Flags::EXTENSION`](crate::cookie::Flags::EXTENSION)
+    ///
+    /// Available since `libmagic` 5.23.
+    #[doc(alias = "MAGIC_EXTENSION")]
+    Extension,
+    /// Getting the current flags
+    ///
+    /// Available since `libmagic` 5.29.
+    #[doc(alias = "magic_getflags")]
+    GetFlags,
+    /// Checking for JSON files, see [`Flags::NO_CHECK_JSON`](crate::cookie::Flags::NO_CHECK_JSON)
+    ///
+    /// Available since `libmagic` 5.35.
+    #[doc(alias = "MAGIC_NO_CHECK_JSON")]
+    JsonCheck,
+}
+
+impl Capability {
+    /// Returns the first `libmagic` version that provides this capability
+    pub const fn since(&self) -> Version {
+        match self {
+            Self::LoadBuffers => Version::new(5, 20),
+            Self::GetParam => Version::new(5, 21),
+            Self::Extension => Version::new(5, 23),
+            Self::GetFlags => Version::new(5, 29),
+            Self::JsonCheck => Version::new(5, 35),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capability, Version};
+
+    #[test]
+    fn from_raw() {
+        assert_eq!(Version::from_raw(541), Version::new(5, 41));
+        assert_eq!(Version::from_raw(504), Version::new(5, 4));
+        assert_eq!(Version::from_raw(-1), Version::new(0, 0));
+    }
+
+    #[test]
+    fn conversions() {
+        use std::convert::TryFrom;
+
+        assert_eq!(Version::try_from(544).unwrap(), Version::new(5, 44));
+        assert!(Version::try_from(-1).is_err());
+
+        assert_eq!(Version::new(5, 44).to_string(), "5.44");
+        assert_eq!(Version::new(5, 4).to_string(), "5.04");
+        assert_eq!("5.04".parse::<Version>().unwrap(), Version::new(5, 4));
+        assert!("5".parse::<Version>().is_err());
+        assert!("5.100".parse::<Version>().is_err());
+        assert!("five.44".parse::<Version>().is_err());
+
+        let version = Version::libmagic();
+        assert_eq!(version.to_string().parse::<Version>().unwrap(), version);
+    }
+
+    #[test]
+    fn supports() {
+        let version = Version::new(5, 23);
+        assert!(version.supports(Capability::LoadBuffers));
+        assert!(version.supports(Capability::Extension));
+        assert!(!version.supports(Capability::JsonCheck));
+    }
+}
@@ -0,0 +1,217 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Analysis of files as they are created or modified
+//!
+//! [`Watcher`] monitors directories via the [`notify` crate](https://crates.io/crates/notify),
+//! e.g. for hot folders of ingest pipelines.
+//!
+//! Filesystem events are delivered on a background thread,
+//! but they are analyzed with the given [`Cookie`] on the thread that iterates [`Watcher::events()`](Watcher::events).
+//!
+//! Requires the `watch` feature.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+//!
+//! let mut watcher = magic::watch::Watcher::new()?;
+//! watcher.watch("incoming", true)?;
+//! for event in watcher.events(&cookie) {
+//!     let event = event?;
+//!     println!("{}: {:?}", event.path().display(), event.result());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::cookie::{Error, Load};
+use crate::Cookie;
+use notify::Watcher as _;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+
+/// Analysis result of a created or modified file
+///
+/// Returned from [`Events`]
+#[derive(Debug)]
+pub struct Event {
+    path: PathBuf,
+    result: Result<String, Error>,
+}
+
+impl Event {
+    /// Returns the path of the created or modified file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the textual result of the analysis of the file
+    pub fn result(&self) -> &Result<String, Error> {
+        &self.result
+    }
+}
+
+/// Error of [`Watcher`]
+#[derive(thiserror::Error, Debug)]
+#[error("could not watch files")]
+pub struct WatchError {
+    #[source]
+    source: notify::Error,
+}
+
+/// Monitors directories and analyzes created or modified files
+pub struct Watcher {
+    watcher: notify::RecommendedWatcher,
+    receiver: Receiver<notify::Result<notify::Event>>,
+    pending: VecDeque<PathBuf>,
+}
+
+impl std::fmt::Debug for Watcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watcher")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Watcher {
+    /// Creates a new watcher with the recommended backend of the platform, e.g. inotify on Linux
+    ///
+    /// # Errors
+    ///
+    /// If the backend could not be initialized, a [`WatchError`] will be returned.
+    pub fn new() -> Result<Self, WatchError> {
+        let (sender, receiver) = channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            // the receiver is only gone once the `Watcher` is dropped
+            let _ = sender.send(event);
+        })
+        .map_err(|source| WatchError { source })?;
+
+        Ok(Self {
+            watcher,
+            receiver,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Starts monitoring `path`, including its subdirectories if `recursive`
+    ///
+    /// # Errors
+    ///
+    /// If `path` could not be monitored, a [`WatchError`] will be returned.
+    pub fn watch<P: AsRef<Path>>(&mut self, path: P, recursive: bool) -> Result<(), WatchError> {
+        let mode = if recursive {
+            notify::RecursiveMode::Recursive
+        } else {
+            notify::RecursiveMode::NonRecursive
+        };
+        self.watcher
+            .watch(path.as_ref(), mode)
+            .map_err(|source| WatchError { source })
+    }
+
+    /// Stops monitoring `path`
+    ///
+    /// # Errors
+    ///
+    /// If `path` was not monitored, a [`WatchError`] will be returned.
+    pub fn unwatch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), WatchError> {
+        self.watcher
+            .unwatch(path.as_ref())
+            .map_err(|source| WatchError { source })
+    }
+
+    /// Returns a blocking iterator that analyzes files with `cookie` as they are created or modified
+    ///
+    /// Directories and files which vanished before they could be analyzed are skipped.
+    /// Note that a file might be analyzed several times while it is being written,
+    /// so clients should rather move complete files into the monitored directories.
+    pub fn events<'w>(&'w mut self, cookie: &'w Cookie<Load>) -> Events<'w> {
+        Events {
+            watcher: self,
+            cookie,
+        }
+    }
+}
+
+/// Blocking iterator over analyzed files
+///
+/// Returned from [`Watcher::events()`](Watcher::events)
+#[derive(Debug)]
+pub struct Events<'w> {
+    watcher: &'w mut Watcher,
+    cookie: &'w Cookie<Load>,
+}
+
+impl Iterator for Events<'_> {
+    type Item = Result<Event, WatchError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            while let Some(path) = self.watcher.pending.pop_front() {
+                if path.is_file() {
+                    let result = self.cookie.file(&path);
+                    return Some(Ok(Event { path, result }));
+                }
+            }
+
+            let event = match self.watcher.receiver.recv().ok()? {
+                Ok(event) => event,
+                Err(source) => return Some(Err(WatchError { source })),
+            };
+            let relevant = match event.kind {
+                notify::EventKind::Create(_) => true,
+                notify::EventKind::Modify(notify::event::ModifyKind::Metadata(_)) => false,
+                notify::EventKind::Modify(_) => true,
+                _ => false,
+            };
+            if relevant {
+                for path in event.paths {
+                    if !self.watcher.pending.contains(&path) {
+                        self.watcher.pending.push_back(path);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Watcher;
+
+    #[test]
+    fn watch_created() {
+        use crate::cookie::Flags;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let directory = std::env::temp_dir().join("rust-magic-test-watch_created");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.watch(&directory, false).unwrap();
+
+        // move the complete file into place, like hot folder clients should
+        let staged = std::env::temp_dir().join("rust-magic-test-watch_created.py");
+        std::fs::write(&staged, "#!/usr/bin/env python\nprint('Hello, world!')").unwrap();
+        let path = directory.join("hello.py");
+        std::fs::rename(&staged, &path).unwrap();
+
+        let event = watcher.events(&cookie).next().unwrap().unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(event.path(), path);
+        assert_eq!(
+            event.result().as_ref().unwrap(),
+            "Python script, ASCII text executable"
+        );
+    }
+}
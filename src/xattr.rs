@@ -0,0 +1,203 @@
+// SPDX-FileCopyrightText: © The `magic` Rust crate authors
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Reconciliation of MIME types declared in extended file attributes
+//!
+//! Desktop tools and file managers on Linux record the media type of a file in the
+//! [`user.mime_type`](https://www.freedesktop.org/wiki/CommonExtendedAttributes/) extended attribute.
+//! [`reconcile()`] compares it with the media type detected by `libmagic` and can update it,
+//! e.g. for file manager or indexing integrations.
+//!
+//! Only available on Linux. Requires the `xattr` feature.
+
+use crate::cookie::{Error, Flags, Load, SetFlagsError};
+use crate::normalize::{normalize, Normalization};
+use crate::Cookie;
+use std::path::Path;
+
+/// Name of the extended attribute with the declared media type
+pub const MIME_TYPE_ATTRIBUTE: &str = "user.mime_type";
+
+/// When [`reconcile()`] writes the detected media type to the [`MIME_TYPE_ATTRIBUTE`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Update {
+    /// Only compare, never write
+    Never,
+    /// Write if the file has no declared media type
+    IfMissing,
+    /// Write if the file has no declared media type or it mismatches
+    Always,
+}
+
+/// Comparison of the declared and detected media types within a [`Report`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Status {
+    /// The file has no declared media type
+    Missing,
+    /// The declared media type is the detected one
+    Match,
+    /// The declared media type differs from the detected one
+    Mismatch,
+}
+
+/// Result of [`reconcile()`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    declared: Option<String>,
+    detected: String,
+    updated: bool,
+}
+
+impl Report {
+    /// Returns the media type declared in the [`MIME_TYPE_ATTRIBUTE`] before reconciliation, if any
+    ///
+    /// Invalid UTF-8 is replaced lossily.
+    pub fn declared(&self) -> Option<&str> {
+        self.declared.as_deref()
+    }
+
+    /// Returns the media type detected by `libmagic`, e.g. `image/png`
+    pub fn detected(&self) -> &str {
+        &self.detected
+    }
+
+    /// Returns how the declared media type compares to the detected one
+    ///
+    /// Media types are compared case-insensitively and without parameters.
+    pub fn status(&self) -> Status {
+        match &self.declared {
+            None => Status::Missing,
+            Some(declared) if normalize(declared, Normalization::MimeType) == self.detected => {
+                Status::Match
+            }
+            Some(_) => Status::Mismatch,
+        }
+    }
+
+    /// Returns whether the detected media type was written to the [`MIME_TYPE_ATTRIBUTE`]
+    pub fn updated(&self) -> bool {
+        self.updated
+    }
+}
+
+/// Error within [`reconcile()`]
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum XattrError {
+    /// Reading the extended attribute failed, e.g. since the file system does not support them
+    #[error("could not read extended attribute")]
+    Read(#[source] std::io::Error),
+    /// Writing the extended attribute failed
+    #[error("could not write extended attribute")]
+    Write(#[source] std::io::Error),
+    /// Setting the flags for the analysis failed
+    #[error("could not set flags to analyze file")]
+    SetFlags(#[source] SetFlagsError),
+    /// Analyzing the file failed
+    #[error("could not analyze file")]
+    Analyze(#[source] Error),
+}
+
+/// Compares the declared media type of the file at `path` with the detected one and updates it as per `update`
+///
+/// The analysis uses [`Flags::MIME_TYPE`] and [`Flags::ERROR`] instead of the flags of the cookie,
+/// see [`with_flags()`](Cookie::with_flags).
+///
+/// # Examples
+///
+/// ```no_run
+/// use magic::xattr::{reconcile, Status, Update};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let cookie = magic::Cookie::open(Default::default())?.load(magic::cookie::DatabasePaths::default())?;
+///
+/// let report = reconcile(&cookie, "Downloads/cat.png", Update::Never)?;
+/// if report.status() == Status::Mismatch {
+///     println!("declared as {:?} but is {}", report.declared(), report.detected());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// If the extended attribute can not be read or written, a [`XattrError::Read`] or [`XattrError::Write`] will be returned.
+///
+/// If the flags can not be set, a [`XattrError::SetFlags`] will be returned.
+///
+/// If there was an `libmagic` internal error, a [`XattrError::Analyze`] will be returned.
+pub fn reconcile<P: AsRef<Path>>(
+    cookie: &Cookie<Load>,
+    path: P,
+    update: Update,
+) -> Result<Report, XattrError> {
+    let path = path.as_ref();
+
+    let declared = ::xattr::get(path, MIME_TYPE_ATTRIBUTE)
+        .map_err(XattrError::Read)?
+        .map(|declared| String::from_utf8_lossy(&declared).into_owned());
+
+    let detected = cookie
+        .with_flags(Flags::ERROR | Flags::MIME_TYPE)
+        .map_err(XattrError::SetFlags)?
+        .file(path)
+        .map_err(XattrError::Analyze)?;
+    let detected = normalize(&detected, Normalization::MimeType);
+
+    let mut report = Report {
+        declared,
+        detected,
+        updated: false,
+    };
+
+    let write = match (update, report.status()) {
+        (Update::Never, _) | (_, Status::Match) => false,
+        (Update::IfMissing, status) => status == Status::Missing,
+        (Update::Always, _) => true,
+    };
+    if write {
+        ::xattr::set(path, MIME_TYPE_ATTRIBUTE, report.detected.as_bytes())
+            .map_err(XattrError::Write)?;
+        report.updated = true;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reconcile, Status, Update, MIME_TYPE_ATTRIBUTE};
+
+    #[test]
+    fn reconcile_update() {
+        use crate::cookie::Flags;
+
+        let cookie = crate::Cookie::open(Flags::ERROR).unwrap();
+        let databases = ["data/tests/db-python"];
+        let cookie = cookie.load(databases).unwrap();
+
+        let path = std::env::temp_dir().join("rust-magic-test-reconcile_update.txt");
+        std::fs::write(&path, "#!/usr/bin/env python\nprint('Hello, world!')").unwrap();
+
+        let report = reconcile(&cookie, &path, Update::Never).unwrap();
+        assert_eq!(report.status(), Status::Missing);
+        assert_eq!(report.detected(), "text/x-python");
+        assert!(!report.updated());
+
+        ::xattr::set(&path, MIME_TYPE_ATTRIBUTE, b"text/plain").unwrap();
+        let report = reconcile(&cookie, &path, Update::IfMissing).unwrap();
+        assert_eq!(report.status(), Status::Mismatch);
+        assert!(!report.updated());
+
+        let report = reconcile(&cookie, &path, Update::Always).unwrap();
+        assert_eq!(report.declared(), Some("text/plain"));
+        assert!(report.updated());
+
+        let report = reconcile(&cookie, &path, Update::Always).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(report.status(), Status::Match);
+        assert!(!report.updated());
+
+        assert_eq!(cookie.flags(), Flags::ERROR);
+    }
+}